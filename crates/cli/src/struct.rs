@@ -54,8 +54,14 @@ fn print_imghdu_struct(hdu: fits::HDU<Image>) -> Result<(), Box<dyn Error>> {
 }
 
 fn print_bintablehdu_struct(hdu: fits::HDU<BinTable>) -> Result<(), Box<dyn Error>> {
-    print_hdu_type("BINTABLE");
-    print_bintable_header(hdu.get_header().get_xtension());
+    let header = hdu.get_header();
+    if header.get_xtension().get_z_image().is_some() {
+        print_hdu_type("COMPRESSED IMAGE");
+        print_img_header(header.image_header()?.get_xtension());
+    } else {
+        print_hdu_type("BINTABLE");
+        print_bintable_header(header.get_xtension());
+    }
     print_data_struct(&hdu);
     Ok(())
 }
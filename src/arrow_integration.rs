@@ -0,0 +1,907 @@
+//! Export of binary tables into Apache Arrow record batches
+//!
+//! The TFORMn of each column gives its Arrow type:
+//!
+//! | TFORM | Arrow type |
+//! |-------|------------|
+//! | L | `Boolean` |
+//! | X | `Boolean` |
+//! | B | `UInt8` |
+//! | I | `Int16` |
+//! | J | `Int32` |
+//! | K | `Int64` |
+//! | A | `Utf8` |
+//! | E | `Float32` |
+//! | D | `Float64` |
+//! | C | `FixedSizeList<Float32, 2>` (real, imaginary) |
+//! | M | `FixedSizeList<Float64, 2>` (real, imaginary) |
+//!
+//! A repeat count greater than 1 wraps the type into a `FixedSizeList` except for
+//! character strings. Variable length arrays (P and Q) are given as `List` and `LargeList`.
+//! Integer values equal to the TNULLn of their column are null. The TTYPEn and TUNITn
+//! of a column are stored in the metadata of its field.
+//!
+//! The TSCALn and TZEROn of a column give its physical values. The standard offsets
+//! of the integers of the other signedness change their type:
+//!
+//! | TFORM | TZERO | Arrow type |
+//! |-------|-------|------------|
+//! | B | -128 | `Int8` |
+//! | I | 32768 | `UInt16` |
+//! | J | 2147483648 | `UInt32` |
+//! | K | 9223372036854775808 | `UInt64` |
+//!
+//! Any other scaling of integers gives `Float64` values. Floating point values are
+//! scaled in place.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::io::{Read, Seek};
+use std::sync::Arc;
+
+use arrow_array::types::{
+    Float32Type, Float64Type, Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type,
+    UInt64Type, UInt8Type,
+};
+use arrow_array::{
+    ArrayRef, ArrowPrimitiveType, BooleanArray, FixedSizeListArray, LargeListArray, ListArray,
+    PrimitiveArray, RecordBatch, StringArray,
+};
+use arrow_buffer::{NullBuffer, OffsetBuffer, ScalarBuffer};
+use arrow_schema::{ArrowError, DataType, Field, FieldRef, Schema, SchemaRef};
+
+use crate::error::Error;
+use crate::hdu::data::bintable::data::TableData;
+use crate::hdu::data::bintable::row::TableRowData;
+use crate::hdu::data::bintable::DataValue;
+use crate::hdu::data::iter::TryNext;
+use crate::hdu::header::extension::bintable::{BinTable, TFormType, VariableArrayTy};
+use crate::hdu::header::Header;
+
+impl From<ArrowError> for Error {
+    fn from(error: ArrowError) -> Self {
+        Error::DynamicError(error.to_string())
+    }
+}
+
+/// Type of the values stored in a column
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Boolean,
+    Bit,
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Int64,
+    UInt64,
+    Utf8,
+    Float32,
+    Float64,
+    Complex32,
+    Complex64,
+}
+
+impl Kind {
+    fn from_variable_array_ty(ty: VariableArrayTy) -> Self {
+        match ty {
+            VariableArrayTy::L => Kind::Boolean,
+            VariableArrayTy::X => Kind::Bit,
+            VariableArrayTy::B => Kind::UInt8,
+            VariableArrayTy::I => Kind::Int16,
+            VariableArrayTy::J => Kind::Int32,
+            VariableArrayTy::K => Kind::Int64,
+            VariableArrayTy::A => Kind::Utf8,
+            VariableArrayTy::E => Kind::Float32,
+            VariableArrayTy::D => Kind::Float64,
+            VariableArrayTy::C => Kind::Complex32,
+            VariableArrayTy::M => Kind::Complex64,
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self, Kind::UInt8 | Kind::Int16 | Kind::Int32 | Kind::Int64)
+    }
+
+    /// Get the kind of the physical values given the TSCALn and TZEROn of the column
+    /// with the scale and the offset that remain to be applied to the stored values
+    fn scaled(self, tscal: f64, tzero: f64) -> (Kind, Option<(f64, f64)>) {
+        if tscal == 1.0 && tzero == 0.0 {
+            return (self, None);
+        }
+
+        match self {
+            // Standard offsets of the integers of the other signedness
+            Kind::UInt8 if tscal == 1.0 && tzero == -128.0 => (Kind::Int8, None),
+            Kind::Int16 if tscal == 1.0 && tzero == 32768.0 => (Kind::UInt16, None),
+            Kind::Int32 if tscal == 1.0 && tzero == 2147483648.0 => (Kind::UInt32, None),
+            Kind::Int64 if tscal == 1.0 && tzero == 9223372036854775808.0 => (Kind::UInt64, None),
+            Kind::UInt8 | Kind::Int16 | Kind::Int32 | Kind::Int64 => {
+                (Kind::Float64, Some((tscal, tzero)))
+            }
+            Kind::Float32 | Kind::Float64 => (self, Some((tscal, tzero))),
+            // TSCALn and TZEROn do not apply to the other columns
+            _ => (self, None),
+        }
+    }
+
+    fn data_type(&self) -> DataType {
+        match self {
+            Kind::Boolean | Kind::Bit => DataType::Boolean,
+            Kind::Int8 => DataType::Int8,
+            Kind::UInt8 => DataType::UInt8,
+            Kind::Int16 => DataType::Int16,
+            Kind::UInt16 => DataType::UInt16,
+            Kind::Int32 => DataType::Int32,
+            Kind::UInt32 => DataType::UInt32,
+            Kind::Int64 => DataType::Int64,
+            Kind::UInt64 => DataType::UInt64,
+            Kind::Utf8 => DataType::Utf8,
+            Kind::Float32 => DataType::Float32,
+            Kind::Float64 => DataType::Float64,
+            Kind::Complex32 => DataType::FixedSizeList(complex_field(DataType::Float32), 2),
+            Kind::Complex64 => DataType::FixedSizeList(complex_field(DataType::Float64), 2),
+        }
+    }
+}
+
+fn complex_field(data_type: DataType) -> FieldRef {
+    Arc::new(Field::new("item", data_type, false))
+}
+
+/// How the values of a cell are grouped
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Layout {
+    /// One value per cell
+    Scalar,
+    /// A repeat count greater than 1
+    FixedSize(usize),
+    /// A 32-bit array descriptor
+    List,
+    /// A 64-bit array descriptor
+    LargeList,
+}
+
+fn kind_and_layout(tform: &TFormType) -> (Kind, Layout) {
+    let (kind, repeat_count) = match *tform {
+        TFormType::L { repeat_count } => (Kind::Boolean, repeat_count),
+        TFormType::X { repeat_count } => (Kind::Bit, repeat_count),
+        TFormType::B { repeat_count } => (Kind::UInt8, repeat_count),
+        TFormType::I { repeat_count } => (Kind::Int16, repeat_count),
+        TFormType::J { repeat_count } => (Kind::Int32, repeat_count),
+        TFormType::K { repeat_count } => (Kind::Int64, repeat_count),
+        // A string is given by all the characters of the cell
+        TFormType::A { .. } => (Kind::Utf8, 1),
+        TFormType::E { repeat_count } => (Kind::Float32, repeat_count),
+        TFormType::D { repeat_count } => (Kind::Float64, repeat_count),
+        TFormType::C { repeat_count } => (Kind::Complex32, repeat_count),
+        TFormType::M { repeat_count } => (Kind::Complex64, repeat_count),
+        TFormType::P { ty, .. } | TFormType::Q { ty, .. } => {
+            let kind = Kind::from_variable_array_ty(ty);
+            let layout = match (kind, tform) {
+                (Kind::Utf8, _) => Layout::Scalar,
+                (_, TFormType::P { .. }) => Layout::List,
+                _ => Layout::LargeList,
+            };
+
+            return (kind, layout);
+        }
+    };
+
+    let layout = if repeat_count == 1 {
+        Layout::Scalar
+    } else {
+        Layout::FixedSize(repeat_count)
+    };
+
+    (kind, layout)
+}
+
+/// Values of a column accumulated for the current batch
+#[derive(Debug)]
+enum Values {
+    Boolean(Vec<bool>),
+    Int8(Vec<i8>),
+    UInt8(Vec<u8>),
+    Int16(Vec<i16>),
+    UInt16(Vec<u16>),
+    Int32(Vec<i32>),
+    UInt32(Vec<u32>),
+    Int64(Vec<i64>),
+    UInt64(Vec<u64>),
+    Utf8(Vec<String>),
+    Float32(Vec<f32>),
+    Float64(Vec<f64>),
+}
+
+impl Values {
+    fn new(kind: Kind) -> Self {
+        match kind {
+            Kind::Boolean | Kind::Bit => Values::Boolean(vec![]),
+            Kind::Int8 => Values::Int8(vec![]),
+            Kind::UInt8 => Values::UInt8(vec![]),
+            Kind::Int16 => Values::Int16(vec![]),
+            Kind::UInt16 => Values::UInt16(vec![]),
+            Kind::Int32 => Values::Int32(vec![]),
+            Kind::UInt32 => Values::UInt32(vec![]),
+            Kind::Int64 => Values::Int64(vec![]),
+            Kind::UInt64 => Values::UInt64(vec![]),
+            Kind::Utf8 => Values::Utf8(vec![]),
+            Kind::Float32 | Kind::Complex32 => Values::Float32(vec![]),
+            Kind::Float64 | Kind::Complex64 => Values::Float64(vec![]),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Values::Boolean(v) => v.len(),
+            Values::Int8(v) => v.len(),
+            Values::UInt8(v) => v.len(),
+            Values::Int16(v) => v.len(),
+            Values::UInt16(v) => v.len(),
+            Values::Int32(v) => v.len(),
+            Values::UInt32(v) => v.len(),
+            Values::Int64(v) => v.len(),
+            Values::UInt64(v) => v.len(),
+            Values::Utf8(v) => v.len(),
+            Values::Float32(v) => v.len(),
+            Values::Float64(v) => v.len(),
+        }
+    }
+}
+
+/// Get the integer stored in the table
+fn stored_integer(value: &DataValue) -> Option<i64> {
+    match value {
+        DataValue::UnsignedByte { value, .. } => Some(*value as i64),
+        DataValue::Short { value, .. } => Some(*value as i64),
+        DataValue::Integer { value, .. } => Some(*value as i64),
+        DataValue::Long { value, .. } => Some(*value),
+        _ => None,
+    }
+}
+
+fn primitive_array<T: ArrowPrimitiveType>(
+    values: Vec<T::Native>,
+    nulls: Option<NullBuffer>,
+) -> Result<ArrayRef, Error> {
+    Ok(Arc::new(PrimitiveArray::<T>::try_new(
+        values.into(),
+        nulls,
+    )?))
+}
+
+fn value_mismatch() -> Error {
+    Error::StaticError("A binary table value does not match the TFORM of its column")
+}
+
+#[derive(Debug)]
+struct ColumnBuilder {
+    /// Index of the column in the binary table
+    col_idx: usize,
+    kind: Kind,
+    layout: Layout,
+    /// The field of the values, i.e. of the list elements for list layouts
+    item_field: FieldRef,
+    /// TNULLn value for integer columns
+    tnull: Option<i64>,
+    /// TSCALn and TZEROn to apply to the stored values
+    scaling: Option<(f64, f64)>,
+
+    values: Values,
+    /// Whether the values differ from the TNULLn of the column
+    valid: Vec<bool>,
+    /// Characters of the current cell for string columns
+    chars: String,
+    /// Number of values before the current cell
+    cell_start: usize,
+    /// Offsets of the cells in the values for variable length arrays
+    offsets: Vec<usize>,
+}
+
+impl ColumnBuilder {
+    fn new(header: &Header<BinTable>, col_idx: usize) -> (Self, Field) {
+        let ctx = header.get_xtension();
+        let (kind, layout) = kind_and_layout(&ctx.tforms[col_idx]);
+
+        let tnull = if kind.is_integer() {
+            header
+                .get_parsed::<i64>(&format!("TNULL{}", col_idx + 1))
+                .ok()
+        } else {
+            None
+        };
+
+        let tscal = header
+            .get_parsed::<f64>(&format!("TSCAL{}", col_idx + 1))
+            .unwrap_or(1.0);
+        let tzero = header
+            .get_parsed::<f64>(&format!("TZERO{}", col_idx + 1))
+            .unwrap_or(0.0);
+        let (kind, scaling) = kind.scaled(tscal, tzero);
+
+        let ttype = ctx.ttypes[col_idx].as_ref().map(|ttype| ttype.trim_end());
+        let name = ttype.map_or_else(|| format!("COL{}", col_idx + 1), str::to_owned);
+
+        let item_field = Arc::new(Field::new("item", kind.data_type(), tnull.is_some()));
+        let field = match layout {
+            Layout::Scalar => Field::new(name, kind.data_type(), tnull.is_some()),
+            Layout::FixedSize(n) => Field::new(
+                name,
+                DataType::FixedSizeList(item_field.clone(), n as i32),
+                false,
+            ),
+            Layout::List => Field::new(name, DataType::List(item_field.clone()), false),
+            Layout::LargeList => Field::new(name, DataType::LargeList(item_field.clone()), false),
+        };
+
+        let mut metadata = HashMap::new();
+        if let Some(ttype) = ttype {
+            metadata.insert("TTYPE".to_owned(), ttype.to_owned());
+        }
+        if let Ok(tunit) = header.get_parsed::<String>(&format!("TUNIT{}", col_idx + 1)) {
+            metadata.insert("TUNIT".to_owned(), tunit.trim_end().to_owned());
+        }
+        let field = field.with_metadata(metadata);
+
+        let builder = Self {
+            col_idx,
+            kind,
+            layout,
+            item_field,
+            tnull,
+            scaling,
+            values: Values::new(kind),
+            valid: vec![],
+            chars: String::new(),
+            cell_start: 0,
+            offsets: vec![0],
+        };
+
+        (builder, field)
+    }
+
+    fn push(&mut self, value: &DataValue) -> Result<(), Error> {
+        let stored = stored_integer(value);
+        // TNULLn is compared to the stored integers
+        if self.tnull.is_some() {
+            self.valid.push(stored != self.tnull);
+        }
+
+        if let Some((scale, zero)) = self.scaling {
+            let physical = |stored: f64| zero + scale * stored;
+            match (&mut self.values, value) {
+                (Values::Float32(v), DataValue::Float { value, .. }) => {
+                    v.push(physical(*value as f64) as f32)
+                }
+                (Values::Float64(v), DataValue::Double { value, .. }) => v.push(physical(*value)),
+                (Values::Float64(v), _) => {
+                    v.push(physical(stored.ok_or_else(value_mismatch)? as f64))
+                }
+                _ => return Err(value_mismatch()),
+            }
+
+            return Ok(());
+        }
+
+        match (&mut self.values, value) {
+            (Values::Utf8(_), DataValue::Character { value, .. }) => self.chars.push(*value),
+            (Values::Boolean(v), DataValue::Logical { value, .. }) => v.push(*value),
+            // Bits are stored from the most significant one. The heap gives one value per bit
+            // of the variable length arrays, the main table one per byte of the fixed ones
+            (Values::Boolean(v), DataValue::Bit { byte, bit_idx, .. }) => match self.layout {
+                Layout::List | Layout::LargeList => v.push((byte >> (7 - bit_idx)) & 1 == 1),
+                Layout::Scalar | Layout::FixedSize(_) => {
+                    v.extend((0..8).rev().map(|i| (byte >> i) & 1 == 1))
+                }
+            },
+            (Values::UInt8(v), DataValue::UnsignedByte { value, .. }) => v.push(*value),
+            (Values::Int16(v), DataValue::Short { value, .. }) => v.push(*value),
+            (Values::Int32(v), DataValue::Integer { value, .. }) => v.push(*value),
+            (Values::Int64(v), DataValue::Long { value, .. }) => v.push(*value),
+            // The standard offsets flip the sign bit of the stored integers
+            (Values::Int8(v), DataValue::UnsignedByte { value, .. }) => {
+                v.push((*value ^ 0x80) as i8)
+            }
+            (Values::UInt16(v), DataValue::Short { value, .. }) => v.push(*value as u16 ^ 0x8000),
+            (Values::UInt32(v), DataValue::Integer { value, .. }) => {
+                v.push(*value as u32 ^ 0x8000_0000)
+            }
+            (Values::UInt64(v), DataValue::Long { value, .. }) => {
+                v.push(*value as u64 ^ 0x8000_0000_0000_0000)
+            }
+            (Values::Float32(v), DataValue::Float { value, .. }) => v.push(*value),
+            (Values::Float64(v), DataValue::Double { value, .. }) => v.push(*value),
+            (Values::Float32(v), DataValue::ComplexFloat { real, imag, .. }) => {
+                v.extend([*real, *imag])
+            }
+            (Values::Float64(v), DataValue::ComplexDouble { real, imag, .. }) => {
+                v.extend([*real, *imag])
+            }
+            _ => return Err(value_mismatch()),
+        }
+
+        Ok(())
+    }
+
+    /// Close the cell of the current row
+    fn end_cell(&mut self) {
+        match &mut self.values {
+            Values::Utf8(v) => {
+                // Strings end at the first NUL character and trailing spaces are not significant
+                let s = self.chars.split('\0').next().unwrap_or_default();
+                v.push(s.trim_end().to_owned());
+                self.chars.clear();
+            }
+            // Only keep the bits of the repeat count, the last byte being padded
+            Values::Boolean(v) if self.kind == Kind::Bit => match self.layout {
+                Layout::Scalar => v.truncate(self.cell_start + 1),
+                Layout::FixedSize(n) => v.truncate(self.cell_start + n),
+                Layout::List | Layout::LargeList => (),
+            },
+            _ => (),
+        }
+
+        self.cell_start = self.values.len();
+        if let Layout::List | Layout::LargeList = self.layout {
+            // Complex values are made of two floats
+            let num_items = match self.kind {
+                Kind::Complex32 | Kind::Complex64 => self.cell_start / 2,
+                _ => self.cell_start,
+            };
+            self.offsets.push(num_items);
+        }
+    }
+
+    /// Build the array of all the cells pushed and reset the builder
+    fn finish(&mut self) -> Result<ArrayRef, Error> {
+        let values = std::mem::replace(&mut self.values, Values::new(self.kind));
+        let offsets = std::mem::replace(&mut self.offsets, vec![0]);
+        let valid = std::mem::take(&mut self.valid);
+        self.cell_start = 0;
+
+        // Only the integer columns have a TNULLn
+        let nulls = self.tnull.map(|_| NullBuffer::from(valid));
+        let items: ArrayRef = match values {
+            Values::Boolean(v) => Arc::new(BooleanArray::from(v)),
+            Values::Int8(v) => primitive_array::<Int8Type>(v, nulls)?,
+            Values::UInt8(v) => primitive_array::<UInt8Type>(v, nulls)?,
+            Values::Int16(v) => primitive_array::<Int16Type>(v, nulls)?,
+            Values::UInt16(v) => primitive_array::<UInt16Type>(v, nulls)?,
+            Values::Int32(v) => primitive_array::<Int32Type>(v, nulls)?,
+            Values::UInt32(v) => primitive_array::<UInt32Type>(v, nulls)?,
+            Values::Int64(v) => primitive_array::<Int64Type>(v, nulls)?,
+            Values::UInt64(v) => primitive_array::<UInt64Type>(v, nulls)?,
+            Values::Utf8(v) => Arc::new(StringArray::from(v)),
+            Values::Float32(v) => primitive_array::<Float32Type>(v, nulls)?,
+            Values::Float64(v) => primitive_array::<Float64Type>(v, nulls)?,
+        };
+
+        let items: ArrayRef = match self.kind {
+            Kind::Complex32 => Arc::new(FixedSizeListArray::try_new(
+                complex_field(DataType::Float32),
+                2,
+                items,
+                None,
+            )?),
+            Kind::Complex64 => Arc::new(FixedSizeListArray::try_new(
+                complex_field(DataType::Float64),
+                2,
+                items,
+                None,
+            )?),
+            _ => items,
+        };
+
+        let array: ArrayRef = match self.layout {
+            Layout::Scalar => items,
+            Layout::FixedSize(n) => Arc::new(FixedSizeListArray::try_new(
+                self.item_field.clone(),
+                n as i32,
+                items,
+                None,
+            )?),
+            Layout::List => {
+                let offsets = offsets
+                    .into_iter()
+                    .map(|off| {
+                        i32::try_from(off).map_err(|_| {
+                            Error::StaticError("Too many values in a list column for a batch")
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Arc::new(ListArray::try_new(
+                    self.item_field.clone(),
+                    OffsetBuffer::new(ScalarBuffer::from(offsets)),
+                    items,
+                    None,
+                )?)
+            }
+            Layout::LargeList => {
+                let offsets = offsets
+                    .into_iter()
+                    .map(|off| off as i64)
+                    .collect::<Vec<_>>();
+                Arc::new(LargeListArray::try_new(
+                    self.item_field.clone(),
+                    OffsetBuffer::new(ScalarBuffer::from(offsets)),
+                    items,
+                    None,
+                )?)
+            }
+        };
+
+        Ok(array)
+    }
+}
+
+/// An iterator over the rows of a binary table giving Arrow record batches
+///
+/// Each batch contains at most `batch_num_rows` rows so that tables that do not
+/// fit in memory can be streamed. Only the fields selected with
+/// [TableData::select_fields] are exported.
+#[derive(Debug)]
+pub struct RecordBatches<R> {
+    rows: TableRowData<R>,
+    schema: SchemaRef,
+    columns: Vec<ColumnBuilder>,
+    /// For each column of the table, the index of its builder if it is selected
+    builder_idx: Vec<Option<usize>>,
+    batch_num_rows: usize,
+}
+
+impl<R> RecordBatches<R> {
+    /// Prepare the export of a binary table
+    ///
+    /// # Params
+    ///
+    /// * `header` - The header of the binary table HDU
+    /// * `data` - The data of the binary table HDU
+    /// * `batch_num_rows` - Maximum number of rows per batch
+    pub fn new(header: &Header<BinTable>, data: TableData<R>, batch_num_rows: usize) -> Self {
+        let ctx = header.get_xtension();
+
+        let (columns, fields): (Vec<_>, Vec<_>) = data
+            .cols_idx
+            .iter()
+            .map(|&col_idx| ColumnBuilder::new(header, col_idx))
+            .unzip();
+
+        let mut builder_idx = vec![None; ctx.tforms.len()];
+        for (i, column) in columns.iter().enumerate() {
+            builder_idx[column.col_idx] = Some(i);
+        }
+
+        Self {
+            rows: data.row_iter(),
+            schema: Arc::new(Schema::new(fields)),
+            columns,
+            builder_idx,
+            batch_num_rows: batch_num_rows.max(1),
+        }
+    }
+
+    /// Get the schema of the record batches
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+}
+
+impl<R> RecordBatches<R>
+where
+    R: Read + Seek + Debug,
+{
+    fn next_batch(&mut self) -> Result<Option<RecordBatch>, Error> {
+        let mut num_rows = 0;
+        while num_rows < self.batch_num_rows {
+            let Some(row) = self.rows.try_next()? else {
+                break;
+            };
+
+            for value in row.iter() {
+                // Descriptors of variable length arrays have no value to export
+                let Some(col_idx) = value.column_idx() else {
+                    continue;
+                };
+                if let Some(&Some(i)) = self.builder_idx.get(col_idx) {
+                    self.columns[i].push(value)?;
+                }
+            }
+            for column in &mut self.columns {
+                column.end_cell();
+            }
+
+            num_rows += 1;
+        }
+
+        if num_rows == 0 {
+            return Ok(None);
+        }
+
+        let arrays = self
+            .columns
+            .iter_mut()
+            .map(|column| column.finish())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Some(RecordBatch::try_new(self.schema.clone(), arrays)?))
+    }
+}
+
+impl<R> Iterator for RecordBatches<R>
+where
+    R: Read + Seek + Debug,
+{
+    type Item = Result<RecordBatch, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_batch().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecordBatches;
+    use crate::hdu::data::bintable::data::BinaryTableData;
+    use crate::mock::MockFits;
+    use crate::{Fits, HDU};
+
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::{
+        Float32Type, Float64Type, Int16Type, Int32Type, Int8Type, UInt16Type, UInt32Type,
+        UInt64Type,
+    };
+    use arrow_array::Array;
+    use arrow_schema::DataType;
+    use std::io::Cursor;
+
+    fn mock_table() -> Vec<u8> {
+        let fits = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "EXTEND  =                    T",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                   25",
+                "NAXIS2  =                    3",
+                "PCOUNT  =                   12",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    5",
+                "TTYPE1  = 'ID      '",
+                "TFORM1  = '1J      '",
+                "TNULL1  =                   -1",
+                "TTYPE2  = 'NAME    '",
+                "TFORM2  = '4A      '",
+                "TTYPE3  = 'FLUX    '",
+                "TFORM3  = '2E      '",
+                "TUNIT3  = 'Jy      '",
+                "TTYPE4  = 'FLAGS   '",
+                "TFORM4  = '1X      '",
+                "TTYPE5  = 'SPECTRUM'",
+                "TFORM5  = '1PI(3)  '",
+                "END",
+            ]);
+
+        // ID, NAME, FLUX, FLAGS, SPECTRUM
+        #[allow(clippy::type_complexity)]
+        let rows: [(i32, &[u8; 4], [f32; 2], u8, &[i16]); 3] = [
+            (1, b"abc ", [1.0, 2.0], 0x80, &[1, 2, 3]),
+            (-1, b"de\0\0", [3.0, 4.0], 0x00, &[]),
+            (3, b"fghi", [5.0, 6.0], 0x80, &[4, 5, 6]),
+        ];
+        let mut data = vec![];
+        let mut heap: Vec<u8> = vec![];
+        for (id, name, flux, flags, spectrum) in rows {
+            data.extend(id.to_be_bytes());
+            data.extend(name);
+            data.extend(flux.iter().flat_map(|f| f.to_be_bytes()));
+            data.push(flags);
+            data.extend((spectrum.len() as u32).to_be_bytes());
+            data.extend((heap.len() as u32).to_be_bytes());
+            heap.extend(spectrum.iter().flat_map(|v| v.to_be_bytes()));
+        }
+        fits.data(&data).data(&heap).build()
+    }
+
+    #[test]
+    fn test_arrow_record_batches() {
+        let mut hdu_list = Fits::from_reader(Cursor::new(mock_table()));
+        let Some(Ok(HDU::Primary(_))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("expected a binary table HDU");
+        };
+        let BinaryTableData::Table(table) = hdu_list.get_data(&hdu) else {
+            panic!("expected a binary table");
+        };
+
+        let batches = RecordBatches::new(hdu.get_header(), table, 2);
+        let schema = batches.schema();
+        assert_eq!(schema.fields().len(), 5);
+        assert_eq!(schema.field(0).data_type(), &DataType::Int32);
+        assert!(schema.field(0).is_nullable());
+        assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(2).metadata()["TUNIT"], "Jy");
+        assert_eq!(schema.field(2).metadata()["TTYPE"], "FLUX");
+        assert!(matches!(
+            schema.field(2).data_type(),
+            DataType::FixedSizeList(_, 2)
+        ));
+        assert_eq!(schema.field(3).data_type(), &DataType::Boolean);
+        assert!(matches!(schema.field(4).data_type(), DataType::List(_)));
+
+        let batches = batches.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            [2, 1]
+        );
+
+        let ids = batches[0].column(0).as_primitive::<Int32Type>();
+        assert_eq!(ids.value(0), 1);
+        assert!(ids.is_null(1));
+
+        let names = batches[0].column(1).as_string::<i32>();
+        assert_eq!(names.value(0), "abc");
+        assert_eq!(names.value(1), "de");
+
+        let flux = batches[1].column(2).as_fixed_size_list();
+        let flux = flux.value(0);
+        assert_eq!(flux.as_primitive::<Float32Type>().values(), &[5.0, 6.0]);
+
+        let flags = batches[0].column(3).as_boolean();
+        assert!(flags.value(0));
+        assert!(!flags.value(1));
+
+        let spectrum = batches[0].column(4).as_list::<i32>();
+        assert_eq!(
+            spectrum.value(0).as_primitive::<Int16Type>().values(),
+            &[1, 2, 3]
+        );
+        assert_eq!(spectrum.value(1).len(), 0);
+        let spectrum = batches[1].column(4).as_list::<i32>();
+        assert_eq!(
+            spectrum.value(0).as_primitive::<Int16Type>().values(),
+            &[4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn test_arrow_truncated_table() {
+        let mut bytes = mock_table();
+        // Keep the headers and the first row only
+        bytes.truncate(2 * 2880 + 25);
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(bytes));
+        let Some(Ok(HDU::Primary(_))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("expected a binary table HDU");
+        };
+        let BinaryTableData::Table(table) = hdu_list.get_data(&hdu) else {
+            panic!("expected a binary table");
+        };
+
+        let mut batches = RecordBatches::new(hdu.get_header(), table, 2);
+        assert!(matches!(batches.next(), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_arrow_scaled_columns() {
+        let fits = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "EXTEND  =                    T",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                   25",
+                "NAXIS2  =                    2",
+                "PCOUNT  =                   3",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    6",
+                "TFORM1  = '1I      '",
+                "TZERO1  =                32768",
+                "TFORM2  = '1J      '",
+                "TZERO2  =           2147483648",
+                "TFORM3  = '1K      '",
+                "TZERO3  =  9223372036854775808",
+                "TFORM4  = '1B      '",
+                "TZERO4  =                 -128",
+                "TFORM5  = '1I      '",
+                "TSCAL5  =                  0.5",
+                "TZERO5  =                 10.0",
+                "TNULL5  =                   -1",
+                "TFORM6  = '1PX(12) '",
+                "END",
+            ]);
+
+        let mut data = vec![];
+        // The first array has 12 bits, the second one 3
+        for (u16, u32, u64, i8, scaled, num_bits, offset) in [
+            (i16::MAX, i32::MIN, -1_i64, 0_u8, 4_i16, 12_u32, 0_u32),
+            (0, 0, 0, 255, -1, 3, 2),
+        ] {
+            data.extend(u16.to_be_bytes());
+            data.extend(u32.to_be_bytes());
+            data.extend(u64.to_be_bytes());
+            data.push(i8);
+            data.extend(scaled.to_be_bytes());
+            data.extend(num_bits.to_be_bytes());
+            data.extend(offset.to_be_bytes());
+        }
+        // The bits of the second array are in the last byte of the heap
+        let heap = [0b1010_1010, 0b1100_0000, 0b0110_0000];
+
+        // Without padding, reading past the heap would reach the end of the file
+        let bytes = fits.data(&data).data(&heap).build_unpadded();
+        let mut hdu_list = Fits::from_reader(Cursor::new(bytes));
+        let Some(Ok(HDU::Primary(_))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("expected a binary table HDU");
+        };
+        let BinaryTableData::Table(table) = hdu_list.get_data(&hdu) else {
+            panic!("expected a binary table");
+        };
+
+        let batches = RecordBatches::new(hdu.get_header(), table, 2);
+        let schema = batches.schema();
+        assert_eq!(schema.field(0).data_type(), &DataType::UInt16);
+        assert_eq!(schema.field(1).data_type(), &DataType::UInt32);
+        assert_eq!(schema.field(2).data_type(), &DataType::UInt64);
+        assert_eq!(schema.field(3).data_type(), &DataType::Int8);
+        assert_eq!(schema.field(4).data_type(), &DataType::Float64);
+
+        let batches = batches.collect::<Result<Vec<_>, _>>().unwrap();
+        let batch = &batches[0];
+        assert_eq!(
+            batch.column(0).as_primitive::<UInt16Type>().values(),
+            &[u16::MAX, 32768]
+        );
+        assert_eq!(
+            batch.column(1).as_primitive::<UInt32Type>().values(),
+            &[0, 2147483648]
+        );
+        assert_eq!(
+            batch.column(2).as_primitive::<UInt64Type>().values(),
+            &[i64::MAX as u64, 1 << 63]
+        );
+        assert_eq!(
+            batch.column(3).as_primitive::<Int8Type>().values(),
+            &[-128, 127]
+        );
+
+        let scaled = batch.column(4).as_primitive::<Float64Type>();
+        assert_eq!(scaled.value(0), 12.0);
+        assert!(scaled.is_null(1));
+
+        let bits = batch.column(5).as_list::<i32>();
+        let bits = bits.value(0);
+        assert_eq!(
+            bits.as_boolean().iter().flatten().collect::<Vec<_>>(),
+            [true, false, true, false, true, false, true, false, true, true, false, false]
+        );
+        let bits = batch.column(5).as_list::<i32>().value(1);
+        assert_eq!(
+            bits.as_boolean().iter().flatten().collect::<Vec<_>>(),
+            [false, true, true]
+        );
+    }
+}
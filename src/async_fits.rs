@@ -25,20 +25,38 @@ pub struct AsyncFits<R> {
     num_bytes_in_cur_hdu: usize,
     // If an error has been encountered, the HDU iterator ends
     error_parsing_encountered: bool,
+    // Limits on the resources a header can ask for
+    #[serde(skip)]
+    limits: Limits,
     reader: R,
 }
 
 use crate::error::Error;
+use crate::fits::Limits;
 impl<R> AsyncFits<R> {
     /// Parse a FITS file
     /// # Params
     /// * `reader` - a reader created i.e. from the opening of a file
     pub fn from_reader(reader: R) -> Self {
+        Self::from_reader_with_limits(reader, Limits::default())
+    }
+
+    /// Parse a FITS file, bounding the resources its headers can ask for
+    ///
+    /// Only the [Limits] of the [ParseOptions](crate::ParseOptions) are supported by the
+    /// asynchronous reader, the headers are always parsed in the
+    /// [tolerant](crate::ParseMode::Tolerant) mode and the stream ends on the first error.
+    ///
+    /// # Params
+    /// * `reader` - a reader created i.e. from the opening of a file
+    /// * `limits` - the limits checked on each header
+    pub fn from_reader_with_limits(reader: R, limits: Limits) -> Self {
         Self {
             reader,
             num_remaining_bytes_in_cur_hdu: 0,
             num_bytes_in_cur_hdu: 0,
             error_parsing_encountered: false,
+            limits,
             start: true,
         }
     }
@@ -142,8 +160,9 @@ where
                     Ok(eof) => {
                         if !eof {
                             // parse the extension HDU
+                            let limits = self.limits;
                             let r = &mut self.reader;
-                            let parse_x_hdu = hdu::AsyncHDU::new_xtension(r);
+                            let parse_x_hdu = hdu::AsyncHDU::new_xtension(r, limits);
                             match std::pin::pin!(parse_x_hdu).poll(cx) {
                                 Poll::Pending => return Poll::Pending,
                                 // the future finished, returning the parsed hdu or the error while parsing it
@@ -157,7 +176,8 @@ where
                 }
             } else {
                 // parse the primary HDU
-                let parse_first_hdu = hdu::AsyncHDU::new_primary(&mut self.reader);
+                let limits = self.limits;
+                let parse_first_hdu = hdu::AsyncHDU::new_primary(&mut self.reader, limits);
                 match std::pin::pin!(parse_first_hdu).poll(cx) {
                     Poll::Pending => return Poll::Pending,
                     // the future finished, returning the parsed hdu or the error while parsing it
@@ -219,11 +239,23 @@ where
         num_bytes_read: &mut usize,
         cards: Vec<Card>,
     ) -> Result<Self, Error>
+    where
+        R: AsyncDataBufRead<'a, X> + 'a,
+    {
+        Self::new_with_limits(reader, num_bytes_read, cards, &Limits::default()).await
+    }
+
+    pub(crate) async fn new_with_limits<'a, R>(
+        reader: &mut R,
+        num_bytes_read: &mut usize,
+        cards: Vec<Card>,
+        limits: &Limits,
+    ) -> Result<Self, Error>
     where
         R: AsyncDataBufRead<'a, X> + 'a,
     {
         /* 1. Parse the header first */
-        let header = Header::parse(cards)?;
+        let header = Header::parse(cards, limits)?;
         /* 2. Skip the next bytes to a new 2880 multiple of bytes
         This is where the data block should start */
         let is_remaining_bytes = !(*num_bytes_read).is_multiple_of(2880);
@@ -495,9 +495,26 @@ impl<'de> Deserializer<'de> for &'de Value {
         }
     }
 
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::Visitor<'de>,
+    {
+        // Unit variants are given by their names
+        if let Value::String { value, .. } = self {
+            visitor.visit_enum(value.as_str().into_deserializer())
+        } else {
+            self.deserialize_any(visitor)
+        }
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes byte_buf unit
-        seq tuple tuple_struct map struct enum identifier ignored_any
+        seq tuple tuple_struct map struct identifier ignored_any
         newtype_struct unit_struct
     }
 }
@@ -940,4 +957,32 @@ mod tests {
             panic!("card is not a string keyword or it is missing its comment")
         }
     }
+
+    #[test]
+    fn string_value_into_enum() {
+        use serde::Deserialize;
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Quantiz {
+            #[serde(rename = "NONE")]
+            Lossless,
+            #[serde(rename = "SUBTRACTIVE_DITHER_1")]
+            SubtractiveDither1,
+        }
+
+        let value = |s: &str| Value::String {
+            value: s.to_owned(),
+            comment: None,
+        };
+        assert_eq!(
+            Option::<Quantiz>::deserialize(&value("NONE")).unwrap(),
+            Some(Quantiz::Lossless)
+        );
+        assert_eq!(
+            Quantiz::deserialize(&value("SUBTRACTIVE_DITHER_1")).unwrap(),
+            Quantiz::SubtractiveDither1
+        );
+        assert!(Quantiz::deserialize(&value("NO_DITHER")).is_err());
+        assert!(Quantiz::deserialize(&Value::Undefined).is_err());
+    }
 }
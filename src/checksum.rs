@@ -0,0 +1,314 @@
+//! Verification and generation of the CHECKSUM and DATASUM keywords
+//!
+//! The checksum of a HDU is the 32-bit ones' complement sum of its 2880 bytes blocks
+//! read as big endian 32-bit words:
+//!
+//! * DATASUM stores, as a decimal string, the sum of the data unit
+//! * CHECKSUM stores, encoded as a 16 characters ASCII string, the complement of the sum
+//!   of the header and the data unit. The sum of a HDU having a valid CHECKSUM is
+//!   therefore equal to `!0`, i.e. the negative zero of the ones' complement arithmetic.
+//!
+//! See the [checksum keyword convention](https://fits.gsfc.nasa.gov/registry/checksum.html).
+
+use crate::card::Value;
+use crate::hdu::header::Header;
+
+/// The CHECKSUM value to write in a header before computing its checksum
+pub const ZERO_CHECKSUM: &str = "0000000000000000";
+
+/// An incremental 32-bit ones' complement sum
+///
+/// The bytes can be given in chunks of any size, e.g. while streaming a HDU
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Checksum {
+    /// Sum of the 32-bit words, the carries are folded back when the sum is retrieved
+    acc: u64,
+    /// Number of bytes given so far, telling the position of the next byte in its word
+    num_bytes: u64,
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from the sum of previous bytes, e.g. a DATASUM value
+    pub fn from_sum(sum: u32) -> Self {
+        Self {
+            acc: sum as u64,
+            num_bytes: 0,
+        }
+    }
+
+    /// Add bytes to the sum
+    ///
+    /// The bytes follow the ones previously given. The number of bytes given before
+    /// a sum with another checksum must be a multiple of 4
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            let shift = 24 - 8 * (self.num_bytes % 4);
+            self.acc += (b as u64) << shift;
+            self.num_bytes += 1;
+
+            // Fold the carries before they overflow
+            if self.acc >> 62 != 0 {
+                self.acc = fold(self.acc);
+            }
+        }
+    }
+
+    /// Add the sum of other bytes, e.g. the sum of the data unit to the one of the header
+    pub fn add(&mut self, other: &Checksum) {
+        self.acc = fold(self.acc) + fold(other.acc);
+    }
+
+    /// The 32-bit ones' complement sum of the bytes
+    pub fn sum(&self) -> u32 {
+        fold(self.acc) as u32
+    }
+
+    /// The CHECKSUM value making the sum of the HDU equal to negative zero
+    ///
+    /// The sum must be the one of the header, having CHECKSUM = [ZERO_CHECKSUM],
+    /// and of the data unit
+    pub fn encode(&self) -> String {
+        encode(!self.sum())
+    }
+}
+
+/// Add the carries beyond 32 bits back to the sum, i.e. the end-around carry
+fn fold(mut acc: u64) -> u64 {
+    while acc >> 32 != 0 {
+        acc = (acc & 0xffff_ffff) + (acc >> 32);
+    }
+
+    acc
+}
+
+/// Encode a 32-bit value into the 16 characters of a CHECKSUM value
+///
+/// Each byte of the value is spread over 4 characters, avoiding the punctuation
+/// characters between the digits and the letters. The sum of the characters,
+/// taken as 32-bit words, minus the offset of `0x30` is equal to the value.
+pub fn encode(value: u32) -> String {
+    const OFFSET: u8 = 0x30;
+    const EXCLUDED: [u8; 13] = [
+        0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f, 0x40, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f, 0x60,
+    ];
+
+    let mut ascii = [0_u8; 16];
+    for (i, byte) in value.to_be_bytes().iter().enumerate() {
+        let quotient = byte / 4 + OFFSET;
+        let remainder = byte % 4;
+
+        let mut ch = [quotient; 4];
+        ch[0] += remainder;
+
+        // Move the excluded characters away by pairs, keeping the sum unchanged
+        let mut check = true;
+        while check {
+            check = false;
+            for j in (0..4).step_by(2) {
+                if EXCLUDED.contains(&ch[j]) || EXCLUDED.contains(&ch[j + 1]) {
+                    ch[j] += 1;
+                    ch[j + 1] -= 1;
+                    check = true;
+                }
+            }
+        }
+
+        for (j, &c) in ch.iter().enumerate() {
+            ascii[4 * j + i] = c;
+        }
+    }
+
+    // The characters are rotated by one to the right, aligning them on the card words
+    ascii.rotate_right(1);
+    ascii.iter().map(|&c| c as char).collect()
+}
+
+/// Status of a CHECKSUM or DATASUM keyword
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ChecksumStatus {
+    /// The keyword matches the content of the HDU
+    Good,
+    /// The keyword does not match the content of the HDU
+    Bad,
+    /// The keyword is not in the header
+    Absent,
+}
+
+/// Result of the verification of a HDU
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct HduChecksum {
+    /// Status of the CHECKSUM keyword, covering the header and the data unit
+    pub checksum: ChecksumStatus,
+    /// Status of the DATASUM keyword, covering the data unit
+    pub datasum: ChecksumStatus,
+    /// Computed sum of the data unit, i.e. the DATASUM value
+    pub data_sum: u32,
+}
+
+impl HduChecksum {
+    /// Compare the CHECKSUM and DATASUM keywords of a header to the computed sums
+    ///
+    /// # Params
+    ///
+    /// * `header` - The parsed header
+    /// * `header_sum` - The sum of the bytes of the header, padding included
+    /// * `data_sum` - The sum of the bytes of the data unit
+    pub fn new<X>(header: &Header<X>, header_sum: &Checksum, data_sum: &Checksum) -> Self {
+        let string_value = |keyword: &str| match header.get(keyword) {
+            Some(Value::String { value, .. }) => Some(value.trim().to_owned()),
+            _ => None,
+        };
+
+        let checksum = match string_value("CHECKSUM") {
+            Some(_) => {
+                let mut sum = *header_sum;
+                sum.add(data_sum);
+                if sum.sum() == !0 {
+                    ChecksumStatus::Good
+                } else {
+                    ChecksumStatus::Bad
+                }
+            }
+            None => ChecksumStatus::Absent,
+        };
+
+        let datasum = match string_value("DATASUM") {
+            Some(value) if value.parse::<u32>().ok() == Some(data_sum.sum()) => {
+                ChecksumStatus::Good
+            }
+            Some(_) => ChecksumStatus::Bad,
+            None => ChecksumStatus::Absent,
+        };
+
+        Self {
+            checksum,
+            datasum,
+            data_sum: data_sum.sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode, Checksum, ChecksumStatus, ZERO_CHECKSUM};
+    use crate::mock::MockFits;
+    use crate::{Fits, HDU};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_incremental_sum() {
+        let bytes = (0..=255_u8).cycle().take(2880).collect::<Vec<_>>();
+
+        let mut whole = Checksum::new();
+        whole.update(&bytes);
+
+        let mut chunks = Checksum::new();
+        for chunk in bytes.chunks(7) {
+            chunks.update(chunk);
+        }
+        assert_eq!(whole.sum(), chunks.sum());
+
+        // The end-around carry
+        let mut sum = Checksum::new();
+        sum.update(&[0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x02]);
+        assert_eq!(sum.sum(), 0x0000_0002);
+    }
+
+    #[test]
+    fn test_encode() {
+        // The characters are alphanumeric and their sum minus the offsets gives the value
+        for value in [0, 1, 868_229_149, 0xffff_fffe, u32::MAX] {
+            let ascii = encode(value);
+            assert_eq!(ascii.len(), 16);
+            assert!(ascii.bytes().all(|c| c.is_ascii_alphanumeric()));
+
+            // Undo the rotation and remove the offsets by adding their complement
+            let mut bytes = ascii.into_bytes();
+            bytes.rotate_left(1);
+            let mut sum = Checksum::new();
+            sum.update(&bytes);
+            sum.update(&[!0x30_u8; 16]);
+
+            // 0 and !0 both represent zero in ones' complement
+            let normalize = |v: u32| if v == !0 { 0 } else { v };
+            assert_eq!(normalize(sum.sum()), normalize(value));
+        }
+    }
+
+    #[test]
+    fn test_verify_generated_keywords() {
+        let mut data = vec![0_u8; 2880];
+        data[..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let mut data_sum = Checksum::new();
+        data_sum.update(&data);
+
+        let header = |checksum: &str, datasum: u32| {
+            MockFits::default()
+                .header(&[
+                    "SIMPLE  =                    T".to_owned(),
+                    "BITPIX  =                    8".to_owned(),
+                    "NAXIS   =                    1".to_owned(),
+                    "NAXIS1  =                    8".to_owned(),
+                    format!("CHECKSUM= '{checksum}'"),
+                    format!("DATASUM = '{datasum}'"),
+                    "END".to_owned(),
+                ])
+                .build()
+        };
+
+        // Generate the CHECKSUM value from the header having a zero CHECKSUM
+        let mut sum = Checksum::new();
+        sum.update(&header(ZERO_CHECKSUM, data_sum.sum()));
+        sum.add(&data_sum);
+        let checksum = sum.encode();
+
+        let mut buf = header(&checksum, data_sum.sum());
+        buf.extend(&data);
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(buf.clone()));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("Should contain a primary HDU");
+        };
+        let verification = hdu_list.verify_checksum(&hdu).unwrap();
+        assert_eq!(verification.checksum, ChecksumStatus::Good);
+        assert_eq!(verification.datasum, ChecksumStatus::Good);
+        assert_eq!(verification.data_sum, data_sum.sum());
+        // The iteration goes on after the verification
+        assert!(hdu_list.next().is_none());
+
+        // Corrupt one byte of the data
+        buf[2880] = 0xff;
+        let mut hdu_list = Fits::from_reader(Cursor::new(buf));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("Should contain a primary HDU");
+        };
+        let verification = hdu_list.verify_checksum(&hdu).unwrap();
+        assert_eq!(verification.checksum, ChecksumStatus::Bad);
+        assert_eq!(verification.datasum, ChecksumStatus::Bad);
+    }
+
+    #[test]
+    fn test_absent_keywords() {
+        let buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .build();
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(buf));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("Should contain a primary HDU");
+        };
+        let verification = hdu_list.verify_checksum(&hdu).unwrap();
+        assert_eq!(verification.checksum, ChecksumStatus::Absent);
+        assert_eq!(verification.datasum, ChecksumStatus::Absent);
+        assert_eq!(verification.data_sum, 0);
+    }
+}
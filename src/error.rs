@@ -1,5 +1,7 @@
+use std::fmt;
+
 quick_error! {
-    #[derive(Debug, PartialEq)]
+    #[derive(Debug)]
     pub enum Error {
         /// General error case
         StaticError(message: &'static str) {
@@ -29,12 +31,139 @@ quick_error! {
             display("Fail to parse a keyword as a utf8 string")
         }
         /// IO error wrapping the std::io::Error
-        Io(kind: std::io::ErrorKind) {
-            // to be able to derive from PartialEq just above
-            // as std::io::Error does not impl PartialEq I decided
-            // to only store its error kind which is sufficiant for our use
-            from(err: std::io::Error) -> (err.kind())
+        Io(err: std::io::Error) {
+            from()
+            source(err)
+            display("{err}")
+        }
+        /// The stream ends before the end of a data unit
+        Truncated(num_missing_bytes: u64) {
+            display("The data unit is truncated, {num_missing_bytes} bytes are missing")
+        }
+        /// A size given by a file exceeds a limit of the parsing options
+        LimitExceeded(quantity: &'static str, value: u64, limit: u64) {
+            display("{quantity} of {value} exceeds the limit of {limit}")
+        }
+        /// An error with the location in the stream where it has been encountered
+        Located(location: Location, err: Box<Error>) {
+            source(&**err)
+            display("{location}: {err}")
+        }
+    }
+}
+
+impl Error {
+    /// Attach a location to the error
+    ///
+    /// If the error is already located, only the unknown parts of its location are completed
+    pub fn at(self, location: Location) -> Self {
+        match self {
+            Error::Located(mut cur, err) => {
+                cur.hdu_idx = cur.hdu_idx.or(location.hdu_idx);
+                cur.card_idx = cur.card_idx.or(location.card_idx);
+                cur.keyword = cur.keyword.or(location.keyword);
+                cur.byte_offset = cur.byte_offset.or(location.byte_offset);
+                Error::Located(cur, err)
+            }
+            err => Error::Located(location, Box::new(err)),
+        }
+    }
+
+    /// Get the location of the error, if known
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            Error::Located(location, _) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Get the error without its location, e.g. to branch on its variant
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Located(_, err) => err.inner(),
+            err => err,
+        }
+    }
+}
+
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Error::StaticError(l), Error::StaticError(r)) => l == r,
+            (Error::Truncated(l), Error::Truncated(r)) => l == r,
+            (
+                Error::LimitExceeded(l, l_value, l_limit),
+                Error::LimitExceeded(r, r_value, r_limit),
+            ) => l == r && l_value == r_value && l_limit == r_limit,
+            (Error::DynamicError(l), Error::DynamicError(r))
+            | (Error::FailFindingKeyword(l), Error::FailFindingKeyword(r))
+            | (Error::NotSupportedXtensionType(l), Error::NotSupportedXtensionType(r)) => l == r,
+            (Error::FailReadingNextBytes, Error::FailReadingNextBytes)
+            | (Error::WCS, Error::WCS)
+            | (Error::Utf8, Error::Utf8) => true,
+            // std::io::Error does not impl PartialEq, only the error kinds are compared
+            (Error::Io(l), Error::Io(r)) => l.kind() == r.kind(),
+            (Error::Located(l_loc, l), Error::Located(r_loc, r)) => l_loc == r_loc && l == r,
+            _ => false,
+        }
+    }
+}
+
+/// Where an error has been encountered, each part being optional
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Location {
+    /// Index of the HDU, the primary HDU being 0
+    pub hdu_idx: Option<usize>,
+    /// Index of the card in the header of the HDU
+    pub card_idx: Option<usize>,
+    /// Keyword of the card
+    pub keyword: Option<String>,
+    /// Byte offset from the beginning of the stream, the one of the card if known
+    pub byte_offset: Option<u64>,
+}
+
+impl Location {
+    /// Location of a keyword whose card index is not known yet
+    pub fn keyword(keyword: &str) -> Self {
+        Self {
+            keyword: Some(keyword.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    /// Location of a card of a header
+    pub fn card(card_idx: usize, keyword: &str) -> Self {
+        Self {
+            card_idx: Some(card_idx),
+            keyword: Some(keyword.to_owned()).filter(|keyword| !keyword.is_empty()),
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    /// e.g. `HDU 3, card 17 (TFORM4) at byte 23040`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(hdu_idx) = self.hdu_idx {
+            parts.push(format!("HDU {hdu_idx}"));
         }
+        match (self.card_idx, &self.keyword) {
+            (Some(card_idx), Some(keyword)) => parts.push(format!("card {card_idx} ({keyword})")),
+            (Some(card_idx), None) => parts.push(format!("card {card_idx}")),
+            (None, Some(keyword)) => parts.push(keyword.to_owned()),
+            (None, None) => (),
+        }
+        write!(f, "{}", parts.join(", "))?;
+
+        if let Some(byte_offset) = self.byte_offset {
+            if !parts.is_empty() {
+                write!(f, " ")?;
+            }
+            write!(f, "at byte {byte_offset}")?;
+        }
+
+        Ok(())
     }
 }
 
@@ -6,7 +6,10 @@ use crate::hdu::header::extension::image::Image;
 use crate::hdu::header::Header;
 use crate::hdu::header::Xtension;
 
+use crate::checksum::{Checksum, HduChecksum};
+
 use std::fmt::Debug;
+use std::io::SeekFrom;
 
 #[derive(Debug, Clone)]
 pub struct Fits<R> {
@@ -19,9 +22,99 @@ pub struct Fits<R> {
     num_bytes_in_cur_du: usize,
     // If an error has been encountered, the HDU iterator ends
     error_parsing_encountered: bool,
+    // How the deviations from the FITS standard are handled
+    options: ParseOptions,
+    // Index of the next HDU to parse
+    hdu_idx: usize,
+    // In recovery mode, where to look for the next extension after a corrupt HDU
+    resync_byte_offset: Option<u64>,
     // The reader
     reader: R,
 }
+
+/// How the parser handles the deviations from the FITS standard
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Fail on the first header violating the standard, see [validate](crate::validate)
+    /// for the checks performed. Suited for validation pipelines.
+    Strict,
+    /// Tolerate the deviations that do not prevent reading the file:
+    /// the unparsable cards are kept as [Card::Undefined] and the invalid
+    /// TFORMn fields are discarded
+    #[default]
+    Tolerant,
+    /// Like [ParseMode::Tolerant], also repairing the common quirks of archive files
+    /// before parsing the cards. Suited for ingest:
+    /// * the non printable characters are replaced by spaces
+    /// * the lowercase keywords are uppercased
+    /// * a missing space after the `=` value indicator is inserted
+    /// * an XTENSION card not being the first card of the header is accepted
+    Lenient,
+}
+
+/// Options of the parsing of a FITS file
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub mode: ParseMode,
+    /// Go on with the next extensions after a HDU that cannot be parsed.
+    /// The error of the corrupt HDU is returned, then the blocks of 2880 bytes that follow
+    /// its header are scanned for the next one starting with a XTENSION card.
+    pub recover: bool,
+    /// Limits on the sizes given by the headers
+    pub limits: Limits,
+}
+
+/// Limits on the sizes given by the headers, guarding against the huge allocations
+/// that hostile or broken files would cause
+///
+/// A header exceeding a limit cannot be parsed, an [Error::LimitExceeded] error is returned.
+/// By default, no limit is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum number of cards of a header, END included
+    pub max_num_cards: usize,
+    /// Maximum size in bytes of a data unit. For tile compressed images, this is also
+    /// the maximum size of the uncompressed image
+    pub max_data_unit_byte_size: u64,
+    /// Maximum size in bytes of a tile of a compressed image, once decompressed. The null
+    /// pixel mask of a tile is also checked against it
+    pub max_tile_byte_size: u64,
+    /// Maximum number of elements of a variable length array, checked when reading the rows.
+    /// The row iteration stops at the first array exceeding it.
+    pub max_vla_len: u64,
+    /// Maximum number of fields of a table
+    pub max_tfields: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_num_cards: usize::MAX,
+            max_data_unit_byte_size: u64::MAX,
+            max_tile_byte_size: u64::MAX,
+            max_vla_len: u64::MAX,
+            max_tfields: usize::MAX,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Options failing on any violation of the standard
+    pub fn strict() -> Self {
+        Self {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        }
+    }
+
+    /// Options repairing the common quirks of archive files
+    pub fn lenient() -> Self {
+        Self {
+            mode: ParseMode::Lenient,
+            ..Default::default()
+        }
+    }
+}
 use std::io::Read;
 impl<R> Read for Fits<R>
 where
@@ -32,18 +125,29 @@ where
     }
 }
 
-use crate::error::Error;
+use crate::error::{Error, Location};
 
 impl<R> Fits<R> {
     /// Parse a FITS file
     /// # Params
     /// * `reader` - a reader created i.e. from the opening of a file
     pub fn from_reader(reader: R) -> Self {
+        Self::from_reader_with(reader, ParseOptions::default())
+    }
+
+    /// Parse a FITS file with specific options
+    /// # Params
+    /// * `reader` - a reader created i.e. from the opening of a file
+    /// * `options` - how the deviations from the FITS standard are handled
+    pub fn from_reader_with(reader: R, options: ParseOptions) -> Self {
         Self {
             reader,
             pos_start_cur_du: 0,
             num_bytes_in_cur_du: 0,
             error_parsing_encountered: false,
+            options,
+            hdu_idx: 0,
+            resync_byte_offset: None,
             start: true,
         }
     }
@@ -85,6 +189,20 @@ impl<R> Fits<R> {
     pub fn get_position_data_unit(&self) -> usize {
         self.pos_start_cur_du
     }
+
+    /// Locate an error encountered while parsing the header of the next HDU
+    fn locate(&self, err: Error, header_byte_offset: Option<u64>) -> Error {
+        // Point to the card the error is about if it is known
+        let card_idx = err.location().and_then(|location| location.card_idx);
+        let byte_offset =
+            header_byte_offset.map(|offset| offset + 80 * card_idx.unwrap_or(0) as u64);
+
+        err.at(Location {
+            hdu_idx: Some(self.hdu_idx),
+            byte_offset,
+            ..Default::default()
+        })
+    }
 }
 
 impl<'a, R> Fits<R>
@@ -105,6 +223,88 @@ where
     }
 }
 
+impl<R> Fits<R>
+where
+    R: Read + Seek,
+{
+    /// Verify the CHECKSUM and DATASUM keywords of a HDU
+    ///
+    /// The header and the data unit are read again block by block, the reader being then
+    /// put back to its position so that the HDU iteration and the data reading are not
+    /// affected.
+    pub fn verify_checksum<X>(&mut self, hdu: &HDU<X>) -> Result<HduChecksum, Error>
+    where
+        X: Xtension + Debug,
+    {
+        let pos = self.reader.stream_position()?;
+
+        let mut sum_bytes = |start: u64, num_bytes: u64| -> Result<Checksum, Error> {
+            self.reader.seek(SeekFrom::Start(start))?;
+
+            let mut sum = Checksum::new();
+            let mut block = [0_u8; 2880];
+            let mut num_remaining_bytes = num_bytes;
+            while num_remaining_bytes > 0 {
+                let n = num_remaining_bytes.min(block.len() as u64) as usize;
+                self.reader.read_exact(&mut block[..n])?;
+                sum.update(&block[..n]);
+                num_remaining_bytes -= n as u64;
+            }
+
+            Ok(sum)
+        };
+
+        // The padding of the header is made of spaces, it must be summed. The one of the
+        // data unit is made of zeros and does not change the sum.
+        let header_sum = sum_bytes(
+            hdu.get_header_byte_offset(),
+            hdu.get_data_unit_byte_offset() - hdu.get_header_byte_offset(),
+        );
+        let data_sum = header_sum.and_then(|header_sum| {
+            let data_sum = sum_bytes(
+                hdu.get_data_unit_byte_offset(),
+                hdu.get_data_unit_byte_size(),
+            )?;
+            Ok((header_sum, data_sum))
+        });
+
+        // Put back the reader where it was, even if the HDU could not be read
+        self.reader.seek(SeekFrom::Start(pos))?;
+        let (header_sum, data_sum) = data_sum?;
+
+        Ok(HduChecksum::new(hdu.get_header(), &header_sum, &data_sum))
+    }
+}
+
+impl<R> Fits<R>
+where
+    R: Seek,
+{
+    /// Check that the data unit of a HDU is entirely available in the stream
+    ///
+    /// The size of the data unit given by the header is compared to the length of the
+    /// stream, e.g. to detect an interrupted download. If it is truncated, an
+    /// [Error::Truncated] error giving the number of bytes missing is returned. The
+    /// available part can still be read, see [It::read_available](crate::It::read_available)
+    /// for the pixels of an image. The row iterators of the tables stop at the last
+    /// complete row.
+    pub fn check_truncation<X>(&mut self, hdu: &HDU<X>) -> Result<(), Error>
+    where
+        X: Xtension + Debug,
+    {
+        let pos = self.reader.stream_position()?;
+        let stream_len = self.reader.seek(SeekFrom::End(0))?;
+        self.reader.seek(SeekFrom::Start(pos))?;
+
+        let data_unit_end = hdu.get_data_unit_byte_offset() + hdu.get_data_unit_byte_size();
+        if data_unit_end > stream_len {
+            Err(Error::Truncated(data_unit_end - stream_len))
+        } else {
+            Ok(())
+        }
+    }
+}
+
 impl<'a, R> Iterator for Fits<R>
 where
     R: FitsRead<'a, Image> + FitsRead<'a, AsciiTable> + FitsRead<'a, BinTable> + Debug + 'a + Seek,
@@ -115,28 +315,32 @@ where
         if self.error_parsing_encountered {
             None
         } else {
-            let n = if !self.start {
+            // Start of the header, to locate the errors in the stream
+            let mut header_byte_offset = self.reader.stream_position().ok();
+            let n = if let Some(pos) = self.resync_byte_offset.take() {
+                // A corrupt HDU has been encountered, look for the next extension
+                match self.resync(pos) {
+                    Ok(true) => {
+                        header_byte_offset = self.reader.stream_position().ok();
+                        self.next_xtension(header_byte_offset)
+                    }
+                    Ok(false) => None,
+                    Err(e) => Some(Err(self.locate(e, header_byte_offset))),
+                }
+            } else if !self.start {
                 // We must consume the bytes until the next header is found
                 // if eof then the iterator finishes
                 match self.consume_until_next_hdu() {
                     Ok(()) => {
-                        let mut num_bytes_read = 0;
-                        match hdu::HDU::new_xtension(&mut self.reader, &mut num_bytes_read) {
-                                Ok(hdu) => Some(Ok(hdu)),
-                                Err(Error::Io(kind))
-                                    // an EOF has been encountered but the number of bytes read is 0
-                                    // this is valid since we have terminated the previous HDU
-                                    if kind == std::io::ErrorKind::UnexpectedEof && num_bytes_read == 0 => {
-                                        None
-                                    },
-                                Err(e) => Some(Err(e))
-                            }
+                        header_byte_offset = self.reader.stream_position().ok();
+                        self.next_xtension(header_byte_offset)
                     }
-                    Err(e) => Some(Err(e)),
+                    Err(e) => Some(Err(self.locate(e, header_byte_offset))),
                 }
             } else {
                 // primary HDU parsing
-                let hdu = hdu::HDU::new_primary(&mut self.reader);
+                let hdu = hdu::HDU::new_primary(&mut self.reader, &self.options)
+                    .map_err(|e| self.locate(e, header_byte_offset));
                 Some(hdu)
             };
 
@@ -144,14 +348,25 @@ where
 
             match n {
                 Some(Ok(hdu)) => {
+                    self.hdu_idx += 1;
                     self.num_bytes_in_cur_du = hdu.get_data_unit_byte_size() as usize;
                     self.pos_start_cur_du = hdu.get_data_unit_byte_offset() as usize;
 
                     Some(Ok(hdu))
                 }
                 Some(Err(e)) => {
-                    // an error has been found we return it and ends the iterator for future next calls
-                    self.error_parsing_encountered = true;
+                    self.hdu_idx += 1;
+                    match header_byte_offset {
+                        // the iteration goes on from the block following the header of the corrupt HDU,
+                        // unless the reader itself fails
+                        Some(offset)
+                            if self.options.recover && !matches!(e.inner(), Error::Io(_)) =>
+                        {
+                            self.resync_byte_offset = Some(offset + 2880)
+                        }
+                        // an error has been found we return it and ends the iterator for future next calls
+                        _ => self.error_parsing_encountered = true,
+                    }
 
                     Some(Err(e))
                 }
@@ -161,6 +376,55 @@ where
     }
 }
 
+impl<'a, R> Fits<R>
+where
+    R: FitsRead<'a, Image> + FitsRead<'a, AsciiTable> + FitsRead<'a, BinTable> + 'a + Seek,
+{
+    /// Parse the extension HDU starting at the current position of the reader
+    ///
+    /// Returns `None` if EOF is reached immediately
+    fn next_xtension(
+        &mut self,
+        header_byte_offset: Option<u64>,
+    ) -> Option<Result<hdu::HDU, Error>> {
+        let mut num_bytes_read = 0;
+        match hdu::HDU::new_xtension(&mut self.reader, &mut num_bytes_read, &self.options) {
+            Ok(hdu) => Some(Ok(hdu)),
+            Err(Error::Io(err))
+                // an EOF has been encountered but the number of bytes read is 0
+                // this is valid since we have terminated the previous HDU
+                if err.kind() == std::io::ErrorKind::UnexpectedEof && num_bytes_read == 0 =>
+            {
+                None
+            }
+            Err(e) => Some(Err(self.locate(e, header_byte_offset))),
+        }
+    }
+
+    /// Scan the blocks of 2880 bytes from a byte offset for one beginning with a XTENSION card
+    ///
+    /// The reader is put at the beginning of the block found. Returns `false` if EOF is
+    /// reached before
+    fn resync(&mut self, mut byte_offset: u64) -> Result<bool, Error> {
+        let mut card = [0_u8; 80];
+        loop {
+            self.reader.seek(SeekFrom::Start(byte_offset))?;
+            match self.reader.read_exact(&mut card) {
+                Ok(()) => (),
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+
+            if card.starts_with(b"XTENSION=") {
+                self.reader.seek(SeekFrom::Start(byte_offset))?;
+                return Ok(true);
+            }
+
+            byte_offset += 2880;
+        }
+    }
+}
+
 /// A generic over a HDU type
 #[derive(Debug, PartialEq)]
 pub struct HDU<X>
@@ -169,6 +433,7 @@ where
 {
     /// The header part that stores all the cards
     header: Header<X>,
+    header_byte_offset: u64,
     data_unit_byte_offset: u64,
 }
 
@@ -181,11 +446,23 @@ where
         num_bytes_read: &mut usize,
         cards: Vec<Card>,
     ) -> Result<Self, Error>
+    where
+        R: FitsRead<'a, X> + Seek + 'a,
+    {
+        Self::new_with_limits(reader, num_bytes_read, cards, &Limits::default())
+    }
+
+    pub(crate) fn new_with_limits<'a, R>(
+        reader: &mut R,
+        num_bytes_read: &mut usize,
+        cards: Vec<Card>,
+        limits: &Limits,
+    ) -> Result<Self, Error>
     where
         R: FitsRead<'a, X> + Seek + 'a,
     {
         /* 1. Parse the header first */
-        let header = Header::parse(cards)?;
+        let header = Header::parse(cards, limits)?;
         /* 2. Skip the next bytes to a new 2880 multiple of bytes
         This is where the data block should start */
         let is_remaining_bytes = !(*num_bytes_read).is_multiple_of(2880);
@@ -203,9 +480,11 @@ where
         }
 
         let data_unit_byte_offset = reader.stream_position()?;
+        let header_byte_offset = data_unit_byte_offset - *num_bytes_read as u64;
 
         Ok(Self {
             header,
+            header_byte_offset,
             data_unit_byte_offset,
         })
     }
@@ -214,6 +493,11 @@ where
         &self.header
     }
 
+    /// Get the byte offset of the first card of the header
+    pub fn get_header_byte_offset(&self) -> u64 {
+        self.header_byte_offset
+    }
+
     pub fn get_data_unit_byte_offset(&self) -> u64 {
         self.data_unit_byte_offset
     }
@@ -1,4 +1,4 @@
-use crate::hdu::data::{stream::St, AsyncDataBufRead};
+use crate::hdu::data::{iter::TryNext, stream::St, AsyncDataBufRead};
 use crate::hdu::header::extension::bintable::{
     TForm, VariableArrayTy, A, B, C, D, E, I, J, K, L, M, P, Q, X,
 };
@@ -9,7 +9,7 @@ use super::row::TableRowData;
 use crate::hdu::header::extension::bintable::{BinTable, TFormType};
 use crate::hdu::FitsRead;
 use byteorder::BigEndian;
-use log::warn;
+use log::{error, warn};
 use std::io::SeekFrom;
 use std::io::{BufReader, Read};
 
@@ -27,10 +27,13 @@ where
     }
 }
 
+// The tile compressed pixels iterator is bigger but it is only built once per HDU
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug)]
 pub enum BinaryTableData<R> {
     Table(TableData<R>),
     TileCompressed(Pixels<R>),
+    TileCompressedTable(TileCompressedTableData<R>),
 }
 
 /*
@@ -50,6 +53,7 @@ where
 */
 
 use super::tile_compressed::pixels::Pixels;
+use super::tile_compressed::table::TileCompressedTableData;
 impl<R> BinaryTableData<R>
 where
     R: Debug + Read,
@@ -60,6 +64,11 @@ where
 
         if let Some(tile_compressed) = &ctx.z_image {
             BinaryTableData::TileCompressed(Pixels::new(data, header, tile_compressed))
+        } else if let Some(tile_compressed) = &ctx.z_table {
+            BinaryTableData::TileCompressedTable(TileCompressedTableData::new(
+                data,
+                tile_compressed,
+            ))
         } else {
             BinaryTableData::Table(data)
         }
@@ -71,9 +80,11 @@ where
                 Pixels::U8(pixels) => pixels.row_it.table_data(),
                 Pixels::I16(pixels) => pixels.row_it.table_data(),
                 Pixels::I32(pixels) => pixels.row_it.table_data(),
+                Pixels::I64(pixels) => pixels.row_it.table_data(),
                 Pixels::F32(pixels) => pixels.row_it.table_data(),
                 Pixels::F64(pixels) => pixels.row_it.table_data(),
             },
+            BinaryTableData::TileCompressedTable(table) => table.data,
             BinaryTableData::Table(table) => table,
         }
     }
@@ -86,9 +97,11 @@ impl<R> BinaryTableData<R> {
                 Pixels::U8(pixels) => pixels.row_it,
                 Pixels::I16(pixels) => pixels.row_it,
                 Pixels::I32(pixels) => pixels.row_it,
+                Pixels::I64(pixels) => pixels.row_it,
                 Pixels::F32(pixels) => pixels.row_it,
                 Pixels::F64(pixels) => pixels.row_it,
             },
+            BinaryTableData::TileCompressedTable(table) => table.data.row_iter(),
             BinaryTableData::Table(table) => table.row_iter(),
         }
     }
@@ -110,12 +123,14 @@ enum DataReaderState {
         main_table_pos: SeekFrom,
         /// The type contained in the heap that we are reading
         ty: VariableArrayTy,
-        /// The number of bytes remaining to read
+        /// The number of bytes remaining to read, the number of bits for a bit array
         num_bytes_to_read: u64,
         /// The number of elements to read
         n_elems: u64,
         /// The size in bytes of one element
         t_byte_size: u64,
+        /// The byte holding the current bit of a bit array
+        byte: u8,
     },
 }
 
@@ -194,7 +209,11 @@ where
 
 impl<R> TableData<R> {
     pub fn new(reader: R, header: &Header<BinTable>, start_pos: u64) -> Self {
-        let ctx = header.get_xtension();
+        Self::from_ctx(reader, header.get_xtension().clone(), start_pos)
+    }
+
+    /// Create the table data from the context of the table, i.e. its parsed header
+    pub(crate) fn from_ctx(reader: R, ctx: BinTable, start_pos: u64) -> Self {
         let state = DataReaderState::MainTable;
 
         // Compute an byte offset for each columns to know at which byte index does the column
@@ -232,7 +251,7 @@ impl<R> TableData<R> {
             byte_offset,
             main_data_table_byte_size,
             start_pos,
-            ctx: ctx.clone(),
+            ctx,
             row_idx,
             heap,
         }
@@ -412,6 +431,7 @@ where
                     num_bytes_to_read,
                     t_byte_size,
                     n_elems,
+                    byte: 0,
                 };
             }
         }
@@ -419,6 +439,23 @@ where
         Ok(())
     }
 
+    /// Check the number of elements of a variable length array against the limit
+    /// of the parsing options
+    fn check_vla_len(&self, num_elems: u64) -> Result<(), Error> {
+        if num_elems > self.ctx.max_vla_len {
+            let err = Error::LimitExceeded(
+                "Variable length array length",
+                num_elems,
+                self.ctx.max_vla_len,
+            );
+            error!("{err}");
+
+            Err(err)
+        } else {
+            Ok(())
+        }
+    }
+
     pub(crate) fn jump_to_main_table(&mut self) -> Result<(), Error>
     where
         R: Seek,
@@ -445,14 +482,13 @@ impl<R> TableData<R> {
 
 use super::{ColumnId, DataValue};
 use std::io::Seek;
-impl<R> Iterator for TableData<R>
+impl<R> TryNext for TableData<R>
 where
     R: Read + Seek + Debug,
 {
-    // Return a vec of fields because to take into account the repeat count value for that field
     type Item = DataValue;
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
         // First get the column index in the main data table where the reader is
         let col_idx = self.cols_idx[self.col_idx];
 
@@ -462,7 +498,7 @@ where
 
             let off = self.col_byte_offsets[col_idx] as i64;
             // seek to the next col location
-            self.reader.seek_relative(off).ok()?;
+            self.reader.seek_relative(off)?;
             // update the byte offset as well
             self.byte_offset += off as usize;
         }
@@ -473,6 +509,7 @@ where
                 num_bytes_to_read,
                 n_elems,
                 t_byte_size,
+                byte,
                 ..
             } => {
                 // We will build an iterator that will parse the variable length array
@@ -484,7 +521,7 @@ where
 
                 let value = match ty {
                     VariableArrayTy::L => {
-                        let value = self.reader.read_u8().ok()? != 0;
+                        let value = self.reader.read_u8()? != 0;
                         *num_bytes_to_read -= L::BYTES_SIZE as u64;
                         DataValue::Logical {
                             value,
@@ -493,17 +530,20 @@ where
                         }
                     }
                     VariableArrayTy::X => {
-                        let byte = self.reader.read_u8().ok()?;
-                        *num_bytes_to_read -= X::BYTES_SIZE as u64;
+                        // The bits are packed in ceil(n/8) bytes, one value is given per bit
+                        if idx.is_multiple_of(8) {
+                            *byte = self.reader.read_u8()?;
+                        }
+                        *num_bytes_to_read -= 1;
                         DataValue::Bit {
-                            byte,
-                            bit_idx: 0,
+                            byte: *byte,
+                            bit_idx: (idx % 8) as u8,
                             column: ColumnId::Index(col_idx),
                             idx,
                         }
                     }
                     VariableArrayTy::B => {
-                        let value = self.reader.read_u8().ok()?;
+                        let value = self.reader.read_u8()?;
                         *num_bytes_to_read -= B::BYTES_SIZE as u64;
                         DataValue::UnsignedByte {
                             value,
@@ -512,7 +552,7 @@ where
                         }
                     }
                     VariableArrayTy::I => {
-                        let value = self.reader.read_i16::<BigEndian>().ok()?;
+                        let value = self.reader.read_i16::<BigEndian>()?;
                         *num_bytes_to_read -= I::BYTES_SIZE as u64;
                         DataValue::Short {
                             value,
@@ -521,7 +561,7 @@ where
                         }
                     }
                     VariableArrayTy::J => {
-                        let value = self.reader.read_i32::<BigEndian>().ok()?;
+                        let value = self.reader.read_i32::<BigEndian>()?;
                         *num_bytes_to_read -= J::BYTES_SIZE as u64;
                         DataValue::Integer {
                             value,
@@ -530,7 +570,7 @@ where
                         }
                     }
                     VariableArrayTy::K => {
-                        let value = self.reader.read_i64::<BigEndian>().ok()?;
+                        let value = self.reader.read_i64::<BigEndian>()?;
                         *num_bytes_to_read -= K::BYTES_SIZE as u64;
                         DataValue::Long {
                             value,
@@ -539,7 +579,7 @@ where
                         }
                     }
                     VariableArrayTy::A => {
-                        let value = self.reader.read_u8().ok()? as char;
+                        let value = self.reader.read_u8()? as char;
                         *num_bytes_to_read -= A::BYTES_SIZE as u64;
                         DataValue::Character {
                             value,
@@ -548,7 +588,7 @@ where
                         }
                     }
                     VariableArrayTy::E => {
-                        let value = self.reader.read_f32::<BigEndian>().ok()?;
+                        let value = self.reader.read_f32::<BigEndian>()?;
                         *num_bytes_to_read -= E::BYTES_SIZE as u64;
                         DataValue::Float {
                             value,
@@ -557,7 +597,7 @@ where
                         }
                     }
                     VariableArrayTy::D => {
-                        let value = self.reader.read_f64::<BigEndian>().ok()?;
+                        let value = self.reader.read_f64::<BigEndian>()?;
                         *num_bytes_to_read -= D::BYTES_SIZE as u64;
                         DataValue::Double {
                             value,
@@ -566,8 +606,8 @@ where
                         }
                     }
                     VariableArrayTy::C => {
-                        let real = self.reader.read_f32::<BigEndian>().ok()?;
-                        let imag = self.reader.read_f32::<BigEndian>().ok()?;
+                        let real = self.reader.read_f32::<BigEndian>()?;
+                        let imag = self.reader.read_f32::<BigEndian>()?;
 
                         *num_bytes_to_read -= C::BYTES_SIZE as u64;
                         DataValue::ComplexFloat {
@@ -578,8 +618,8 @@ where
                         }
                     }
                     VariableArrayTy::M => {
-                        let real = self.reader.read_f64::<BigEndian>().ok()?;
-                        let imag = self.reader.read_f64::<BigEndian>().ok()?;
+                        let real = self.reader.read_f64::<BigEndian>()?;
+                        let imag = self.reader.read_f64::<BigEndian>()?;
 
                         *num_bytes_to_read -= M::BYTES_SIZE as u64;
                         DataValue::ComplexDouble {
@@ -594,12 +634,12 @@ where
                 if *num_bytes_to_read == 0 {
                     // no more bytes to read on the heap.
                     // we first jump back to the main table where we were
-                    self.jump_to_main_table().ok()?;
+                    self.jump_to_main_table()?;
                     // and we seek the next column there
-                    self.seek_to_next_col().ok()?;
+                    self.seek_to_next_col()?;
                 }
 
-                Some(value)
+                Ok(Some(value))
             }
             DataReaderState::MainTable => {
                 // Check whether we are at the end of the main data table
@@ -607,7 +647,7 @@ where
                 // Read the next value
 
                 if self.byte_offset == self.main_data_table_byte_size {
-                    None
+                    Ok(None)
                 } else {
                     let idx = self.item_idx;
 
@@ -615,240 +655,242 @@ where
                     match &self.ctx.tforms[col_idx] {
                         // Logical
                         TFormType::L { repeat_count } => {
-                            let byte = self.reader.read_u8().ok()?;
+                            let byte = self.reader.read_u8()?;
                             self.byte_offset += L::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Logical {
+                            Ok(Some(DataValue::Logical {
                                 value: byte != 0,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Bit
                         TFormType::X { repeat_count } => {
-                            let byte = self.reader.read_u8().ok()?;
+                            let byte = self.reader.read_u8()?;
                             self.byte_offset += X::BYTES_SIZE;
 
                             self.item_idx += 1;
-                            if self.item_idx == (*repeat_count / 8) + 1 {
-                                self.seek_to_next_col().ok()?;
+                            if self.item_idx == repeat_count.div_ceil(8) {
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Bit {
+                            Ok(Some(DataValue::Bit {
                                 byte,
                                 bit_idx: 0,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Unsigned byte
                         TFormType::B { repeat_count } => {
-                            let byte = self.reader.read_u8().ok()?;
+                            let byte = self.reader.read_u8()?;
                             self.byte_offset += B::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::UnsignedByte {
+                            Ok(Some(DataValue::UnsignedByte {
                                 value: byte,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // 16-bit integer
                         TFormType::I { repeat_count } => {
-                            let short = self.reader.read_i16::<BigEndian>().ok()?;
+                            let short = self.reader.read_i16::<BigEndian>()?;
                             self.byte_offset += I::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Short {
+                            Ok(Some(DataValue::Short {
                                 value: short,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // 32-bit integer
                         TFormType::J { repeat_count } => {
-                            let int = self.reader.read_i32::<BigEndian>().ok()?;
+                            let int = self.reader.read_i32::<BigEndian>()?;
                             self.byte_offset += J::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Integer {
+                            Ok(Some(DataValue::Integer {
                                 value: int,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // 64-bit integer
                         TFormType::K { repeat_count } => {
-                            let long = self.reader.read_i64::<BigEndian>().ok()?;
+                            let long = self.reader.read_i64::<BigEndian>()?;
                             self.byte_offset += K::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Long {
+                            Ok(Some(DataValue::Long {
                                 value: long,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Character
                         TFormType::A { repeat_count } => {
-                            let c = self.reader.read_u8().ok()?;
+                            let c = self.reader.read_u8()?;
                             self.byte_offset += A::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Character {
+                            Ok(Some(DataValue::Character {
                                 value: c as char,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Single-precision floating point
                         TFormType::E { repeat_count } => {
-                            let float = self.reader.read_f32::<BigEndian>().ok()?;
+                            let float = self.reader.read_f32::<BigEndian>()?;
                             self.byte_offset += E::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Float {
+                            Ok(Some(DataValue::Float {
                                 value: float,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Double-precision floating point
                         TFormType::D { repeat_count } => {
-                            let double = self.reader.read_f64::<BigEndian>().ok()?;
+                            let double = self.reader.read_f64::<BigEndian>()?;
                             self.byte_offset += D::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::Double {
+                            Ok(Some(DataValue::Double {
                                 value: double,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Single-precision complex
                         TFormType::C { repeat_count } => {
-                            let real = self.reader.read_f32::<BigEndian>().ok()?;
-                            let imag = self.reader.read_f32::<BigEndian>().ok()?;
+                            let real = self.reader.read_f32::<BigEndian>()?;
+                            let imag = self.reader.read_f32::<BigEndian>()?;
                             self.byte_offset += C::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::ComplexFloat {
+                            Ok(Some(DataValue::ComplexFloat {
                                 real,
                                 imag,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Double-precision complex
                         TFormType::M { repeat_count } => {
-                            let real = self.reader.read_f64::<BigEndian>().ok()?;
-                            let imag = self.reader.read_f64::<BigEndian>().ok()?;
+                            let real = self.reader.read_f64::<BigEndian>()?;
+                            let imag = self.reader.read_f64::<BigEndian>()?;
                             self.byte_offset += M::BYTES_SIZE;
 
                             self.item_idx += 1;
                             if self.item_idx == *repeat_count {
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
                             }
 
-                            Some(DataValue::ComplexDouble {
+                            Ok(Some(DataValue::ComplexDouble {
                                 real,
                                 imag,
                                 column: ColumnId::Index(col_idx),
                                 idx,
-                            }) // Determine the count idx inside the field
+                            })) // Determine the count idx inside the field
                         }
                         // Array Descriptor (32-bit)
                         TFormType::P {
                             ty, t_byte_size, ..
                         } => {
-                            let num_elems = self.reader.read_u32::<BigEndian>().ok()?;
-                            let offset_byte = self.reader.read_u32::<BigEndian>().ok()?;
+                            let num_elems = self.reader.read_u32::<BigEndian>()?;
+                            let offset_byte = self.reader.read_u32::<BigEndian>()?;
+                            self.check_vla_len(num_elems as u64)?;
 
                             self.byte_offset += P::BYTES_SIZE;
 
-                            if self.heap {
+                            // An empty array has nothing to read in the heap
+                            if self.heap && num_elems > 0 {
                                 self.jump_to_heap(
                                     *ty,
                                     offset_byte as u64,
                                     num_elems as u64,
                                     *t_byte_size,
-                                )
-                                .ok()?;
+                                )?;
 
-                                self.next()
+                                self.try_next()
                             } else {
                                 // We need to seek to the next call if we do not jump to the heap, notifying
                                 // we finished parsing this field
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
 
                                 // just returns the n_elems and offset from the iterator
-                                Some(DataValue::VariableLengthArray32 {
+                                Ok(Some(DataValue::VariableLengthArray32 {
                                     num_elems,
                                     offset_byte,
-                                })
+                                }))
                             }
                         }
                         // Array Descriptor (64-bit)
                         TFormType::Q {
                             ty, t_byte_size, ..
                         } => {
-                            let num_elems = self.reader.read_u64::<BigEndian>().ok()?;
-                            let offset_byte = self.reader.read_u64::<BigEndian>().ok()?;
+                            let num_elems = self.reader.read_u64::<BigEndian>()?;
+                            let offset_byte = self.reader.read_u64::<BigEndian>()?;
+                            self.check_vla_len(num_elems)?;
 
                             self.byte_offset += Q::BYTES_SIZE;
 
-                            if self.heap {
-                                self.jump_to_heap(*ty, offset_byte, num_elems, *t_byte_size)
-                                    .ok()?;
+                            // An empty array has nothing to read in the heap
+                            if self.heap && num_elems > 0 {
+                                self.jump_to_heap(*ty, offset_byte, num_elems, *t_byte_size)?;
 
-                                self.next()
+                                self.try_next()
                             } else {
                                 // We need to seek to the next call if we do not jump to the heap, notifying
                                 // we finished parsing this field
-                                self.seek_to_next_col().ok()?;
+                                self.seek_to_next_col()?;
 
                                 // just returns the n_elems and offset from the iterator
-                                Some(DataValue::VariableLengthArray64 {
+                                Ok(Some(DataValue::VariableLengthArray64 {
                                     num_elems,
                                     offset_byte,
-                                })
+                                }))
                             }
                         }
                     }
@@ -858,6 +900,18 @@ where
     }
 }
 
+impl<R> Iterator for TableData<R>
+where
+    R: Read + Seek + Debug,
+{
+    // Return a vec of fields because to take into account the repeat count value for that field
+    type Item = DataValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
+    }
+}
+
 use async_trait::async_trait;
 use futures::AsyncReadExt;
 use std::marker::Unpin;
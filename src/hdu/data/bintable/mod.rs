@@ -140,3 +140,31 @@ pub enum DataValue {
         offset_byte: u64,
     },
 }
+
+impl DataValue {
+    /// Get the index of the column the value belongs to
+    ///
+    /// Returns `None` for variable length array descriptors as they are not
+    /// attached to a column
+    pub fn column_idx(&self) -> Option<usize> {
+        match self {
+            DataValue::Logical { column, .. }
+            | DataValue::Bit { column, .. }
+            | DataValue::UnsignedByte { column, .. }
+            | DataValue::Short { column, .. }
+            | DataValue::Integer { column, .. }
+            | DataValue::Long { column, .. }
+            | DataValue::Character { column, .. }
+            | DataValue::Float { column, .. }
+            | DataValue::Double { column, .. }
+            | DataValue::ComplexFloat { column, .. }
+            | DataValue::ComplexDouble { column, .. } => match column {
+                ColumnId::Index(idx) => Some(*idx),
+                ColumnId::Name(_) => None,
+            },
+            DataValue::VariableLengthArray32 { .. } | DataValue::VariableLengthArray64 { .. } => {
+                None
+            }
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::error::Error;
+use crate::hdu::data::iter::TryNext;
 use crate::hdu::header::extension::bintable::BinTable;
 use std::fmt::Debug;
 use std::io::{Read, Seek};
@@ -38,22 +40,36 @@ impl<R> TableRowData<R> {
     }
 }
 
-impl<R> Iterator for TableRowData<R>
+impl<R> TryNext for TableRowData<R>
 where
     R: Read + Seek + Debug,
 {
-    // Return a vec of fields because to take into account the repeat count value for that field
     type Item = Box<[DataValue]>;
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
         let mut row_data = vec![];
 
         while self.data.row_idx == self.idx_row {
-            row_data.push(self.data.next()?);
+            let Some(value) = self.data.try_next()? else {
+                return Ok(None);
+            };
+            row_data.push(value);
         }
 
         self.idx_row += 1;
 
-        Some(row_data.into_boxed_slice())
+        Ok(Some(row_data.into_boxed_slice()))
+    }
+}
+
+impl<R> Iterator for TableRowData<R>
+where
+    R: Read + Seek + Debug,
+{
+    // Return a vec of fields because to take into account the repeat count value for that field
+    type Item = Box<[DataValue]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
     }
 }
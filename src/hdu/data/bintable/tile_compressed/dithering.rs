@@ -23,9 +23,71 @@ pub(crate) const fn random_generator() -> [f32; N_RANDOM] {
 
 pub(crate) static RAND_VALUES: [f32; N_RANDOM] = random_generator();
 
+/// Position inside the random sequence used to dither the pixels of a tile
+#[derive(Debug)]
+pub(crate) struct Dither {
+    /// Index of the random value giving the start of the current sequence
+    i0: usize,
+    /// Index of the random value of the next pixel
+    i1: usize,
+}
+
+impl Dither {
+    /// Start the random sequence of a tile
+    ///
+    /// # Params
+    ///
+    /// * `row` - The 1-based row storing the tile in the binary table
+    /// * `z_dither_0` - The ZDITHER0 seed, ranging from 1 to 10000
+    pub(crate) fn new(row: usize, z_dither_0: i64) -> Self {
+        // Same offset as CFITSIO, the seed and the row are both 1-based
+        let i0 = (row as i64 - 2 + z_dither_0).rem_euclid(N_RANDOM as i64) as usize;
+        let i1 = (RAND_VALUES[i0] * 500.0) as usize;
+
+        Self { i0, i1 }
+    }
+
+    /// Returns the random value of the current pixel and moves on to the next pixel
+    ///
+    /// Once the end of the random values is reached, a new sequence starts from the next seed
+    pub(crate) fn next_value(&mut self) -> f32 {
+        let value = RAND_VALUES[self.i1];
+
+        self.i1 += 1;
+        if self.i1 == N_RANDOM {
+            self.i0 = (self.i0 + 1) % N_RANDOM;
+            self.i1 = (RAND_VALUES[self.i0] * 500.0) as usize;
+        }
+
+        value
+    }
+}
+
+#[cfg(test)]
 mod tests {
+    use super::{Dither, N_RANDOM, RAND_VALUES};
+
     #[test]
     fn test_random_generator() {
         super::random_generator();
     }
+
+    #[test]
+    fn test_dither_sequence() {
+        // The first tile with a seed of 1 starts from the first random value
+        let mut dither = Dither::new(1, 1);
+        let i1 = (RAND_VALUES[0] * 500.0) as usize;
+        assert_eq!(dither.next_value(), RAND_VALUES[i1]);
+
+        // Reaching the end of the random values restarts from the next seed
+        for _ in (i1 + 1)..N_RANDOM {
+            dither.next_value();
+        }
+        let i1 = (RAND_VALUES[1] * 500.0) as usize;
+        assert_eq!(dither.next_value(), RAND_VALUES[i1]);
+
+        // The seed wraps around for the last tiles
+        let dither = Dither::new(3, 9999);
+        assert_eq!(dither.i0, 0);
+    }
 }
@@ -0,0 +1,857 @@
+//! This code is a port in Rust of CFITSIO's fits_hdecompress.c
+//!
+//! The original code can be found here: <https://github.com/HEASARC/cfitsio/blob/develop/fits_hdecompress.c>
+//! The H-compress algorithm has been written by Richard White at STScI. A compressed tile is made of:
+//! * a header giving the size of the tile, the digitization scale factor and the sum of all its pixels,
+//! * the bit planes of the H-transform coefficients of each quadrant, either written directly or
+//!   quadtree-coded with a fixed Huffman code,
+//! * the sign bits of the non-zero coefficients.
+//!
+//! The decoding is done on 64-bit integers as in `fits_hdecompress64` so that the sums of the
+//! H-transform do not overflow for 32-bit images.
+
+use crate::error::Error;
+use std::io::Read;
+
+/// Magic code starting an H-compressed stream
+const CODE_MAGIC: [u8; 2] = [0xDD, 0x99];
+
+/// Decompress a H-compressed tile without smoothing
+///
+/// # Params
+///
+/// * `input` - The compressed bytes of the tile
+/// * `num_pixels` - The number of pixels of the tile given by ZTILEn. The size stored in
+///   the stream must match it
+///
+/// # Returns
+///
+/// The pixels of the tile, the first FITS axis varying the most rapidly
+pub(crate) fn decompress(input: &[u8], num_pixels: usize) -> Result<Vec<i64>, Error> {
+    let mut reader = BitReader::new(input);
+
+    // In the stream the second FITS axis is called `nx` and the first one `ny`, the
+    // pixel `a[i, j]` being at index `i * ny + j`
+    let (mut a, nx, ny, scale) = decode(&mut reader, num_pixels)?;
+    undigitize(&mut a, scale)?;
+    hinv(&mut a, nx, ny);
+
+    Ok(a)
+}
+
+/// Read and decompress a H-compressed tile, writing its pixels as native endian `i32` into `buf`
+///
+/// # Params
+///
+/// * `reader` - A reader located at the start of the compressed tile
+/// * `num_bytes` - The number of compressed bytes of the tile
+/// * `buf` - The output buffer, storing 4 bytes per pixel
+pub(crate) fn decompress_tile<R: Read>(
+    reader: &mut R,
+    num_bytes: usize,
+    buf: &mut [u8],
+) -> Result<(), Error> {
+    let mut input = vec![0_u8; num_bytes];
+    reader.read_exact(&mut input)?;
+
+    let pixels = decompress(&input, buf.len() / 4)?;
+    for (dst, &v) in buf.chunks_exact_mut(4).zip(pixels.iter()) {
+        dst.copy_from_slice(&(v as i32).to_ne_bytes());
+    }
+
+    Ok(())
+}
+
+/// Read the H-compressed stream into the H-transform coefficients
+///
+/// The size stored in the stream is checked against `num_pixels` before allocating anything
+fn decode(
+    reader: &mut BitReader,
+    num_pixels: usize,
+) -> Result<(Vec<i64>, usize, usize, i32), Error> {
+    // File starts with a special 2-byte magic code
+    if [reader.read_u8()?, reader.read_u8()?] != CODE_MAGIC {
+        return Err(Error::StaticError(
+            "H-compressed tile does not start with the magic code",
+        ));
+    }
+
+    let nx = reader.read_i32()?;
+    let ny = reader.read_i32()?;
+    let scale = reader.read_i32()?;
+    if nx <= 0 || ny <= 0 {
+        return Err(Error::StaticError(
+            "H-compressed tile has a non positive size",
+        ));
+    }
+    let (nx, ny) = (nx as usize, ny as usize);
+    if nx.checked_mul(ny) != Some(num_pixels) {
+        return Err(Error::StaticError(
+            "H-compressed tile size does not match ZTILEn",
+        ));
+    }
+
+    // Sum of all pixels
+    let sumall = reader.read_i64()?;
+    // Number of bits in quadrants
+    let nbitplanes = [reader.read_u8()?, reader.read_u8()?, reader.read_u8()?];
+    // The coefficients are 64-bit signed integers
+    if nbitplanes.iter().any(|&n| n > 63) {
+        return Err(Error::StaticError(
+            "H-compressed tile has more than 63 bit planes",
+        ));
+    }
+
+    let mut a = dodecode(reader, nx, ny, nbitplanes)?;
+    // Put sum of all pixels back into pixel 0
+    a[0] = sumall;
+
+    Ok((a, nx, ny, scale))
+}
+
+fn dodecode(
+    reader: &mut BitReader,
+    nx: usize,
+    ny: usize,
+    nbitplanes: [u8; 3],
+) -> Result<Vec<i64>, Error> {
+    let nel = nx * ny;
+    let nx2 = nx.div_ceil(2);
+    let ny2 = ny.div_ceil(2);
+
+    let mut a = vec![0_i64; nel];
+
+    // Read bit planes for each quadrant. For a tile of width or height 1, some quadrants
+    // are empty and may start past the end of the array
+    reader.start_inputing_bits();
+    let quadrants = [
+        (0, nx2, ny2, nbitplanes[0]),
+        (ny2, nx2, ny / 2, nbitplanes[1]),
+        (ny * nx2, nx / 2, ny2, nbitplanes[1]),
+        (ny * nx2 + ny2, nx / 2, ny / 2, nbitplanes[2]),
+    ];
+    for (start, nqx, nqy, nbitplanes) in quadrants {
+        let quadrant = a.get_mut(start..).unwrap_or_default();
+        qtree_decode(reader, quadrant, ny, nqx, nqy, nbitplanes)?;
+    }
+
+    // Make sure there is an EOF symbol (nybble = 0) at the end
+    if reader.input_nybble()? != 0 {
+        return Err(Error::StaticError(
+            "H-compressed tile has bad bit plane values",
+        ));
+    }
+
+    // Now get the sign bits
+    reader.start_inputing_bits();
+    for v in a.iter_mut() {
+        if *v != 0 && reader.input_bit()? == 1 {
+            *v = -*v;
+        }
+    }
+
+    Ok(a)
+}
+
+/// Smallest `log2n` such that `1 << log2n >= n`
+fn log2_ceil(n: usize) -> u32 {
+    let mut log2n = 0;
+    while (1 << log2n) < n {
+        log2n += 1;
+    }
+
+    log2n
+}
+
+/// Decode the bit planes of a quadrant
+///
+/// # Params
+///
+/// * `a` - The coefficients, starting at the first one of the quadrant
+/// * `n` - Declared y dimension of `a`
+/// * `nqx` - Partial length of row to decode
+/// * `nqy` - Partial length of column (<= n)
+/// * `nbitplanes` - Number of bit planes to decode
+fn qtree_decode(
+    reader: &mut BitReader,
+    a: &mut [i64],
+    n: usize,
+    nqx: usize,
+    nqy: usize,
+    nbitplanes: u8,
+) -> Result<(), Error> {
+    // log2n is log2 of max(nqx,nqy) rounded up to next power of 2
+    let log2n = log2_ceil(nqx.max(nqy));
+
+    // Scratch array for working space
+    let nqx2 = nqx.div_ceil(2);
+    let nqy2 = nqy.div_ceil(2);
+    let mut scratch = vec![0_u8; nqx2 * nqy2];
+
+    // Decode each bit plane, starting at the top
+    for bit in (0..nbitplanes).rev() {
+        // Was bitplane quadtree-coded or written directly?
+        match reader.input_nybble()? {
+            0 => {
+                // Bit map was written directly, 4 pixels per nybble
+                for s in scratch.iter_mut() {
+                    *s = reader.input_nybble()?;
+                }
+            }
+            0xf => {
+                // Bit map was quadtree-coded, do log2n expansions
+                // read first code
+                let Some(first) = scratch.first_mut() else {
+                    return Err(Error::StaticError(
+                        "H-compressed tile has a quadtree-coded bit plane in an empty quadrant",
+                    ));
+                };
+                *first = reader.input_huffman()?;
+
+                // Now do log2n expansions, reading codes from file as necessary
+                let mut nx = 1;
+                let mut ny = 1;
+                let mut nfx = nqx;
+                let mut nfy = nqy;
+                let mut c = 1 << log2n;
+                for _ in 1..log2n {
+                    // This somewhat cryptic code generates the sequence
+                    // n[k-1] = (n[k]+1)/2 where n[log2n]=nqx or nqy
+                    c >>= 1;
+                    nx <<= 1;
+                    ny <<= 1;
+                    if nfx <= c {
+                        nx -= 1;
+                    } else {
+                        nfx -= c;
+                    }
+                    if nfy <= c {
+                        ny -= 1;
+                    } else {
+                        nfy -= c;
+                    }
+                    qtree_expand(reader, &mut scratch, nx, ny)?;
+                }
+            }
+            _ => {
+                return Err(Error::StaticError(
+                    "H-compressed tile has a bad bit plane format code",
+                ))
+            }
+        }
+
+        // Copy last set of 4-bit codes to bitplane bit of array a
+        qtree_bitins(&scratch, nqx, nqy, a, n, bit);
+    }
+
+    Ok(())
+}
+
+/// Do one quadtree expansion step on array `a[(nx+1)/2,(ny+1)/2]`.
+/// The result is put in place into `a[nx,ny]`
+fn qtree_expand(reader: &mut BitReader, a: &mut [u8], nx: usize, ny: usize) -> Result<(), Error> {
+    // First copy a to b, expanding each 4-bit value
+    qtree_copy(a, nx, ny, ny);
+
+    // Now read new 4-bit values into b for each non-zero element
+    for v in a[..nx * ny].iter_mut().rev() {
+        if *v != 0 {
+            *v = reader.input_huffman()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy 4-bit values from `a[(nx+1)/2,(ny+1)/2]` to `a[nx,ny]`, expanding
+/// each value to 2x2 pixels
+///
+/// `n` is the declared y dimension of the expanded array
+fn qtree_copy(a: &mut [u8], nx: usize, ny: usize, n: usize) {
+    // First copy 4-bit values
+    // start at end as the destination is the same array
+    let nx2 = nx.div_ceil(2);
+    let ny2 = ny.div_ceil(2);
+    for i in (0..nx2).rev() {
+        for j in (0..ny2).rev() {
+            a[2 * (n * i + j)] = a[ny2 * i + j];
+        }
+    }
+
+    // Now expand each 2x2 block
+    let mut i = 0;
+    while i + 1 < nx {
+        let s00 = n * i;
+        let s10 = s00 + n;
+
+        let mut j = 0;
+        while j + 1 < ny {
+            let v = a[s00 + j];
+            a[s10 + j + 1] = v & 1;
+            a[s10 + j] = (v >> 1) & 1;
+            a[s00 + j + 1] = (v >> 2) & 1;
+            a[s00 + j] = (v >> 3) & 1;
+
+            j += 2;
+        }
+        if j < ny {
+            // Row size is odd, do last element in row
+            // s00+1, s10+1 are off edge
+            let v = a[s00 + j];
+            a[s10 + j] = (v >> 1) & 1;
+            a[s00 + j] = (v >> 3) & 1;
+        }
+
+        i += 2;
+    }
+    if i < nx {
+        // Column size is odd, do last row
+        // s10, s10+1 are off edge
+        let s00 = n * i;
+
+        let mut j = 0;
+        while j + 1 < ny {
+            let v = a[s00 + j];
+            a[s00 + j + 1] = (v >> 2) & 1;
+            a[s00 + j] = (v >> 3) & 1;
+
+            j += 2;
+        }
+        if j < ny {
+            // Both row and column size are odd, do corner element
+            // s00+1, s10, s10+1 are off edge
+            a[s00 + j] = (a[s00 + j] >> 3) & 1;
+        }
+    }
+}
+
+/// Copy 4-bit values from `a[(nx+1)/2,(ny+1)/2]` to `b[nx,ny]`, expanding
+/// each value to 2x2 pixels and inserting into bitplane `bit` of `b`
+///
+/// `n` is the declared y dimension of `b`
+fn qtree_bitins(a: &[u8], nx: usize, ny: usize, b: &mut [i64], n: usize, bit: u8) {
+    let plane_val = 1_i64 << bit;
+
+    // Expand each 2x2 block
+    let mut k = 0;
+    let mut i = 0;
+    while i + 1 < nx {
+        let s00 = n * i;
+        let s10 = s00 + n;
+
+        let mut j = 0;
+        while j + 1 < ny {
+            let v = a[k];
+            if v & 1 != 0 {
+                b[s10 + j + 1] |= plane_val;
+            }
+            if v & 2 != 0 {
+                b[s10 + j] |= plane_val;
+            }
+            if v & 4 != 0 {
+                b[s00 + j + 1] |= plane_val;
+            }
+            if v & 8 != 0 {
+                b[s00 + j] |= plane_val;
+            }
+
+            j += 2;
+            k += 1;
+        }
+        if j < ny {
+            // Row size is odd, do last element in row
+            // s00+1, s10+1 are off edge
+            if a[k] & 2 != 0 {
+                b[s10 + j] |= plane_val;
+            }
+            if a[k] & 8 != 0 {
+                b[s00 + j] |= plane_val;
+            }
+            k += 1;
+        }
+
+        i += 2;
+    }
+    if i < nx {
+        // Column size is odd, do last row
+        // s10, s10+1 are off edge
+        let s00 = n * i;
+
+        let mut j = 0;
+        while j + 1 < ny {
+            if a[k] & 4 != 0 {
+                b[s00 + j + 1] |= plane_val;
+            }
+            if a[k] & 8 != 0 {
+                b[s00 + j] |= plane_val;
+            }
+
+            j += 2;
+            k += 1;
+        }
+        if j < ny {
+            // Both row and column size are odd, do corner element
+            // s00+1, s10, s10+1 are off edge
+            if a[k] & 8 != 0 {
+                b[s00 + j] |= plane_val;
+            }
+        }
+    }
+}
+
+/// Multiply the coefficients by the digitization scale factor
+fn undigitize(a: &mut [i64], scale: i32) -> Result<(), Error> {
+    if scale <= 1 {
+        return Ok(());
+    }
+
+    for v in a.iter_mut() {
+        *v = v.checked_mul(scale as i64).ok_or(Error::StaticError(
+            "H-compressed tile has a coefficient overflowing once scaled",
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Unshuffle the `n` elements of `a` spaced by `n2`. The first half of the elements
+/// goes to the even positions and the second half to the odd positions
+fn unshuffle(a: &mut [i64], n: usize, n2: usize, tmp: &mut [i64]) {
+    let nhalf = n.div_ceil(2);
+
+    // Copy 2nd half of array to tmp
+    for (i, t) in (nhalf..n).zip(tmp.iter_mut()) {
+        *t = a[n2 * i];
+    }
+    // Distribute 1st half of array to even elements
+    for i in (0..nhalf).rev() {
+        a[2 * n2 * i] = a[n2 * i];
+    }
+    // Now distribute 2nd half of array (in tmp) to odd elements
+    for (i, t) in (1..n).step_by(2).zip(tmp.iter()) {
+        a[n2 * i] = *t;
+    }
+}
+
+/// Round `v` to a multiple of `mask`
+fn round(v: i64, prnd: i64, nrnd: i64, mask: i64) -> i64 {
+    v.wrapping_add(if v >= 0 { prnd } else { nrnd }) & mask
+}
+
+/// Inverse H-transform of the `nx * ny` coefficients
+///
+/// The sums wrap around as in CFITSIO. They do not overflow for the coefficients of a valid
+/// tile, but the ones of a corrupted tile are arbitrary
+fn hinv(a: &mut [i64], nx: usize, ny: usize) {
+    // log2n is log2 of max(nx,ny) rounded up to next power of 2
+    let nmax = nx.max(ny);
+    let log2n = log2_ceil(nmax);
+    if log2n == 0 {
+        // A single pixel, its value is the sum of all pixels
+        return;
+    }
+
+    // Temporary storage for shuffling elements
+    let mut tmp = vec![0_i64; nmax.div_ceil(2)];
+
+    // Set up masks, rounding parameters
+    let mut shift = 1;
+    let mut bit0 = 1_i64 << (log2n - 1);
+    let mut bit1 = bit0 << 1;
+    let bit2 = bit0 << 2;
+    let mut mask0 = -bit0;
+    let mut mask1 = mask0 << 1;
+    let mask2 = mask0 << 2;
+    let mut prnd0 = bit0 >> 1;
+    let mut prnd1 = bit1 >> 1;
+    let prnd2 = bit2 >> 1;
+    let mut nrnd0 = prnd0 - 1;
+    let mut nrnd1 = prnd1 - 1;
+    let nrnd2 = prnd2 - 1;
+
+    // Round h0 to multiple of bit2
+    a[0] = round(a[0], prnd2, nrnd2, mask2);
+
+    // Do log2n expansions
+    // We're indexing a as a 2-D array with dimensions (nx,ny).
+    let mut nxtop = 1;
+    let mut nytop = 1;
+    let mut nxf = nx;
+    let mut nyf = ny;
+    let mut c = 1 << log2n;
+    for k in (0..log2n).rev() {
+        // This somewhat cryptic code generates the sequence
+        // ntop[k-1] = (ntop[k]+1)/2, where ntop[0] is nx or ny
+        c >>= 1;
+        nxtop <<= 1;
+        nytop <<= 1;
+        if nxf <= c {
+            nxtop -= 1;
+        } else {
+            nxf -= c;
+        }
+        if nyf <= c {
+            nytop -= 1;
+        } else {
+            nyf -= c;
+        }
+
+        // Double shift and fix nrnd0 (because prnd0=0) on last pass
+        if k == 0 {
+            nrnd0 = 0;
+            shift = 2;
+        }
+
+        // Unshuffle in each dimension to interleave coefficients
+        for i in 0..nxtop {
+            unshuffle(&mut a[ny * i..], nytop, 1, &mut tmp);
+        }
+        for j in 0..nytop {
+            unshuffle(&mut a[j..], nxtop, ny, &mut tmp);
+        }
+
+        let oddx = nxtop % 2;
+        let oddy = nytop % 2;
+        let mut i = 0;
+        while i < nxtop - oddx {
+            let mut s00 = ny * i;
+            let mut s10 = s00 + ny;
+
+            let mut j = 0;
+            while j < nytop - oddy {
+                let mut h0 = a[s00];
+                let mut hx = a[s10];
+                let mut hy = a[s00 + 1];
+                let mut hc = a[s10 + 1];
+
+                // Round hx and hy to multiple of bit1, hc to multiple of bit0
+                // h0 is already a multiple of bit2
+                hx = round(hx, prnd1, nrnd1, mask1);
+                hy = round(hy, prnd1, nrnd1, mask1);
+                hc = round(hc, prnd0, nrnd0, mask0);
+
+                // Propagate bit0 of hc to hx,hy
+                let lowbit0 = hc & bit0;
+                hx = if hx >= 0 {
+                    hx.wrapping_sub(lowbit0)
+                } else {
+                    hx.wrapping_add(lowbit0)
+                };
+                hy = if hy >= 0 {
+                    hy.wrapping_sub(lowbit0)
+                } else {
+                    hy.wrapping_add(lowbit0)
+                };
+
+                // Propagate bits 0 and 1 of hc,hx,hy to h0.
+                // This could be simplified if we assume h0>0, but then
+                // the inversion would not be lossless for images with
+                // negative pixels.
+                let lowbit1 = (hc ^ hx ^ hy) & bit1;
+                h0 = if h0 >= 0 {
+                    h0.wrapping_add(lowbit0).wrapping_sub(lowbit1)
+                } else if lowbit0 == 0 {
+                    h0.wrapping_add(lowbit1)
+                } else {
+                    h0.wrapping_add(lowbit0 - lowbit1)
+                };
+
+                // Divide sums by 2 (4 last time)
+                let (sum, diff) = (h0.wrapping_add(hx), h0.wrapping_sub(hx));
+                let (hyc_sum, hyc_diff) = (hy.wrapping_add(hc), hy.wrapping_sub(hc));
+                a[s10 + 1] = sum.wrapping_add(hyc_sum) >> shift;
+                a[s10] = sum.wrapping_sub(hyc_sum) >> shift;
+                a[s00 + 1] = diff.wrapping_add(hyc_diff) >> shift;
+                a[s00] = diff.wrapping_sub(hyc_diff) >> shift;
+
+                s00 += 2;
+                s10 += 2;
+                j += 2;
+            }
+            if oddy == 1 {
+                // Do last element in row if row length is odd
+                // s00+1, s10+1 are off edge
+                let mut h0 = a[s00];
+                let hx = round(a[s10], prnd1, nrnd1, mask1);
+                let lowbit1 = hx & bit1;
+                h0 = if h0 >= 0 {
+                    h0.wrapping_sub(lowbit1)
+                } else {
+                    h0.wrapping_add(lowbit1)
+                };
+                a[s10] = h0.wrapping_add(hx) >> shift;
+                a[s00] = h0.wrapping_sub(hx) >> shift;
+            }
+
+            i += 2;
+        }
+        if oddx == 1 {
+            // Do last row if column length is odd
+            // s10, s10+1 are off edge
+            let mut s00 = ny * i;
+
+            let mut j = 0;
+            while j < nytop - oddy {
+                let mut h0 = a[s00];
+                let hy = round(a[s00 + 1], prnd1, nrnd1, mask1);
+                let lowbit1 = hy & bit1;
+                h0 = if h0 >= 0 {
+                    h0.wrapping_sub(lowbit1)
+                } else {
+                    h0.wrapping_add(lowbit1)
+                };
+                a[s00 + 1] = h0.wrapping_add(hy) >> shift;
+                a[s00] = h0.wrapping_sub(hy) >> shift;
+
+                s00 += 2;
+                j += 2;
+            }
+            if oddy == 1 {
+                // Do corner element if both row and column lengths are odd
+                // s00+1, s10, s10+1 are off edge
+                a[s00] >>= shift;
+            }
+        }
+
+        // Divide all the masks and rounding values by 2
+        bit1 = bit0;
+        bit0 >>= 1;
+        mask1 = mask0;
+        mask0 >>= 1;
+        prnd1 = prnd0;
+        prnd0 >>= 1;
+        nrnd1 = nrnd0;
+        nrnd0 = prnd0 - 1;
+    }
+}
+
+/// A reader of the compressed stream, either byte by byte or bit by bit
+struct BitReader<'a> {
+    input: &'a [u8],
+    /// Position of the next byte to read
+    pos: usize,
+    /// Bits waiting to be input
+    buffer: u32,
+    /// Number of bits still in buffer
+    bits_to_go: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self {
+            input,
+            pos: 0,
+            buffer: 0,
+            bits_to_go: 0,
+        }
+    }
+
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        let byte = *self
+            .input
+            .get(self.pos)
+            .ok_or(Error::StaticError("H-compressed tile ends unexpectedly"))?;
+        self.pos += 1;
+
+        Ok(byte)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, Error> {
+        let mut bytes = [0_u8; 4];
+        for b in bytes.iter_mut() {
+            *b = self.read_u8()?;
+        }
+
+        Ok(i32::from_be_bytes(bytes))
+    }
+
+    fn read_i64(&mut self) -> Result<i64, Error> {
+        let mut bytes = [0_u8; 8];
+        for b in bytes.iter_mut() {
+            *b = self.read_u8()?;
+        }
+
+        Ok(i64::from_be_bytes(bytes))
+    }
+
+    fn start_inputing_bits(&mut self) {
+        // Buffer starts out with no bits in it
+        self.buffer = 0;
+        self.bits_to_go = 0;
+    }
+
+    fn input_bit(&mut self) -> Result<u32, Error> {
+        if self.bits_to_go == 0 {
+            // Read the next byte if no bits are left
+            self.buffer = self.read_u8()? as u32;
+            self.bits_to_go = 8;
+        }
+
+        // Return the next bit
+        self.bits_to_go -= 1;
+        Ok((self.buffer >> self.bits_to_go) & 1)
+    }
+
+    fn input_nbits(&mut self, n: u32) -> Result<u32, Error> {
+        if self.bits_to_go < n {
+            // Need another byte's worth of bits
+            self.buffer = (self.buffer << 8) | self.read_u8()? as u32;
+            self.bits_to_go += 8;
+        }
+
+        // Now pick off the first n bits
+        self.bits_to_go -= n;
+        Ok((self.buffer >> self.bits_to_go) & ((1 << n) - 1))
+    }
+
+    fn input_nybble(&mut self) -> Result<u8, Error> {
+        Ok(self.input_nbits(4)? as u8)
+    }
+
+    /// Huffman decoding for fixed codes
+    ///
+    /// Coded values range from 0-15
+    /// Huffman code values (hex):
+    ///   3e, 00, 01, 08, 02, 09, 1a, 1b,
+    ///   03, 1c, 0a, 1d, 0b, 1e, 3f, 0c
+    /// and number of bits in each code:
+    ///   6,  3,  3,  4,  3,  4,  5,  5,
+    ///   3,  5,  4,  5,  4,  5,  6,  4
+    fn input_huffman(&mut self) -> Result<u8, Error> {
+        // Get first 3 bits to start
+        let mut c = self.input_nbits(3)?;
+        if c < 4 {
+            // This is all we need, return 1,2,4,8 for c=0,1,2,3
+            return Ok(1 << c);
+        }
+
+        // Get the next bit
+        c = self.input_bit()? | (c << 1);
+        match c {
+            8 => return Ok(3),
+            9 => return Ok(5),
+            10 => return Ok(10),
+            11 => return Ok(12),
+            12 => return Ok(15),
+            _ => (),
+        }
+
+        // Get yet another bit
+        c = self.input_bit()? | (c << 1);
+        match c {
+            26 => return Ok(6),
+            27 => return Ok(7),
+            28 => return Ok(9),
+            29 => return Ok(11),
+            30 => return Ok(13),
+            _ => (),
+        }
+
+        // Need the 6th bit
+        c = self.input_bit()? | (c << 1);
+        if c == 62 {
+            Ok(0)
+        } else {
+            Ok(14)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::decompress;
+
+    /// A 2x2 tile whose coefficients are h0 = 10, hx = 4, hy = -2, hc = 0.
+    /// The hy bit planes are written directly and the hx ones are quadtree-coded.
+    const TILE_2X2: [u8; 38] = [
+        // magic code
+        0xDD, 0x99, //
+        // nx, ny and scale
+        0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0, 0, //
+        // sum of all pixels
+        0, 0, 0, 0, 0, 0, 0, 10, //
+        // number of bit planes of the quadrants
+        0, 3, 0, //
+        // bit planes followed by the EOF nybble
+        0x00, 0x08, 0x00, 0xF6, 0x00, 0x00, 0x00, //
+        // sign bits
+        0x80, // padding
+        0, 0, 0, 0, 0,
+    ];
+
+    #[test]
+    fn test_hdecompress_2x2() {
+        let pixels = decompress(&TILE_2X2, 4).unwrap();
+        assert_eq!(pixels, [2, 1, 4, 3]);
+    }
+
+    #[test]
+    fn test_hdecompress_constant() {
+        // All coefficients but the sum of the pixels are null
+        let mut tile = vec![0xDD, 0x99, 0, 0, 0, 3, 0, 0, 0, 5, 0, 0, 0, 0];
+        tile.extend(((3 * 5 * 7) as i64).to_be_bytes());
+        tile.extend([0, 0, 0, 0]);
+
+        let pixels = decompress(&tile, 15).unwrap();
+        assert_eq!(pixels, [7; 15]);
+    }
+
+    #[test]
+    fn test_hdecompress_bad_magic() {
+        assert!(decompress(&[0xDD, 0x98], 4).is_err());
+        assert!(decompress(&TILE_2X2[..20], 4).is_err());
+    }
+
+    /// A tile of constant pixels, whose coefficients are all null but `h0`
+    fn constant_tile(nx: i32, ny: i32, h0: i64) -> Vec<u8> {
+        let mut tile = vec![0xDD, 0x99];
+        tile.extend(nx.to_be_bytes());
+        tile.extend(ny.to_be_bytes());
+        tile.extend(0_i32.to_be_bytes());
+        tile.extend(h0.to_be_bytes());
+        tile.extend([0, 0, 0, 0]);
+        tile
+    }
+
+    #[test]
+    fn test_hdecompress_thin_tiles() {
+        // Some quadrants are empty
+        assert_eq!(decompress(&constant_tile(1, 1, 7), 1).unwrap(), [7]);
+        assert_eq!(decompress(&constant_tile(1, 2, 28), 2).unwrap(), [7; 2]);
+        assert_eq!(decompress(&constant_tile(1, 5, 105), 5).unwrap(), [7; 5]);
+        assert_eq!(decompress(&constant_tile(5, 1, 105), 5).unwrap(), [7; 5]);
+
+        // A quadtree-coded bit plane in an empty quadrant
+        let mut tile = constant_tile(1, 1, 7);
+        tile[22..25].copy_from_slice(&[0, 1, 0]);
+        tile[25] = 0xF0;
+        assert!(decompress(&tile, 1).is_err());
+    }
+
+    #[test]
+    fn test_hdecompress_size_mismatch() {
+        // The size is checked before allocating the coefficients
+        let tile = constant_tile(i32::MAX, i32::MAX, 0);
+        assert!(decompress(&tile, 16).is_err());
+        assert!(decompress(&constant_tile(4, 4, 112), 15).is_err());
+    }
+
+    #[test]
+    fn test_hdecompress_overflow() {
+        // A scale overflowing the coefficients
+        let mut tile = constant_tile(2, 2, i64::MAX / 2);
+        tile[10..14].copy_from_slice(&i32::MAX.to_be_bytes());
+        assert!(decompress(&tile, 4).is_err());
+
+        // 64 bit planes do not fit in a coefficient
+        for quadrant in 0..3 {
+            let mut tile = constant_tile(2, 2, 0);
+            tile[22 + quadrant] = 64;
+            assert!(decompress(&tile, 4).is_err());
+        }
+
+        // The sum of all pixels is arbitrary in a corrupted tile
+        assert!(decompress(&constant_tile(4, 4, i64::MAX), 16).is_ok());
+        assert!(decompress(&constant_tile(3, 5, i64::MIN), 15).is_ok());
+    }
+}
@@ -0,0 +1,147 @@
+//! Inspection of the tiles of a compressed image without decompressing them
+//!
+//! Only the array descriptors of the binary table rows are read, giving for each tile
+//! the field storing it, its compressed size and its offset in the heap.
+
+use std::fmt::Debug;
+use std::io::{Read, Seek};
+
+use super::pixels::{non_empty_array_descriptor, Tile};
+use crate::error::Error;
+use crate::hdu::data::iter::TryNext;
+use crate::hdu::header::extension::bintable::{BinTable, TFormType};
+use crate::TableRowData;
+
+/// Field of the binary table storing a tile
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TileStorage {
+    /// COMPRESSED_DATA, compressed with ZCMPTYPE
+    Compressed,
+    /// GZIP_COMPRESSED_DATA, for the tiles that could not be compressed with ZCMPTYPE
+    GzipCompressed,
+    /// UNCOMPRESSED_DATA, for the tiles that could not be compressed at all
+    Uncompressed,
+}
+
+/// Position and storage of a tile
+#[derive(Debug, PartialEq, Clone)]
+pub struct TileLayout {
+    /// Pixel bounds of the tile in the image
+    pub tile: Tile,
+    /// Field storing the tile
+    pub storage: TileStorage,
+    /// Size in bytes of the stored tile. 0 for an empty tile
+    pub num_bytes: u64,
+    /// Byte offset of the stored tile from the beginning of the heap
+    pub heap_offset: u64,
+}
+
+/// An iterator over the layout of the tiles of a compressed image
+///
+/// The pixels of the tiles are neither read nor decompressed
+#[derive(Debug)]
+pub struct TileLayouts<R> {
+    /// An iterator over the rows of the binary table, not reading the heap
+    row_it: TableRowData<R>,
+
+    z_tile: Box<[usize]>,
+    z_naxis: Box<[usize]>,
+    /// The fields storing the tiles, with their index and the size in bytes of their elements
+    fields: Vec<(TileStorage, usize, u64)>,
+    /// Size in bytes of the uncompressed image
+    uncompressed_byte_size: u64,
+}
+
+impl<R> TileLayouts<R> {
+    pub(crate) fn new(
+        row_it: TableRowData<R>,
+        z_tile: Box<[usize]>,
+        z_naxis: Box<[usize]>,
+        fields: [(TileStorage, Option<usize>); 3],
+        uncompressed_byte_size: u64,
+    ) -> Self {
+        let fields = fields
+            .iter()
+            .filter_map(|&(storage, idx)| {
+                let idx = idx?;
+                let elem_byte_size = elem_byte_size(row_it.get_ctx(), idx);
+                Some((storage, idx, elem_byte_size))
+            })
+            .collect();
+
+        Self {
+            row_it,
+            z_tile,
+            z_naxis,
+            fields,
+            uncompressed_byte_size,
+        }
+    }
+}
+
+impl<R> TileLayouts<R>
+where
+    R: Read + Seek + Debug,
+{
+    /// Ratio between the size of the uncompressed image and the total size of its stored tiles
+    ///
+    /// The null pixel masks are not taken into account. The tiles already returned by the
+    /// iterator are not taken into account either, so this must be called on a fresh iterator.
+    pub fn compression_ratio(self) -> f64 {
+        let uncompressed_byte_size = self.uncompressed_byte_size;
+        let num_bytes = self.map(|layout| layout.num_bytes).sum::<u64>();
+
+        uncompressed_byte_size as f64 / num_bytes as f64
+    }
+}
+
+impl<R> TryNext for TileLayouts<R>
+where
+    R: Read + Seek + Debug,
+{
+    type Item = TileLayout;
+
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(row_data) = self.row_it.try_next()? else {
+            return Ok(None);
+        };
+        let tile = Tile::new(self.row_it.get_row_idx() - 1, &self.z_tile, &self.z_naxis);
+
+        // A tile is stored in the first non empty of these fields. If all are empty,
+        // it is reported as an empty COMPRESSED_DATA tile
+        let (storage, num_bytes, heap_offset) = self
+            .fields
+            .iter()
+            .find_map(|&(storage, idx, elem_byte_size)| {
+                let (num_elems, heap_offset) = non_empty_array_descriptor(&row_data[idx])?;
+                Some((storage, num_elems * elem_byte_size, heap_offset))
+            })
+            .unwrap_or((TileStorage::Compressed, 0, 0));
+
+        Ok(Some(TileLayout {
+            tile,
+            storage,
+            num_bytes,
+            heap_offset,
+        }))
+    }
+}
+
+impl<R> Iterator for TileLayouts<R>
+where
+    R: Read + Seek + Debug,
+{
+    type Item = TileLayout;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
+    }
+}
+
+/// Size in bytes of the elements of a variable length array field
+fn elem_byte_size(ctx: &BinTable, idx: usize) -> u64 {
+    match ctx.tforms[idx] {
+        TFormType::P { t_byte_size, .. } | TFormType::Q { t_byte_size, .. } => t_byte_size,
+        _ => 1,
+    }
+}
@@ -1,9 +1,15 @@
 mod dithering;
+mod hcompress;
+pub mod layout;
 pub mod pixels;
+mod plio;
 mod rice;
+pub mod table;
 
-use dithering::{N_RANDOM, RAND_VALUES};
+use dithering::Dither;
 
+use super::DataValue;
+use crate::error::{Error, Location};
 use crate::hdu::header::extension::bintable::{BinTable, TileCompressedImage, ZQuantiz};
 use crate::hdu::header::Header;
 
@@ -11,6 +17,14 @@ pub trait Keywords {
     type T;
 
     fn new(header: &Header<BinTable>, config: &TileCompressedImage) -> Self;
+
+    /// Update the values given for each tile before decompressing it, e.g. the ones of
+    /// the ZSCALE and ZZERO fields
+    ///
+    /// `row_idx` is the number of rows read, i.e. the index of the tile starting from 1
+    fn start_tile(&mut self, _row_data: &[DataValue], _row_idx: usize) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -41,9 +55,11 @@ where
 #[derive(Debug)]
 pub struct F32Keywords {
     /// Idx column storing z_scale values for each tile
-    z_scale_idx: usize,
+    /// Absent for losslessly compressed values or if given by the ZSCALE keyword
+    z_scale_idx: Option<usize>,
     /// Idx column storing z_zero values for each tile
-    z_zero_idx: usize,
+    /// Absent for losslessly compressed values or if given by the ZZERO keyword
+    z_zero_idx: Option<usize>,
     /// Idx column storing z_blank values for each tile
     z_blank_idx: Option<usize>,
 
@@ -53,23 +69,27 @@ pub struct F32Keywords {
     z_quantiz: ZQuantiz,
 
     /// Current value of ZSCALE field (floating point case)
-    scale: f32,
+    scale: f64,
     /// Current value of ZZERO field (floating point case)
-    zero: f32,
+    zero: f64,
     /// Current value of ZBLANK (floating point case)
     /// Stores the integer value that evaluates to a floating point NAN
     z_blank: Option<i32>,
     /// Current value of ZBLANK field (float and integer case)
     /// Current quantiz state
     quantiz: Quantiz,
+    /// ZSCALE or ZZERO keyword not being a floating point
+    invalid_keyword: Option<&'static str>,
 }
 
 #[derive(Debug)]
 pub struct F64Keywords {
     /// Idx column storing z_scale values for each tile
-    z_scale_idx: usize,
+    /// Absent for losslessly compressed values or if given by the ZSCALE keyword
+    z_scale_idx: Option<usize>,
     /// Idx column storing z_zero values for each tile
-    z_zero_idx: usize,
+    /// Absent for losslessly compressed values or if given by the ZZERO keyword
+    z_zero_idx: Option<usize>,
     /// Idx column storing z_blank values for each tile
     z_blank_idx: Option<usize>,
 
@@ -79,87 +99,70 @@ pub struct F64Keywords {
     z_quantiz: ZQuantiz,
 
     /// Current value of ZSCALE field (floating point case)
-    scale: f32,
+    scale: f64,
     /// Current value of ZZERO field (floating point case)
-    zero: f32,
+    zero: f64,
     /// Current value of ZBLANK (floating point case)
     /// Stores the integer value that evaluates to a floating point NAN
     z_blank: Option<i32>,
     /// Current value of ZBLANK field (float and integer case)
     /// Current quantiz state
     quantiz: Quantiz,
+    /// ZSCALE or ZZERO keyword not being a floating point
+    invalid_keyword: Option<&'static str>,
 }
 
+/// Quantized value of the pixels that were exactly 0.0 with SUBTRACTIVE_DITHER_2
+const ZERO_VALUE: i32 = -2147483646;
+
 impl F64Keywords {
     /// Unquantize the integer decoded value to the real floating point value
-    fn unquantize(&mut self, value: i32) -> f32 {
+    ///
+    /// The values are computed in double precision, as CFITSIO does
+    fn unquantize(&mut self, value: i32) -> f64 {
+        // Dithered tiles consume a random value for every pixel, blank ones included
+        let ri = self.quantiz.next_random_value();
+
         // map the NaN if value corresponds to BLANK
-        if let Some(z_blank) = self.z_blank {
-            if z_blank == value {
-                return f32::NAN;
-            }
+        if self.z_blank == Some(value) {
+            return f64::NAN;
         }
 
-        match &mut self.quantiz {
-            Quantiz::NoDither => (value as f32) * self.scale + self.zero,
-            Quantiz::SubtractiveDither1 { i1 } => {
-                let ri = RAND_VALUES[*i1];
-                // increment i1 for the next pixel
-                *i1 = (*i1 + 1) % N_RANDOM;
-
-                ((value as f32) - ri + 0.5) * self.scale + self.zero
-            }
-            Quantiz::SubtractiveDither2 { i1 } => {
-                // FIXME: i32::MIN is -2147483648 !
-                if value == -2147483647 {
-                    *i1 = (*i1 + 1) % N_RANDOM;
-
-                    0.0
-                } else {
-                    let ri = RAND_VALUES[*i1];
-                    // increment i1 for the next pixel
-                    *i1 = (*i1 + 1) % N_RANDOM;
+        // SUBTRACTIVE_DITHER_2 preserves the pixels being exactly zero
+        if value == ZERO_VALUE && matches!(self.quantiz, Quantiz::SubtractiveDither2(_)) {
+            return 0.0;
+        }
 
-                    ((value as f32) - ri + 0.5) * self.scale + self.zero
-                }
-            }
+        if let Some(ri) = ri {
+            ((value as f64) - ri as f64 + 0.5) * self.scale + self.zero
+        } else {
+            (value as f64) * self.scale + self.zero
         }
     }
 }
 
 impl F32Keywords {
     /// Unquantize the integer decoded value to the real floating point value
+    ///
+    /// The values are computed in double precision before being cast, as CFITSIO does
     fn unquantize(&mut self, value: i32) -> f32 {
+        // Dithered tiles consume a random value for every pixel, blank ones included
+        let ri = self.quantiz.next_random_value();
+
         // map the NaN if value corresponds to BLANK
-        if let Some(z_blank) = self.z_blank {
-            if z_blank == value {
-                return f32::NAN;
-            }
+        if self.z_blank == Some(value) {
+            return f32::NAN;
         }
 
-        match &mut self.quantiz {
-            Quantiz::NoDither => (value as f32) * self.scale + self.zero,
-            Quantiz::SubtractiveDither1 { i1 } => {
-                let ri = RAND_VALUES[*i1];
-                // increment i1 for the next pixel
-                *i1 = (*i1 + 1) % N_RANDOM;
-
-                ((value as f32) - ri + 0.5) * self.scale + self.zero
-            }
-            Quantiz::SubtractiveDither2 { i1 } => {
-                // FIXME: i32::MIN is -2147483648 !
-                if value == -2147483647 {
-                    *i1 = (*i1 + 1) % N_RANDOM;
-
-                    0.0
-                } else {
-                    let ri = RAND_VALUES[*i1];
-                    // increment i1 for the next pixel
-                    *i1 = (*i1 + 1) % N_RANDOM;
+        // SUBTRACTIVE_DITHER_2 preserves the pixels being exactly zero
+        if value == ZERO_VALUE && matches!(self.quantiz, Quantiz::SubtractiveDither2(_)) {
+            return 0.0;
+        }
 
-                    ((value as f32) - ri + 0.5) * self.scale + self.zero
-                }
-            }
+        if let Some(ri) = ri {
+            (((value as f64) - ri as f64 + 0.5) * self.scale + self.zero) as f32
+        } else {
+            ((value as f64) * self.scale + self.zero) as f32
         }
     }
 }
@@ -167,6 +170,39 @@ impl F32Keywords {
 impl Keywords for F32Keywords {
     type T = f32;
 
+    fn start_tile(&mut self, row_data: &[DataValue], row_idx: usize) -> Result<(), Error> {
+        if let Some(keyword) = self.invalid_keyword {
+            return Err(invalid_float_keyword(keyword));
+        }
+
+        self.quantiz = match self.z_quantiz {
+            ZQuantiz::SubtractiveDither1 => {
+                Quantiz::SubtractiveDither1(Dither::new(row_idx, self.z_dither_0))
+            }
+            ZQuantiz::SubtractiveDither2 => {
+                Quantiz::SubtractiveDither2(Dither::new(row_idx, self.z_dither_0))
+            }
+            _ => Quantiz::NoDither,
+        };
+
+        if let Some(idx) = self.z_scale_idx {
+            self.scale = float_value(&row_data[idx], "ZSCALE field must be a floating point")?;
+        }
+
+        if let Some(idx) = self.z_zero_idx {
+            self.zero = float_value(&row_data[idx], "ZZERO field must be a floating point")?;
+        }
+
+        if let Some(idx) = self.z_blank_idx {
+            self.z_blank = Some(integer_value(
+                &row_data[idx],
+                "ZBLANK field must be an integer",
+            )?);
+        }
+
+        Ok(())
+    }
+
     fn new(header: &Header<BinTable>, config: &TileCompressedImage) -> Self {
         let TileCompressedImage {
             z_dither_0,
@@ -175,8 +211,11 @@ impl Keywords for F32Keywords {
         } = config;
 
         let ctx = header.get_xtension();
-        let z_scale_idx = ctx.find_field_by_ttype("ZSCALE").unwrap();
-        let z_zero_idx = ctx.find_field_by_ttype("ZZERO").unwrap();
+        // ZSCALE and ZZERO are either given for each tile in a column, or for the whole
+        // image as keywords. Their presence has been checked when parsing the header
+        let z_scale_idx = ctx.find_field_by_ttype("ZSCALE");
+        let z_zero_idx = ctx.find_field_by_ttype("ZZERO");
+        let (scale, zero, invalid_keyword) = scaling_keywords(header, z_scale_idx, z_zero_idx);
 
         let mut z_blank = None;
         let z_blank_idx = ctx
@@ -193,8 +232,8 @@ impl Keywords for F32Keywords {
         // If no ZBLANK colum has been found then check the header keywords (ZBLANK for float, BLANK for integer)
 
         Self {
-            scale: 1.0,
-            zero: 0.0,
+            scale,
+            zero,
             z_scale_idx,
             z_zero_idx,
             z_blank_idx,
@@ -202,6 +241,7 @@ impl Keywords for F32Keywords {
             quantiz: Quantiz::NoDither,
             z_quantiz: z_quantiz.clone().unwrap_or(ZQuantiz::NoDither),
             z_dither_0: z_dither_0.unwrap_or(0),
+            invalid_keyword,
         }
     }
 }
@@ -209,6 +249,39 @@ impl Keywords for F32Keywords {
 impl Keywords for F64Keywords {
     type T = f64;
 
+    fn start_tile(&mut self, row_data: &[DataValue], row_idx: usize) -> Result<(), Error> {
+        if let Some(keyword) = self.invalid_keyword {
+            return Err(invalid_float_keyword(keyword));
+        }
+
+        self.quantiz = match self.z_quantiz {
+            ZQuantiz::SubtractiveDither1 => {
+                Quantiz::SubtractiveDither1(Dither::new(row_idx, self.z_dither_0))
+            }
+            ZQuantiz::SubtractiveDither2 => {
+                Quantiz::SubtractiveDither2(Dither::new(row_idx, self.z_dither_0))
+            }
+            _ => Quantiz::NoDither,
+        };
+
+        if let Some(idx) = self.z_scale_idx {
+            self.scale = float_value(&row_data[idx], "ZSCALE field must be a floating point")?;
+        }
+
+        if let Some(idx) = self.z_zero_idx {
+            self.zero = float_value(&row_data[idx], "ZZERO field must be a floating point")?;
+        }
+
+        if let Some(idx) = self.z_blank_idx {
+            self.z_blank = Some(integer_value(
+                &row_data[idx],
+                "ZBLANK field must be an integer",
+            )?);
+        }
+
+        Ok(())
+    }
+
     fn new(header: &Header<BinTable>, config: &TileCompressedImage) -> Self {
         let TileCompressedImage {
             z_dither_0,
@@ -217,8 +290,11 @@ impl Keywords for F64Keywords {
         } = config;
 
         let ctx = header.get_xtension();
-        let z_scale_idx = ctx.find_field_by_ttype("ZSCALE").unwrap();
-        let z_zero_idx = ctx.find_field_by_ttype("ZZERO").unwrap();
+        // ZSCALE and ZZERO are either given for each tile in a column, or for the whole
+        // image as keywords. Their presence has been checked when parsing the header
+        let z_scale_idx = ctx.find_field_by_ttype("ZSCALE");
+        let z_zero_idx = ctx.find_field_by_ttype("ZZERO");
+        let (scale, zero, invalid_keyword) = scaling_keywords(header, z_scale_idx, z_zero_idx);
 
         let mut z_blank = None;
         let z_blank_idx = ctx
@@ -235,8 +311,8 @@ impl Keywords for F64Keywords {
         // If no ZBLANK colum has been found then check the header keywords (ZBLANK for float, BLANK for integer)
 
         Self {
-            scale: 1.0,
-            zero: 0.0,
+            scale,
+            zero,
             z_scale_idx,
             z_zero_idx,
             z_blank_idx,
@@ -244,6 +320,7 @@ impl Keywords for F64Keywords {
             quantiz: Quantiz::NoDither,
             z_quantiz: z_quantiz.clone().unwrap_or(ZQuantiz::NoDither),
             z_dither_0: z_dither_0.unwrap_or(0),
+            invalid_keyword,
         }
     }
 }
@@ -293,26 +370,115 @@ impl Keywords for I32Keywords {
     }
 }
 
+#[derive(Debug)]
+pub struct I64Keywords {
+    _blank: Option<i64>,
+}
+
+impl Keywords for I64Keywords {
+    type T = i64;
+
+    fn new(header: &Header<BinTable>, _: &TileCompressedImage) -> Self {
+        let _blank = header.get_parsed::<Self::T>("BLANK").ok();
+
+        Self { _blank }
+    }
+}
+
+/// Get the value of a floating point field, e.g. ZSCALE or ZZERO
+///
+/// The format of the field is not checked when parsing the header, `err` is returned
+/// if it is not a floating point
+fn float_value(value: &DataValue, err: &'static str) -> Result<f64, Error> {
+    match *value {
+        DataValue::Float { value, .. } => Ok(value as f64),
+        DataValue::Double { value, .. } => Ok(value),
+        _ => Err(Error::StaticError(err)),
+    }
+}
+
+/// Get ZSCALE and ZZERO from the header keywords when they are not given in a column,
+/// an absent keyword leaving the values unscaled
+///
+/// A keyword which is not a floating point is returned to be reported when reading the tiles,
+/// as a field of the wrong type is
+fn scaling_keywords(
+    header: &Header<BinTable>,
+    z_scale_idx: Option<usize>,
+    z_zero_idx: Option<usize>,
+) -> (f64, f64, Option<&'static str>) {
+    let keyword = |idx: Option<usize>, keyword: &'static str, default: f64| match (
+        idx,
+        header.get(keyword),
+    ) {
+        (None, Some(_)) => header.get_parsed::<f64>(keyword).map_err(|_| keyword),
+        _ => Ok(default),
+    };
+
+    match (
+        keyword(z_scale_idx, "ZSCALE", 1.0),
+        keyword(z_zero_idx, "ZZERO", 0.0),
+    ) {
+        (Ok(scale), Ok(zero)) => (scale, zero, None),
+        (Err(keyword), _) | (_, Err(keyword)) => (1.0, 0.0, Some(keyword)),
+    }
+}
+
+fn invalid_float_keyword(keyword: &'static str) -> Error {
+    Error::DynamicError(format!("{keyword} keyword must be a floating point"))
+        .at(Location::keyword(keyword))
+}
+
+/// Get the value of an integer field, e.g. ZBLANK
+///
+/// The format of the field is not checked when parsing the header, `err` is returned
+/// if it is not an integer
+fn integer_value(value: &DataValue, err: &'static str) -> Result<i32, Error> {
+    match *value {
+        DataValue::UnsignedByte { value, .. } => Ok(value as i32),
+        DataValue::Short { value, .. } => Ok(value as i32),
+        DataValue::Integer { value, .. } => Ok(value),
+        DataValue::Long { value, .. } => Ok(value as i32),
+        _ => Err(Error::StaticError(err)),
+    }
+}
+
 #[derive(Debug)]
 enum Quantiz {
     NoDither,
-    SubtractiveDither1 { i1: usize },
-    SubtractiveDither2 { i1: usize },
+    SubtractiveDither1(Dither),
+    SubtractiveDither2(Dither),
+}
+
+impl Quantiz {
+    /// Returns the random value dithering the current pixel, if any, and moves on to the next pixel
+    fn next_random_value(&mut self) -> Option<f32> {
+        match self {
+            Quantiz::NoDither => None,
+            Quantiz::SubtractiveDither1(dither) | Quantiz::SubtractiveDither2(dither) => {
+                Some(dither.next_value())
+            }
+        }
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use crate::hdu::data::bintable::data::BinaryTableData;
     use image::DynamicImage;
     use std::io::{Cursor, Read};
     use test_case::test_case;
 
-    use crate::{hdu::data::bintable::tile_compressed::pixels::Pixels, Fits, HDU};
+    use crate::error::Error;
+    use crate::mock::MockFits;
+    use crate::{
+        hdu::data::bintable::tile_compressed::pixels::Pixels, Fits, Limits, ParseOptions, TryNext,
+        HDU,
+    };
 
-    /*
     #[test]
     fn test_tile_size_from_row_idx() {
-        use super::pixels::tile_size_from_row_idx;
+        use super::pixels::Tile;
 
         let ground_truth = [
             [300, 200, 150],
@@ -353,10 +519,839 @@ mod tests {
             [100, 100, 50],
         ];
         for (i, &ground_truth) in ground_truth.iter().enumerate() {
-            let tile_s = tile_size_from_row_idx(&[300, 200, 150], &[1000, 500, 350], i);
-            assert_eq!([tile_s[0], tile_s[1], tile_s[2]], ground_truth);
+            let tile = Tile::new(i, &[300, 200, 150], &[1000, 500, 350]);
+            assert_eq!(tile.size[..], ground_truth);
+        }
+
+        let tile = Tile::new(11, &[300, 200, 150], &[1000, 500, 350]);
+        assert_eq!(tile.origin[..], [900, 400, 0]);
+    }
+
+    /// Build an in-memory GZIP_1 tile compressed image of 32-bit integers
+    pub(crate) fn mock_gzip_tiles(
+        z_naxis: [usize; 2],
+        z_tile: [usize; 2],
+        tiles: &[&[i32]],
+    ) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut main_table = vec![];
+        let mut heap = vec![];
+        for tile in tiles {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            for pixel in tile.iter() {
+                encoder.write_all(&pixel.to_be_bytes()).unwrap();
+            }
+            let data = encoder.finish().unwrap();
+
+            main_table.extend((data.len() as i32).to_be_bytes());
+            main_table.extend((heap.len() as i32).to_be_bytes());
+            heap.extend(data);
         }
-    }*/
+        MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'".to_owned(),
+                "BITPIX  =                    8".to_owned(),
+                "NAXIS   =                    2".to_owned(),
+                "NAXIS1  =                    8".to_owned(),
+                format!("NAXIS2  = {:>20}", tiles.len()),
+                format!("PCOUNT  = {:>20}", heap.len()),
+                "GCOUNT  =                    1".to_owned(),
+                "TFIELDS =                    1".to_owned(),
+                "TTYPE1  = 'COMPRESSED_DATA'".to_owned(),
+                "TFORM1  = '1PB     '".to_owned(),
+                "ZIMAGE  =                    T".to_owned(),
+                "ZCMPTYPE= 'GZIP_1  '".to_owned(),
+                "ZBITPIX =                   32".to_owned(),
+                "ZNAXIS  =                    2".to_owned(),
+                format!("ZNAXIS1 = {:>20}", z_naxis[0]),
+                format!("ZNAXIS2 = {:>20}", z_naxis[1]),
+                format!("ZTILE1  = {:>20}", z_tile[0]),
+                format!("ZTILE2  = {:>20}", z_tile[1]),
+                "END".to_owned(),
+            ])
+            .data(&main_table)
+            .data(&heap)
+            .build()
+    }
+
+    #[test]
+    fn test_raster_order() {
+        // A 3x3 image compressed in tiles of 2x2 pixels. The tiles on the borders are smaller
+        let buf = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4], &[2, 5], &[6, 7], &[8]]);
+
+        // Tile order along with the position of the tiles
+        let tiles = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I32(mut it) => {
+                let mut tiles = vec![];
+                while let Some(pixel) = it.next() {
+                    let tile = it.current_tile();
+                    tiles.push((pixel, tile.origin.to_vec(), tile.size.to_vec()));
+                }
+                tiles
+            }
+            _ => unreachable!(),
+        });
+        assert_eq!(tiles.len(), 9);
+        assert_eq!(tiles[4], (2, vec![2, 0], vec![1, 2]));
+        assert_eq!(tiles[8], (8, vec![2, 2], vec![1, 1]));
+
+        let raster = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I32(it) => it.raster(),
+            _ => unreachable!(),
+        });
+        assert_eq!(raster[..], [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let bands = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I32(it) => it.raster_bands().collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(bands.len(), 2);
+        assert_eq!(bands[0][..], [0, 1, 2, 3, 4, 5]);
+        assert_eq!(bands[1][..], [6, 7, 8]);
+    }
+
+    #[test]
+    fn test_truncated_raster_bands() {
+        // The tile table misses its last row, i.e. the second tile of the first band
+        let buf = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4]]);
+
+        let band = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I32(it) => it.raster_bands().try_next(),
+            _ => unreachable!(),
+        });
+        assert_eq!(
+            band,
+            Err(Error::DynamicError(
+                "The tile compressed image ends 2 pixels before the end of a band".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_corrupted_tile() {
+        let mut buf = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4], &[2, 5], &[6, 7], &[8]]);
+        // Break the gzip header of the second tile, the heap starts after the 4 rows of the table
+        let heap = 2 * 2880 + 4 * 8;
+        let second_tile = buf[heap + 1..]
+            .windows(2)
+            .position(|b| b == [0x1f, 0x8b])
+            .unwrap();
+        buf[heap + 1 + second_tile] = 0;
+
+        tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I32(mut it) => {
+                for pixel in [0, 1, 3, 4] {
+                    assert_eq!(it.try_next().unwrap(), Some(pixel));
+                }
+                assert!(it.try_next().is_err());
+                // The iterator is left on the last tile read
+                assert_eq!(it.current_tile().idx, 0);
+
+                // The reader is back in the main table, the following tiles can be read
+                assert_eq!(it.try_next().unwrap(), Some(6));
+                assert_eq!(it.current_tile().origin[..], [0, 2]);
+            }
+            _ => unreachable!(),
+        });
+
+        let raster = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I32(it) => it.try_raster(),
+            _ => unreachable!(),
+        });
+        assert!(raster.is_err());
+    }
+
+    #[test]
+    fn test_tile_layouts() {
+        use super::layout::TileStorage;
+        use crate::hdu::header::extension::bintable::ZCmpType;
+        use crate::hdu::header::extension::Xtension;
+
+        let buf = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4], &[2, 5], &[6, 7], &[8]]);
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+
+        let hdu = hdu_list
+            .find_map(|hdu| match hdu {
+                Ok(HDU::XBinaryTable(hdu)) => Some(hdu),
+                _ => None,
+            })
+            .unwrap();
+
+        // Tile grid from the header
+        let xtension = hdu.get_header().get_xtension();
+        let z_image = xtension.get_z_image().as_ref().unwrap();
+        assert_eq!(z_image.get_z_cmp_type(), ZCmpType::Gzip1);
+        assert_eq!(z_image.get_z_tilen(), [2, 2]);
+        assert_eq!(z_image.get_tile_grid()[..], [2, 2]);
+        assert_eq!(z_image.get_num_tiles(), 4);
+        assert_eq!(z_image.get_tile(1).unwrap().size[..], [1, 2]);
+        assert_eq!(z_image.get_tile(4), None);
+        assert_eq!(z_image.get_uncompressed_byte_size(), 36);
+
+        // Compressed sizes and heap offsets from the array descriptors
+        let heap_size = xtension.get_num_bytes_data_block() - 4 * 8;
+        let BinaryTableData::TileCompressed(pixels) = hdu_list.get_data(&hdu) else {
+            panic!("no tile compressed image found");
+        };
+        let layouts = pixels.tile_layouts().collect::<Vec<_>>();
+        assert_eq!(layouts.len(), 4);
+
+        let mut heap_offset = 0;
+        for (idx, layout) in layouts.iter().enumerate() {
+            assert_eq!(layout.tile, z_image.get_tile(idx).unwrap());
+            assert_eq!(layout.storage, TileStorage::Compressed);
+            assert_eq!(layout.heap_offset, heap_offset);
+            heap_offset += layout.num_bytes;
+        }
+        assert_eq!(heap_offset, heap_size);
+
+        let ratio =
+            tile_compressed_pixels(&buf, |pixels| pixels.tile_layouts().compression_ratio());
+        assert_eq!(ratio, 36.0 / heap_size as f64);
+    }
+
+    /// Build an in-memory tile compressed image of 2x2 pixels made of one gzipped tile
+    /// and optionally its GZIP_1 null pixel mask
+    ///
+    /// # Params
+    ///
+    /// * `cards` - The ZCMPTYPE, ZBITPIX and ZQUANTIZ cards
+    /// * `tile` - The uncompressed bytes of the tile
+    /// * `mask` - The uncompressed bytes of the null pixel mask
+    fn mock_tile_compressed_image(cards: &[&str], tile: &[u8], mask: Option<&[u8]>) -> Vec<u8> {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let gzip = |bytes: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(bytes).unwrap();
+            encoder.finish().unwrap()
+        };
+        let data = gzip(tile);
+        let mask = mask.map(gzip);
+
+        let mut descriptors = vec![(data.len(), 0)];
+        let mut heap = data;
+        let mut header = vec![
+            "XTENSION= 'BINTABLE'".to_owned(),
+            "BITPIX  =                    8".to_owned(),
+            "NAXIS   =                    2".to_owned(),
+        ];
+        let mut fields = vec!["TTYPE1  = 'COMPRESSED_DATA'", "TFORM1  = '1PB     '"];
+        if let Some(mask) = mask {
+            descriptors.push((mask.len(), heap.len()));
+            heap.extend(mask);
+            fields.extend(["TTYPE2  = 'NULL_PIXEL_MASK'", "TFORM2  = '1PB     '"]);
+            fields.push("ZMASKCMP= 'GZIP_1  '");
+        }
+        header.extend([
+            format!("NAXIS1  = {:>20}", 8 * descriptors.len()),
+            "NAXIS2  =                    1".to_owned(),
+            format!("PCOUNT  = {:>20}", heap.len()),
+            "GCOUNT  =                    1".to_owned(),
+            format!("TFIELDS = {:>20}", descriptors.len()),
+            "ZIMAGE  =                    T".to_owned(),
+            "ZNAXIS  =                    2".to_owned(),
+            "ZNAXIS1 =                    2".to_owned(),
+            "ZNAXIS2 =                    2".to_owned(),
+            "ZTILE1  =                    2".to_owned(),
+            "ZTILE2  =                    2".to_owned(),
+        ]);
+        header.extend(fields.iter().chain(cards).map(|card| card.to_string()));
+        header.push("END".to_owned());
+
+        let main_table = descriptors
+            .iter()
+            .flat_map(|&(num_elems, offset)| {
+                [
+                    (num_elems as i32).to_be_bytes(),
+                    (offset as i32).to_be_bytes(),
+                ]
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "EXTEND  =                    T",
+                "END",
+            ])
+            .header(&header)
+            .data(&main_table)
+            .data(&heap)
+            .build()
+    }
+
+    /// Get the tile compressed pixels of the first binary table extension
+    fn tile_compressed_pixels<T>(buf: &[u8], f: impl Fn(Pixels<&mut Cursor<&[u8]>>) -> T) -> T {
+        let mut hdu_list = Fits::from_reader(Cursor::new(buf));
+
+        while let Some(Ok(hdu)) = hdu_list.next() {
+            if let HDU::XBinaryTable(hdu) = hdu {
+                if let BinaryTableData::TileCompressed(pixels) = hdu_list.get_data(&hdu) {
+                    return f(pixels);
+                }
+            }
+        }
+
+        panic!("no tile compressed image found");
+    }
+
+    #[test]
+    fn test_limits() {
+        let buf = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4], &[2, 5], &[6, 7], &[8]]);
+        let options = |limits: Limits| ParseOptions {
+            limits,
+            ..Default::default()
+        };
+        let parse = |limits: Limits| {
+            let mut hdu_list = Fits::from_reader_with(Cursor::new(&buf[..]), options(limits));
+            assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+            hdu_list.next().unwrap()
+        };
+
+        // The tiles have 2x2 pixels of 4 bytes
+        let err = parse(Limits {
+            max_tile_byte_size: 8,
+            ..Default::default()
+        })
+        .expect_err("The tiles are too large");
+        assert_eq!(
+            err.inner(),
+            &Error::LimitExceeded("Tile size in bytes", 16, 8)
+        );
+        assert_eq!(
+            err.location().and_then(|l| l.keyword.as_deref()),
+            Some("ZTILE1")
+        );
+
+        let err = parse(Limits {
+            max_tfields: 0,
+            ..Default::default()
+        })
+        .expect_err("The table has too many fields");
+        assert_eq!(
+            err.inner(),
+            &Error::LimitExceeded("Number of table fields", 1, 0)
+        );
+
+        // The data unit of the table holds four gzip streams, it is larger than 35 bytes
+        let err = parse(Limits {
+            max_data_unit_byte_size: 35,
+            ..Default::default()
+        })
+        .expect_err("The image is too large");
+        assert!(matches!(err.inner(), Error::LimitExceeded(_, _, 35)));
+
+        let err = parse(Limits {
+            max_num_cards: 5,
+            ..Default::default()
+        })
+        .expect_err("The header has too many cards");
+        assert_eq!(
+            err.inner(),
+            &Error::LimitExceeded("Number of header cards", 6, 5)
+        );
+
+        assert!(parse(Limits {
+            max_tile_byte_size: 16,
+            ..Default::default()
+        })
+        .is_ok());
+
+        // The sizes the tiles are computed from are checked before being used
+        for (card, keyword) in [
+            ("ZNAXIS  =     1000000000000000", "ZNAXIS"),
+            ("ZTILE1  =                    0", "ZTILE1"),
+            ("ZNAXIS2 =                   -3", "ZNAXIS2"),
+        ] {
+            let mut buf = buf.clone();
+            let idx = buf
+                .chunks(80)
+                .position(|c| c[..8] == card.as_bytes()[..8])
+                .unwrap();
+            buf[80 * idx..80 * idx + card.len()].copy_from_slice(card.as_bytes());
+
+            let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+            assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+            let err = hdu_list.next().unwrap().expect_err(card);
+            assert_eq!(
+                err.location().and_then(|l| l.keyword.as_deref()),
+                Some(keyword)
+            );
+        }
+
+        // The tiles are read from ZNAXIS even without ZIMAGE, their sizes are checked all the same
+        let mut no_zimage = buf.clone();
+        for (card, replacement) in [
+            ("ZIMAGE  ", "COMMENT"),
+            ("ZTILE1  ", "ZTILE1  =                    0"),
+        ] {
+            let idx = no_zimage
+                .chunks(80)
+                .position(|c| c[..8] == card.as_bytes()[..8])
+                .unwrap();
+            no_zimage[80 * idx..80 * (idx + 1)].fill(b' ');
+            no_zimage[80 * idx..80 * idx + replacement.len()]
+                .copy_from_slice(replacement.as_bytes());
+        }
+        let mut hdu_list = Fits::from_reader(Cursor::new(&no_zimage[..]));
+        assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+        let err = hdu_list.next().unwrap().expect_err("ZTILE1 = 0");
+        assert_eq!(
+            err.location().and_then(|l| l.keyword.as_deref()),
+            Some("ZTILE1")
+        );
+
+        // The compressed tiles are longer than one byte, the iteration stops at the first one
+        let limits = Limits {
+            max_vla_len: 1,
+            ..Default::default()
+        };
+        let mut hdu_list = Fits::from_reader_with(Cursor::new(&buf[..]), options(limits));
+        assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("Should contain a binary table");
+        };
+        let BinaryTableData::TileCompressed(Pixels::I32(it)) = hdu_list.get_data(&hdu) else {
+            panic!("Should contain a tile compressed image");
+        };
+        assert_eq!(it.count(), 0);
+    }
+
+    #[test]
+    fn test_null_pixel_mask() {
+        let tile = [1_i16, 2, 3, -4]
+            .iter()
+            .flat_map(|p| p.to_be_bytes())
+            .collect::<Vec<_>>();
+        let buf = mock_tile_compressed_image(
+            &["ZCMPTYPE= 'GZIP_1  '", "ZBITPIX =                   16"],
+            &tile,
+            Some(&[0, 1, 0, 1]),
+        );
+
+        let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I16(it) => it.nullable().collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels, [Some(1), None, Some(3), None]);
+    }
+
+    #[test]
+    fn test_null_pixel_mask_size() {
+        let tile = [0_u8; 8];
+        let cards = ["ZCMPTYPE= 'GZIP_1  '", "ZBITPIX =                   16"];
+        let first_pixel = |buf: &[u8], limits: Limits| {
+            let options = ParseOptions {
+                limits,
+                ..Default::default()
+            };
+            let mut hdu_list = Fits::from_reader_with(Cursor::new(buf), options);
+            assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+            let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+                panic!("Should contain a binary table");
+            };
+            let BinaryTableData::TileCompressed(Pixels::I16(mut it)) = hdu_list.get_data(&hdu)
+            else {
+                panic!("Should contain a tile compressed image");
+            };
+            it.try_next()
+        };
+
+        // A mask of 1MB for a tile of 4 pixels is not inflated entirely
+        let buf = mock_tile_compressed_image(&cards, &tile, Some(&vec![0; 1 << 20]));
+        assert_eq!(
+            first_pixel(&buf, Limits::default()),
+            Err(Error::StaticError(
+                "Null pixel mask size does not match ZTILEn"
+            ))
+        );
+
+        // The mask values are stored on 4 bytes, i.e. 16 bytes for the tile
+        let buf = mock_tile_compressed_image(&cards, &tile, Some(&[0; 16]));
+        assert_eq!(first_pixel(&buf, Limits::default()), Ok(Some(0)));
+        let limits = Limits {
+            max_tile_byte_size: 8,
+            ..Default::default()
+        };
+        assert_eq!(
+            first_pixel(&buf, limits),
+            Err(Error::LimitExceeded("Null pixel mask size in bytes", 16, 8))
+        );
+    }
+
+    #[test]
+    fn test_gzip_i64() {
+        let values = [1_i64, -2, i64::MAX, i64::MIN];
+        let tile = values
+            .iter()
+            .flat_map(|p| p.to_be_bytes())
+            .collect::<Vec<_>>();
+        let buf = mock_tile_compressed_image(
+            &["ZCMPTYPE= 'GZIP_1  '", "ZBITPIX =                   64"],
+            &tile,
+            None,
+        );
+
+        let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I64(it) => it.collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels, values);
+    }
+
+    #[test]
+    fn test_gzip_u8_i16() {
+        // The pixels are stored at the width of ZBITPIX
+        let values = [1_u8, 0, 127, 255];
+        for z_cmp_type in ["ZCMPTYPE= 'GZIP_1  '", "ZCMPTYPE= 'GZIP_2  '"] {
+            let buf = mock_tile_compressed_image(
+                &[z_cmp_type, "ZBITPIX =                    8"],
+                &values,
+                None,
+            );
+            let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+                Pixels::U8(it) => it.collect::<Vec<_>>(),
+                _ => unreachable!(),
+            });
+            assert_eq!(pixels, values);
+        }
+
+        let values = [1_i16, -2, i16::MAX, i16::MIN];
+        let tile = values
+            .iter()
+            .flat_map(|p| p.to_be_bytes())
+            .collect::<Vec<_>>();
+        let buf = mock_tile_compressed_image(
+            &["ZCMPTYPE= 'GZIP_1  '", "ZBITPIX =                   16"],
+            &tile,
+            None,
+        );
+        let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I16(it) => it.collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels, values);
+
+        // GZIP_2 shuffles the bytes, the most significant ones of all the pixels coming first
+        let bytes = values.iter().map(|p| p.to_be_bytes()).collect::<Vec<_>>();
+        let tile = (0..2)
+            .flat_map(|k| bytes.iter().map(move |b| b[k]))
+            .collect::<Vec<_>>();
+        let buf = mock_tile_compressed_image(
+            &["ZCMPTYPE= 'GZIP_2  '", "ZBITPIX =                   16"],
+            &tile,
+            None,
+        );
+        let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::I16(it) => it.collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels, values);
+    }
+
+    #[test]
+    fn test_gzip_lossless_floats() {
+        let values = [1.5_f32, -2.25, 1e30, f32::MIN_POSITIVE];
+        let tile = values
+            .iter()
+            .flat_map(|p| p.to_be_bytes())
+            .collect::<Vec<_>>();
+        let buf = mock_tile_compressed_image(
+            &[
+                "ZCMPTYPE= 'GZIP_1  '",
+                "ZBITPIX =                  -32",
+                "ZQUANTIZ= 'NONE    '",
+            ],
+            &tile,
+            None,
+        );
+        let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::F32(it) => it.collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels, values);
+
+        // GZIP_2 shuffles the bytes, the most significant ones of all the pixels coming first
+        let values = [1.5_f64, -2.25, 1e300, f64::MIN_POSITIVE];
+        let bytes = values.iter().map(|p| p.to_be_bytes()).collect::<Vec<_>>();
+        let tile = (0..8)
+            .flat_map(|k| bytes.iter().map(move |b| b[k]))
+            .collect::<Vec<_>>();
+        let buf = mock_tile_compressed_image(
+            &[
+                "ZCMPTYPE= 'GZIP_2  '",
+                "ZBITPIX =                  -64",
+                "ZQUANTIZ= 'NONE    '",
+            ],
+            &tile,
+            None,
+        );
+        let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::F64(it) => it.collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels, values);
+    }
+
+    #[test]
+    fn test_unquantize_subtractive_dither_2() {
+        use super::dithering::{Dither, RAND_VALUES};
+        use super::{F32Keywords, Quantiz, ZQuantiz, ZERO_VALUE};
+
+        let mut keywords = F32Keywords {
+            z_scale_idx: None,
+            z_zero_idx: None,
+            z_blank_idx: None,
+            z_dither_0: 1,
+            z_quantiz: ZQuantiz::SubtractiveDither2,
+            scale: 0.25,
+            zero: 100.0,
+            z_blank: Some(-2147483647),
+            quantiz: Quantiz::SubtractiveDither2(Dither::new(1, 1)),
+            invalid_keyword: None,
+        };
+
+        let i1 = (RAND_VALUES[0] * 500.0) as usize;
+        let pixels = [ZERO_VALUE, -2147483647, 8]
+            .iter()
+            .map(|&v| keywords.unquantize(v))
+            .collect::<Vec<_>>();
+
+        assert_eq!(pixels[0], 0.0);
+        assert!(pixels[1].is_nan());
+        // Zero and blank pixels still consume their random value
+        let expected = ((8.0 - RAND_VALUES[i1 + 2] as f64 + 0.5) * 0.25 + 100.0) as f32;
+        assert_eq!(pixels[2], expected);
+    }
+
+    #[test]
+    fn test_fallback_data_fields() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let gzip = |bytes: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(bytes).unwrap();
+            encoder.finish().unwrap()
+        };
+        let be_bytes = |values: &[f32]| {
+            values
+                .iter()
+                .flat_map(|v| v.to_be_bytes())
+                .collect::<Vec<_>>()
+        };
+
+        // A 2x3 image with one tile per row. The first one is quantized and compressed, the second one
+        // is stored in GZIP_COMPRESSED_DATA and the last one in UNCOMPRESSED_DATA
+        let quantized = [1_i32, 2]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+        let rows = [
+            [gzip(&quantized), vec![], vec![]],
+            [vec![], gzip(&be_bytes(&[0.1, f32::NAN])), vec![]],
+            [vec![], vec![], be_bytes(&[-3.5, 1e30])],
+        ];
+
+        let mut main_table = vec![];
+        let mut heap: Vec<u8> = vec![];
+        for row in &rows {
+            for (field, data) in row.iter().enumerate() {
+                // UNCOMPRESSED_DATA stores floats
+                let num_elems = if field == 2 {
+                    data.len() / 4
+                } else {
+                    data.len()
+                };
+                main_table.extend((num_elems as i32).to_be_bytes());
+                main_table.extend((heap.len() as i32).to_be_bytes());
+                heap.extend(data);
+            }
+        }
+        let header = [
+            "XTENSION= 'BINTABLE'",
+            "BITPIX  =                    8",
+            "NAXIS   =                    2",
+            "NAXIS1  =                   24",
+            "NAXIS2  =                    3",
+            &format!("PCOUNT  = {:>20}", heap.len()),
+            "GCOUNT  =                    1",
+            "TFIELDS =                    3",
+            "TTYPE1  = 'COMPRESSED_DATA'",
+            "TFORM1  = '1PB     '",
+            "TTYPE2  = 'GZIP_COMPRESSED_DATA'",
+            "TFORM2  = '1PB     '",
+            "TTYPE3  = 'UNCOMPRESSED_DATA'",
+            "TFORM3  = '1PE     '",
+            "ZIMAGE  =                    T",
+            "ZCMPTYPE= 'GZIP_1  '",
+            "ZBITPIX =                  -32",
+            "ZNAXIS  =                    2",
+            "ZNAXIS1 =                    2",
+            "ZNAXIS2 =                    3",
+            "ZTILE1  =                    2",
+            "ZTILE2  =                    1",
+            "ZQUANTIZ= 'NO_DITHER'",
+            "ZSCALE  =                  2.0",
+            "ZZERO   =                  0.0",
+            "END",
+        ];
+        let bytes = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .header(&header)
+            .data(&main_table)
+            .data(&heap)
+            .build();
+
+        let pixels = tile_compressed_pixels(&bytes, |pixels| match pixels {
+            Pixels::F32(it) => it.collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels[..3], [2.0, 4.0, 0.1]);
+        assert!(pixels[3].is_nan());
+        assert_eq!(pixels[4..], [-3.5, 1e30]);
+    }
+
+    #[test]
+    fn test_zscale_zzero_keywords() {
+        let tile = [0_i32, 2, 4, -2]
+            .iter()
+            .flat_map(|p| p.to_be_bytes())
+            .collect::<Vec<_>>();
+        let cards = [
+            "ZCMPTYPE= 'GZIP_1  '",
+            "ZBITPIX =                  -32",
+            "ZQUANTIZ= 'NO_DITHER'",
+            "ZSCALE  =                  0.5",
+            "ZZERO   =                 10.0",
+        ];
+        let buf = mock_tile_compressed_image(&cards, &tile, None);
+        let pixels = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::F32(it) => it.collect::<Vec<_>>(),
+            _ => unreachable!(),
+        });
+        assert_eq!(pixels, [10.0, 11.0, 12.0, 9.0]);
+
+        // Without ZZERO, neither as a column nor as a keyword, the header cannot be parsed
+        let buf = mock_tile_compressed_image(&cards[..4], &tile, None);
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+        let err = hdu_list.next().unwrap().expect_err("ZZERO is missing");
+        assert_eq!(err.inner(), &Error::FailFindingKeyword("ZZERO".to_owned()));
+    }
+
+    #[test]
+    fn test_zscale_field_format() {
+        let tile = [0_u8; 16];
+        let cards = [
+            "ZCMPTYPE= 'GZIP_1  '",
+            "ZBITPIX =                  -32",
+            "ZQUANTIZ= 'NO_DITHER'",
+            "ZZERO   =                 10.0",
+            "TTYPE2  = 'ZSCALE'",
+            "TFORM2  = '1J      '",
+        ];
+        let mut buf = mock_tile_compressed_image(&cards, &tile, None);
+        // Append a ZSCALE field storing integers to the row of the tile
+        for card in [
+            "NAXIS1  =                   12",
+            "TFIELDS =                    2",
+        ] {
+            let idx = buf
+                .chunks(80)
+                .position(|c| c[..8] == card.as_bytes()[..8])
+                .unwrap();
+            buf[80 * idx..80 * idx + card.len()].copy_from_slice(card.as_bytes());
+        }
+        let main_table = 2 * 2880;
+        buf.splice(main_table + 8..main_table + 8, 2_i32.to_be_bytes());
+
+        let pixel = tile_compressed_pixels(&buf, |pixels| match pixels {
+            Pixels::F32(mut it) => it.try_next(),
+            _ => unreachable!(),
+        });
+        assert_eq!(
+            pixel,
+            Err(Error::StaticError("ZSCALE field must be a floating point"))
+        );
+    }
+
+    #[test]
+    fn test_zscale_keyword_format() {
+        let tile = [0_u8; 16];
+        let first_pixel = |cards: &[&str]| {
+            let buf = mock_tile_compressed_image(cards, &tile, None);
+            let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+            assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+            let hdu = match hdu_list.next() {
+                Some(Ok(HDU::XBinaryTable(hdu))) => hdu,
+                Some(Err(e)) => return Err(e),
+                _ => panic!("Should contain a binary table"),
+            };
+            let BinaryTableData::TileCompressed(Pixels::F32(mut it)) = hdu_list.get_data(&hdu)
+            else {
+                panic!("Should contain a tile compressed image");
+            };
+            it.try_next()
+        };
+
+        // A ZZERO keyword of the wrong type is not taken as absent
+        let err = first_pixel(&[
+            "ZCMPTYPE= 'GZIP_1  '",
+            "ZBITPIX =                  -32",
+            "ZQUANTIZ= 'NONE    '",
+            "ZZERO   = 'zero    '",
+        ])
+        .expect_err("ZZERO is a string");
+        assert_eq!(
+            err.location().and_then(|l| l.keyword.as_deref()),
+            Some("ZZERO")
+        );
+
+        // Neither when parsing the header of quantized floats
+        let err = first_pixel(&[
+            "ZCMPTYPE= 'GZIP_1  '",
+            "ZBITPIX =                  -32",
+            "ZQUANTIZ= 'NO_DITHER'",
+            "ZSCALE  = 'one     '",
+            "ZZERO   =                  0.0",
+        ])
+        .expect_err("ZSCALE is a string");
+        assert!(!matches!(err.inner(), Error::FailFindingKeyword(_)));
+        assert_eq!(
+            err.location().and_then(|l| l.keyword.as_deref()),
+            Some("ZSCALE")
+        );
+
+        // The absent keywords leave the values unscaled
+        let pixel = first_pixel(&[
+            "ZCMPTYPE= 'GZIP_1  '",
+            "ZBITPIX =                  -32",
+            "ZQUANTIZ= 'NONE    '",
+        ]);
+        assert_eq!(pixel, Ok(Some(0.0)));
+    }
 
     #[test_case("samples/fits.gsfc.nasa.gov/m13real_rice.fits", 1000.0)]
     #[test_case("samples/fits.gsfc.nasa.gov/m13_rice.fits", 1000.0)]
@@ -1,12 +1,15 @@
 use flate2::read::GzDecoder;
 
 use super::super::DataValue;
-use super::dithering::RAND_VALUES;
-use super::rice::RICEDecoder;
+use super::hcompress;
+use super::layout::{TileLayouts, TileStorage};
+use super::plio;
+use super::rice;
 use super::{
-    F32Keywords, F64Keywords, I16Keywords, I32Keywords, Keywords, Quantiz, TileDesc, U8Keywords,
+    F32Keywords, F64Keywords, I16Keywords, I32Keywords, I64Keywords, Keywords, TileDesc, U8Keywords,
 };
 use crate::error::Error;
+use crate::hdu::data::iter::TryNext;
 use crate::hdu::header::extension::bintable::{BinTable, TileCompressedImage, ZCmpType, ZQuantiz};
 use crate::hdu::header::{Bitpix, Header};
 use crate::{TableData, TableRowData};
@@ -17,6 +20,7 @@ pub enum Pixels<R> {
     U8(It<R, U8Keywords>),
     I16(It<R, I16Keywords>),
     I32(It<R, I32Keywords>),
+    I64(It<R, I64Keywords>),
     F32(It<R, F32Keywords>),
     F64(It<R, F64Keywords>),
 }
@@ -31,9 +35,21 @@ impl<R> Pixels<R> {
             Bitpix::U8 => Self::U8(It::new(header, data, config)),
             Bitpix::I16 => Self::I16(It::new(header, data, config)),
             Bitpix::I32 => Self::I32(It::new(header, data, config)),
+            Bitpix::I64 => Self::I64(It::new(header, data, config)),
             Bitpix::F32 => Self::F32(It::new(header, data, config)),
             Bitpix::F64 => Self::F64(It::new(header, data, config)),
-            _ => unreachable!(),
+        }
+    }
+
+    /// Iterate over the layout of the tiles not read yet, without decompressing them
+    pub fn tile_layouts(self) -> TileLayouts<R> {
+        match self {
+            Self::U8(it) => it.tile_layouts(),
+            Self::I16(it) => it.tile_layouts(),
+            Self::I32(it) => it.tile_layouts(),
+            Self::I64(it) => it.tile_layouts(),
+            Self::F32(it) => it.tile_layouts(),
+            Self::F64(it) => it.tile_layouts(),
         }
     }
 }
@@ -45,16 +61,36 @@ where
 {
     /// An iterator over the row of a binary table
     pub row_it: TableRowData<R>,
-    /// A buffer for storing uncompressed data from GZIP1, GZIP2 or RICE
+    /// A buffer for storing uncompressed data from GZIP1, GZIP2, RICE, HCOMPRESS or PLIO
     buf: Vec<u8>,
 
     /// Current tile pointer
     desc: TileDesc<K>,
 
     z_tile: Box<[usize]>,
-    //z_naxis: Box<[usize]>,
+    z_naxis: Box<[usize]>,
+    /// The tile containing the last returned pixel
+    tile: Tile,
+    /// Number of bytes storing a pixel in `buf`
+    num_bytes_per_pixel: usize,
     z_cmp_type: ZCmpType,
-    data_compressed_idx: usize,
+    /// Idx of the COMPRESSED_DATA field
+    data_compressed_idx: Option<usize>,
+    /// Idx of the GZIP_COMPRESSED_DATA fallback field
+    gzip_compressed_data_idx: Option<usize>,
+    /// Idx of the UNCOMPRESSED_DATA fallback field
+    uncompressed_data_idx: Option<usize>,
+    /// Big endian pixels of the current tile if it is stored in a fallback field, empty otherwise
+    raw: Vec<u8>,
+    /// Size in bytes of the pixels stored in the fallback fields, given by ZBITPIX
+    raw_bytes_per_pixel: usize,
+
+    /// Idx of the NULL_PIXEL_MASK field and the compression of the mask
+    null_pixel_mask: Option<(usize, ZCmpType)>,
+    /// Null pixels of the current tile. Empty if the tile does not have any
+    mask: Box<[bool]>,
+    /// Whether the last returned pixel is flagged as null
+    null: bool,
 }
 
 impl<R, K> It<R, K>
@@ -77,9 +113,15 @@ where
     ) -> Self {
         // This buffer is only used if tile compressed image in the gzip compression is to be found
         let TileCompressedImage {
+            z_naxisn,
             z_tilen,
             z_cmp_type,
             data_compressed_idx,
+            gzip_compressed_data_idx,
+            uncompressed_data_idx,
+            null_pixel_mask,
+            z_bitpix,
+            z_quantiz,
             ..
         } = config;
         // Allocation of a buffer at init of the iterator that is the size of the biggest tiles we can found
@@ -87,12 +129,21 @@ where
         // Some tiles found on the border of the image can be smaller
         let n_elems_max = z_tilen.iter().product::<usize>();
 
-        // FIXME
-        // A little precision. The gzdecoder from flate2 seems to unzip data in a stream of u32 i.e. even if the type of data
-        // to uncompress is byte or short, the result will be cast in a u32. I thus allocate for gzip compression, a buffer of
-        // n_elems_max * size_in_bytes of u32.
-        // It seems to be the same for RICE, so the latter rice decompression code is called on i32
-        let num_bytes_max_tile = n_elems_max * std::mem::size_of::<u32>();
+        // GZIP tiles store the pixels at the width of ZBITPIX, the quantized floats as 32-bit integers.
+        // The other compressions are decoded into 32-bit integers
+        let num_bytes_per_pixel = match (z_cmp_type, z_bitpix, z_quantiz) {
+            (ZCmpType::Gzip1 | ZCmpType::Gzip2, Bitpix::F32 | Bitpix::F64, quantiz)
+                if *quantiz != Some(ZQuantiz::Lossless) =>
+            {
+                std::mem::size_of::<u32>()
+            }
+            (ZCmpType::Gzip1 | ZCmpType::Gzip2, _, _) => z_bitpix.byte_size(),
+            (_, Bitpix::I64, _) | (_, Bitpix::F64, Some(ZQuantiz::Lossless)) => {
+                std::mem::size_of::<u64>()
+            }
+            _ => std::mem::size_of::<u32>(),
+        };
+        let num_bytes_max_tile = n_elems_max * num_bytes_per_pixel;
 
         let buf = vec![0_u8; num_bytes_max_tile];
 
@@ -107,346 +158,494 @@ where
             desc,
 
             data_compressed_idx: *data_compressed_idx,
-            //z_naxis: z_naxisn.clone(),
+            gzip_compressed_data_idx: *gzip_compressed_data_idx,
+            uncompressed_data_idx: *uncompressed_data_idx,
+            raw: Vec::new(),
+            raw_bytes_per_pixel: z_bitpix.byte_size(),
+            z_naxis: z_naxisn.iter().map(|&naxis| naxis as usize).collect(),
             z_tile: z_tilen.clone(),
+            tile: Tile::default(),
+            num_bytes_per_pixel,
             z_cmp_type: *z_cmp_type,
+
+            null_pixel_mask: *null_pixel_mask,
+            mask: Box::default(),
+            null: false,
         }
     }
+
+    /// Returns whether the last pixel returned is flagged as null by the NULL_PIXEL_MASK column
+    ///
+    /// Floating point null pixels are already returned as NaN
+    pub fn is_null(&self) -> bool {
+        self.null
+    }
+
+    /// Iterate over the pixels, giving `None` for the ones flagged as null by the NULL_PIXEL_MASK column
+    pub fn nullable(self) -> NullablePixels<R, K> {
+        NullablePixels(self)
+    }
+
+    /// Returns the tile containing the last pixel returned
+    ///
+    /// The pixels are returned tile by tile, each tile being in raster order
+    pub fn current_tile(&self) -> &Tile {
+        &self.tile
+    }
+
+    /// Returns the dimensions of the image, given by the ZNAXISn keywords
+    pub fn image_size(&self) -> &[usize] {
+        &self.z_naxis
+    }
+
+    /// Iterate over the layout of the tiles not read yet, without decompressing them
+    pub fn tile_layouts(self) -> TileLayouts<R> {
+        let uncompressed_byte_size =
+            self.z_naxis.iter().product::<usize>() as u64 * self.raw_bytes_per_pixel as u64;
+
+        TileLayouts::new(
+            self.row_it,
+            self.z_tile,
+            self.z_naxis,
+            [
+                (TileStorage::Compressed, self.data_compressed_idx),
+                (TileStorage::GzipCompressed, self.gzip_compressed_data_idx),
+                (TileStorage::Uncompressed, self.uncompressed_data_idx),
+            ],
+            uncompressed_byte_size,
+        )
+    }
+
+    /// The decompressed bytes of the current tile
+    fn tile_bytes(&self) -> &[u8] {
+        &self.buf[..(self.desc.n_pixels as usize) * self.num_bytes_per_pixel]
+    }
+
+    /// Offset of the last returned pixel in an array of the given strides
+    ///
+    /// Along the axes before `first_axis`, the pixel coordinates are taken in the image,
+    /// along the other ones they are taken inside the tile
+    fn pixel_offset(&self, first_axis: usize, strides: &[usize]) -> usize {
+        let mut n = (self.desc.n_pixels - self.desc.remaining_pixels - 1) as usize;
+
+        self.tile
+            .size
+            .iter()
+            .zip(self.tile.origin.iter())
+            .zip(strides)
+            .enumerate()
+            .map(|(axis, ((&size, &origin), &stride))| {
+                let coo = n % size;
+                n /= size;
+
+                if axis < first_axis {
+                    (origin + coo) * stride
+                } else {
+                    coo * stride
+                }
+            })
+            .sum()
+    }
 }
 
-/*
-/// Compute the size of the tile from its row position inside the compressed data column
-/// FIXME: optimize this computation by using memoization
-pub(crate) fn tile_size_from_row_idx(
-    z_tile: &[usize],
-    z_naxis: &[usize],
-    n: usize,
-) -> Box<[usize]> {
-    let d = z_tile.len();
-
-    if d == 0 {
-        // There must be at least one dimension
-        unreachable!();
-    } else {
-        let mut u = vec![0_usize; z_tile.len()];
-
-        let s = z_naxis
+impl<R, K> It<R, K>
+where
+    K: Keywords,
+    K::T: Copy + Default,
+    Self: Iterator<Item = K::T>,
+{
+    /// Assemble all the tiles into a buffer storing the pixels in raster order,
+    /// i.e. the order of the pixels of an uncompressed image
+    ///
+    /// The assembly stops at the first tile that cannot be read, its pixels and the ones of the
+    /// following tiles are left to their default value. Use [It::try_raster] to get the error instead
+    pub fn raster(mut self) -> Box<[K::T]> {
+        let strides = strides(&self.z_naxis);
+        let mut pixels = vec![K::T::default(); self.z_naxis.iter().product()];
+
+        while let Some(value) = self.next() {
+            let off = self.pixel_offset(self.z_naxis.len(), &strides);
+            pixels[off] = value;
+        }
+
+        pixels.into_boxed_slice()
+    }
+
+    /// Assemble all the tiles into a buffer storing the pixels in raster order,
+    /// returning the error of the first tile that cannot be read
+    ///
+    /// An error is also returned if the tile table ends before all the pixels of the image
+    pub fn try_raster(mut self) -> Result<Box<[K::T]>, Error>
+    where
+        Self: TryNext<Item = K::T>,
+    {
+        let strides = strides(&self.z_naxis);
+        let num_pixels = self.z_naxis.iter().product::<usize>();
+        let mut pixels = vec![K::T::default(); num_pixels];
+
+        let mut num_read = 0;
+        while let Some(value) = self.try_next()? {
+            let off = self.pixel_offset(self.z_naxis.len(), &strides);
+            pixels[off] = value;
+            num_read += 1;
+        }
+        if num_read < num_pixels {
+            return Err(Error::DynamicError(format!(
+                "The tile compressed image ends {} pixels before its end",
+                num_pixels - num_read
+            )));
+        }
+
+        Ok(pixels.into_boxed_slice())
+    }
+
+    /// Iterate over the bands of tiles sharing the same position along all the axes but the first one
+    ///
+    /// Each band is given in raster order, e.g. for a 2D image, a band is made of ZNAXIS1 x ZTILE2 pixels.
+    /// This allows streaming an image in raster order, keeping in memory only one band at a time
+    pub fn raster_bands(self) -> RasterBands<R, K> {
+        RasterBands(self)
+    }
+}
+
+/// Strides of the axes of an array of `dims` dimensions, the first axis being the most rapidly varying
+fn strides(dims: &[usize]) -> Vec<usize> {
+    dims.iter()
+        .scan(1, |stride, &dim| {
+            let cur = *stride;
+            *stride *= dim;
+            Some(cur)
+        })
+        .collect()
+}
+
+/// An iterator over the bands of tiles of a tile compressed image, each band being given in raster order
+#[derive(Debug)]
+pub struct RasterBands<R, K>(It<R, K>)
+where
+    K: Keywords;
+
+impl<R, K> TryNext for RasterBands<R, K>
+where
+    K: Keywords,
+    K::T: Copy + Default,
+    It<R, K>: TryNext<Item = K::T>,
+{
+    type Item = Box<[K::T]>;
+
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let it = &mut self.0;
+
+        // The first pixel of the band gives the size of its tiles along all the axes but the first one
+        let Some(value) = it.try_next()? else {
+            return Ok(None);
+        };
+        let mut dims = it.tile.size.clone();
+        dims[0] = it.z_naxis[0];
+
+        let strides = strides(&dims);
+        let num_pixels = dims.iter().product::<usize>();
+        let mut band = vec![K::T::default(); num_pixels];
+
+        band[it.pixel_offset(1, &strides)] = value;
+        for i in 1..num_pixels {
+            // The tile table ends in the middle of the band
+            let Some(value) = it.try_next()? else {
+                return Err(Error::DynamicError(format!(
+                    "The tile compressed image ends {} pixels before the end of a band",
+                    num_pixels - i
+                )));
+            };
+            band[it.pixel_offset(1, &strides)] = value;
+        }
+
+        Ok(Some(band.into_boxed_slice()))
+    }
+}
+
+impl<R, K> Iterator for RasterBands<R, K>
+where
+    K: Keywords,
+    Self: TryNext,
+{
+    type Item = <Self as TryNext>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
+    }
+}
+
+/// An iterator over the pixels of a tile compressed image returning `None` for the null pixels
+#[derive(Debug)]
+pub struct NullablePixels<R, K>(It<R, K>)
+where
+    K: Keywords;
+
+impl<R, K> TryNext for NullablePixels<R, K>
+where
+    K: Keywords,
+    It<R, K>: TryNext,
+{
+    type Item = Option<<It<R, K> as TryNext>::Item>;
+
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(value) = self.0.try_next()? else {
+            return Ok(None);
+        };
+
+        Ok(Some(if self.0.is_null() { None } else { Some(value) }))
+    }
+}
+
+impl<R, K> Iterator for NullablePixels<R, K>
+where
+    K: Keywords,
+    Self: TryNext,
+{
+    type Item = <Self as TryNext>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
+    }
+}
+
+impl<R, K> Iterator for It<R, K>
+where
+    K: Keywords,
+    Self: TryNext,
+{
+    type Item = <Self as TryNext>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
+    }
+}
+
+/// Position of a tile inside the image
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tile {
+    /// Index of the tile, i.e. the row of the binary table storing it
+    pub idx: usize,
+    /// Coordinates of the first pixel of the tile. The first axis is the most rapidly varying one
+    pub origin: Box<[usize]>,
+    /// Number of pixels of the tile along each axis. Tiles on the borders of the image
+    /// are smaller than ZTILEn if ZNAXISn is not a multiple of it
+    pub size: Box<[usize]>,
+}
+
+impl Tile {
+    /// Compute the position of a tile from its row index inside the compressed data column
+    ///
+    /// The tiles are stored in the same order as their first pixel appears in the image
+    pub(crate) fn new(idx: usize, z_tile: &[usize], z_naxis: &[usize]) -> Self {
+        let mut n = idx;
+        let (origin, size) = z_tile
             .iter()
-            .zip(z_tile.iter())
-            .map(|(naxisi, tilei)| naxisi.div_ceil(*tilei))
-            .collect::<Vec<_>>();
-
-        // Compute the position inside the first dimension
-        u[0] = n % s[0];
-
-        for i in 1..d {
-            u[i] = n
-                - u[0]
-                - (1..i)
-                    .map(|k| {
-                        let prod_sk = s.iter().take(k).product::<usize>();
-                        u[k] * prod_sk
-                    })
-                    .sum::<usize>();
-
-            let prod_si = s.iter().take(i).product::<usize>();
-
-            u[i] = (u[i] / prod_si) % s[i];
+            .zip(z_naxis)
+            .map(|(&tile, &naxis)| {
+                let num_tiles = naxis.div_ceil(tile);
+                let origin = (n % num_tiles) * tile;
+                n /= num_tiles;
+
+                (origin, tile.min(naxis - origin))
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        Self {
+            idx,
+            origin: origin.into_boxed_slice(),
+            size: size.into_boxed_slice(),
         }
+    }
 
-        u.iter()
-            .zip(z_naxis.iter().zip(z_tile.iter()))
-            .map(|(&u_i, (&naxis, &tilez))| tilez.min(naxis - u_i * tilez))
-            .collect()
+    /// Number of pixels of the tile
+    pub fn num_pixels(&self) -> usize {
+        self.size.iter().product()
     }
 }
-    */
 
 use std::fmt::Debug;
-impl<R> Iterator for It<R, U8Keywords>
+impl<R, K> It<R, K>
 where
     R: Read + Seek + Debug,
+    K: Keywords,
 {
-    // Return a vec of fields because to take into account the repeat count value for that field
-    type Item = u8;
+    /// Move on to the next pixel, decompressing the next tile once the current one has been read
+    ///
+    /// Returns the index of the pixel inside its tile, `None` once all the tiles have been read
+    fn next_pixel(&mut self) -> Result<Option<usize>, Error> {
+        if self.desc.remaining_pixels == 0 && !self.read_tile()? {
+            return Ok(None);
+        }
 
-    fn next(&mut self) -> Option<Self::Item> {
+        let idx = (self.desc.n_pixels - self.desc.remaining_pixels) as usize;
+        self.null = self.mask.get(idx).copied().unwrap_or(false);
+        self.desc.remaining_pixels -= 1;
+
+        Ok(Some(idx))
+    }
+
+    /// Read and decompress the tile stored in the next row of the table
+    ///
+    /// Returns `false` if there is no row left
+    fn read_tile(&mut self) -> Result<bool, Error> {
         // We first retrieve the whole row from the row data iterator
-        if self.desc.remaining_pixels == 0 {
-            let row_data = self.row_it.next()?;
-
-            let (_, byte_offset) = match row_data[self.data_compressed_idx] {
-                DataValue::VariableLengthArray32 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems as u64, offset_byte as u64),
-                DataValue::VariableLengthArray64 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems, offset_byte),
-                _ => unreachable!(),
-            };
+        let Some(row_data) = self.row_it.try_next()? else {
+            return Ok(false);
+        };
 
-            let ctx = self.row_it.get_ctx();
-            let row_idx = self.row_it.get_row_idx();
-
-            // Update the tile compressed currently decompressed
-            let num_pixels = self.z_tile.iter().product::<usize>() as u64;
-            self.desc.n_pixels = num_pixels;
-            self.desc.remaining_pixels = num_pixels;
-
-            // We jump to the heap at the position of the tile
-            // Then we decomp the tile and store it into out internal buf
-            // Finally we go back to the main data table location before jumping to the heap
-            let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
-            let off =
-                // go back to the beginning of the main table data block
-                - (main_data_table_offset as i64)
-                // from the beginning of the main table go to the beginning of the heap
-                + ctx.theap as i64
-                // from the beginning of the heap go to the start of the array
-                + byte_offset as i64;
-            self.jump_to_location(
-                |s| {
-                    let It { buf, row_it, .. } = s;
-
-                    let reader = row_it.get_reader();
-
-                    match s.z_cmp_type {
-                        // For GZIP2, the byte shuffling is done in the next method
-                        ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
-                            let mut gz = GzDecoder::new(reader);
-                            gz.read_exact(&mut buf[..])?;
-                        }
-                        // FIXME support bytepix
-                        ZCmpType::Rice { blocksize, .. } => {
-                            let mut rice = RICEDecoder::<_, i32>::new(
-                                reader,
-                                blocksize as i32,
-                                num_pixels as i32,
-                            );
-                            rice.read_exact(&mut buf[..])?;
-                        }
-                        // Other compression not supported, when parsing the bintable extension keywords
-                        // we ensured that z_image is `None` for other compressions than GZIP or RICE
-                        _ => unreachable!(),
+        // Tiles that could not be compressed are stored in one of the fallback fields
+        let Some((num_elems, byte_offset)) = self.compressed_tile_descriptor(&row_data) else {
+            self.read_raw_tile(&row_data)?;
+            return Ok(true);
+        };
+        self.raw.clear();
+
+        let ctx = self.row_it.get_ctx();
+        let row_idx = self.row_it.get_row_idx();
+
+        // The tile compressed currently decompressed
+        let tile = Tile::new(row_idx - 1, &self.z_tile, &self.z_naxis);
+        let num_pixels = tile.num_pixels() as u64;
+
+        self.desc.keywords.start_tile(&row_data, row_idx)?;
+
+        // We jump to the heap at the position of the tile
+        // Then we decomp the tile and store it into out internal buf
+        // Finally we go back to the main data table location before jumping to the heap
+        let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
+        let off =
+            // go back to the beginning of the main table data block
+            - (main_data_table_offset as i64)
+            // from the beginning of the main table go to the beginning of the heap
+            + ctx.theap as i64
+            // from the beginning of the heap go to the start of the array
+            + byte_offset as i64;
+        self.jump_to_location(
+            |s| {
+                let It {
+                    buf,
+                    row_it,
+                    num_bytes_per_pixel,
+                    ..
+                } = s;
+                // Tiles on the borders of the image may be smaller
+                let buf = &mut buf[..(num_pixels as usize) * *num_bytes_per_pixel];
+
+                let reader = row_it.get_reader();
+
+                // The compressions not supported for the type of the pixels have been
+                // discarded when parsing the bintable extension keywords
+                match s.z_cmp_type {
+                    // For GZIP2, the byte shuffling is done when reading the pixels
+                    ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
+                        let mut gz = GzDecoder::new(reader);
+                        gz.read_exact(&mut buf[..])?;
+                    }
+                    ZCmpType::Rice { blocksize, bytepix } => {
+                        rice::decompress_tile(reader, blocksize, bytepix, &mut buf[..])?;
+                    }
+                    ZCmpType::Hcompress1 => {
+                        hcompress::decompress_tile(reader, num_elems as usize, &mut buf[..])?;
                     }
+                    ZCmpType::Plio1 => {
+                        plio::decompress_tile(reader, num_elems as usize, &mut buf[..])?;
+                    }
+                }
 
-                    Ok(())
-                },
-                SeekFrom::Current(off),
-            )
-            .ok()?;
-        }
+                Ok(())
+            },
+            SeekFrom::Current(off),
+        )?;
 
-        // There is remaining pixels inside our buffer, we simply return the current one
-        let idx = (self.desc.n_pixels - self.desc.remaining_pixels) as usize;
+        self.read_null_pixel_mask(&row_data, num_pixels as usize)?;
+
+        // The tile is only made current once decompressed, so that a failure leaves
+        // the iterator on the previous one
+        self.tile = tile;
+        self.desc.n_pixels = num_pixels;
+        self.desc.remaining_pixels = num_pixels;
+
+        Ok(true)
+    }
+}
+
+impl<R> TryNext for It<R, U8Keywords>
+where
+    R: Read + Seek + Debug,
+{
+    type Item = u8;
+
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(idx) = self.next_pixel()? else {
+            return Ok(None);
+        };
+
+        // Tiles stored in a fallback field hold the pixel values
+        if !self.raw.is_empty() {
+            return Ok(Some(u8::from_be_bytes(gzip1_bytes(&self.raw, idx))));
+        }
 
         let value = match self.z_cmp_type {
-            ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                let off = 4 * idx;
-                // read from BigEndian, i.e. the most significant byte is at first and the least one is at last position
-                self.buf[off + 3]
-            }
-            ZCmpType::Rice { .. } => {
+            ZCmpType::Gzip1 => u8::from_be_bytes(gzip1_bytes(&self.buf, idx)),
+            ZCmpType::Gzip2 => u8::from_be_bytes(gzip2_bytes(self.tile_bytes(), idx)),
+            ZCmpType::Rice { .. } | ZCmpType::Hcompress1 | ZCmpType::Plio1 => {
                 // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
                 let off = 4 * idx;
                 self.buf[off]
             }
-            // Not supported compression/bitpix results in parsing the binary table as normal and thus this part is not reachable
-            _ => unreachable!(),
         };
-        self.desc.remaining_pixels -= 1;
 
-        Some(value)
+        Ok(Some(value))
     }
 }
 
-impl<R> Iterator for It<R, I16Keywords>
+impl<R> TryNext for It<R, I16Keywords>
 where
     R: Read + Seek + Debug,
 {
-    // Return a vec of fields because to take into account the repeat count value for that field
     type Item = i16;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // We first retrieve the whole row from the row data iterator
-        if self.desc.remaining_pixels == 0 {
-            let row_data = self.row_it.next()?;
-
-            let (_, byte_offset) = match row_data[self.data_compressed_idx] {
-                DataValue::VariableLengthArray32 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems as u64, offset_byte as u64),
-                DataValue::VariableLengthArray64 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems, offset_byte),
-                _ => unreachable!(),
-            };
-
-            let ctx = self.row_it.get_ctx();
-            let row_idx = self.row_it.get_row_idx();
-
-            // Update the tile compressed currently decompressed
-            let num_pixels = self.z_tile.iter().product::<usize>() as u64;
-            self.desc.n_pixels = num_pixels;
-            self.desc.remaining_pixels = num_pixels;
-            // We jump to the heap at the position of the tile
-            // Then we decomp the tile and store it into out internal buf
-            // Finally we go back to the main data table location before jumping to the heap
-            let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
-            let off =
-                // go back to the beginning of the main table data block
-                - (main_data_table_offset as i64)
-                // from the beginning of the main table go to the beginning of the heap
-                + ctx.theap as i64
-                // from the beginning of the heap go to the start of the array
-                + byte_offset as i64;
-            self.jump_to_location(
-                |s| {
-                    let It { buf, row_it, .. } = s;
-
-                    let reader = row_it.get_reader();
-
-                    match s.z_cmp_type {
-                        // For GZIP2, the byte shuffling is done in the next method
-                        ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
-                            let mut gz = GzDecoder::new(reader);
-                            gz.read_exact(&mut buf[..])?;
-                        }
-                        // FIXME support bytepix
-                        ZCmpType::Rice { blocksize, .. } => {
-                            let mut rice = RICEDecoder::<_, i32>::new(
-                                reader,
-                                blocksize as i32,
-                                num_pixels as i32,
-                            );
-                            rice.read_exact(&mut buf[..])?;
-                        }
-                        // Other compression not supported, when parsing the bintable extension keywords
-                        // we ensured that z_image is `None` for other compressions than GZIP or RICE
-                        _ => unreachable!(),
-                    }
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(idx) = self.next_pixel()? else {
+            return Ok(None);
+        };
 
-                    Ok(())
-                },
-                SeekFrom::Current(off),
-            )
-            .ok()?;
+        // Tiles stored in a fallback field hold the pixel values
+        if !self.raw.is_empty() {
+            return Ok(Some(i16::from_be_bytes(gzip1_bytes(&self.raw, idx))));
         }
 
-        // There is remaining pixels inside our buffer, we simply return the current one
-        let idx = (self.desc.n_pixels - self.desc.remaining_pixels) as usize;
-
         let value = match self.z_cmp_type {
-            ZCmpType::Gzip1 => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                // read from BigEndian, i.e. the most significant byte is at first and the least one is at last position
-                let off = 4 * idx;
-                (self.buf[off + 3] as i16) | ((self.buf[off + 2] as i16) << 8)
-            }
-            ZCmpType::Gzip2 => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                // read from BigEndian, i.e. the most significant byte is at first and the least one is at last position
-                let num_bytes = self.buf.len();
-                let step_msb = num_bytes / 4;
-                (self.buf[3 * step_msb + idx] as i16) | ((self.buf[2 * step_msb + idx] as i16) << 8)
-            }
-            ZCmpType::Rice { .. } => {
+            ZCmpType::Gzip1 => i16::from_be_bytes(gzip1_bytes(&self.buf, idx)),
+            ZCmpType::Gzip2 => i16::from_be_bytes(gzip2_bytes(self.tile_bytes(), idx)),
+            ZCmpType::Rice { .. } | ZCmpType::Hcompress1 | ZCmpType::Plio1 => {
                 // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
                 let off = 4 * idx;
                 (self.buf[off] as i16) | ((self.buf[off + 1] as i16) << 8)
             }
-            // Not supported compression/bitpix results in parsing the binary table as normal and thus this part is not reachable
-            _ => unreachable!(),
         };
-        self.desc.remaining_pixels -= 1;
 
-        Some(value)
+        Ok(Some(value))
     }
 }
 
-impl<R> Iterator for It<R, I32Keywords>
+impl<R> TryNext for It<R, I32Keywords>
 where
     R: Read + Seek + Debug,
 {
-    // Return a vec of fields because to take into account the repeat count value for that field
     type Item = i32;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // We first retrieve the whole row from the row data iterator
-        if self.desc.remaining_pixels == 0 {
-            let row_data = self.row_it.next()?;
-
-            let (_, byte_offset) = match row_data[self.data_compressed_idx] {
-                DataValue::VariableLengthArray32 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems as u64, offset_byte as u64),
-                DataValue::VariableLengthArray64 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems, offset_byte),
-                _ => unreachable!(),
-            };
-
-            let ctx = self.row_it.get_ctx();
-            let row_idx = self.row_it.get_row_idx();
-
-            // Update the tile compressed currently decompressed
-            let num_pixels = self.z_tile.iter().product::<usize>() as u64;
-            self.desc.n_pixels = num_pixels;
-            self.desc.remaining_pixels = num_pixels;
-            // We jump to the heap at the position of the tile
-            // Then we decomp the tile and store it into out internal buf
-            // Finally we go back to the main data table location before jumping to the heap
-            let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
-            let off =
-                // go back to the beginning of the main table data block
-                - (main_data_table_offset as i64)
-                // from the beginning of the main table go to the beginning of the heap
-                + ctx.theap as i64
-                // from the beginning of the heap go to the start of the array
-                + byte_offset as i64;
-            self.jump_to_location(
-                |s| {
-                    let It { buf, row_it, .. } = s;
-
-                    let reader = row_it.get_reader();
-
-                    match s.z_cmp_type {
-                        // For GZIP2, the byte shuffling is done in the next method
-                        ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
-                            let mut gz = GzDecoder::new(reader);
-                            gz.read_exact(&mut buf[..])?;
-                        }
-                        // FIXME support bytepix
-                        ZCmpType::Rice { blocksize, .. } => {
-                            let mut rice = RICEDecoder::<_, i32>::new(
-                                reader,
-                                blocksize as i32,
-                                num_pixels as i32,
-                            );
-                            rice.read_exact(&mut buf[..])?;
-                        }
-                        // Other compression not supported, when parsing the bintable extension keywords
-                        // we ensured that z_image is `None` for other compressions than GZIP or RICE
-                        _ => unreachable!(),
-                    }
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(idx) = self.next_pixel()? else {
+            return Ok(None);
+        };
 
-                    Ok(())
-                },
-                SeekFrom::Current(off),
-            )
-            .ok()?;
+        // Tiles stored in a fallback field hold the pixel values
+        if !self.raw.is_empty() {
+            return Ok(Some(i32::from_be_bytes(gzip1_bytes(&self.raw, idx))));
         }
 
-        // There is remaining pixels inside our buffer, we simply return the current one
-        let idx = (self.desc.n_pixels - self.desc.remaining_pixels) as usize;
-
         let value = match self.z_cmp_type {
             ZCmpType::Gzip1 => {
                 // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
@@ -461,14 +660,14 @@ where
             ZCmpType::Gzip2 => {
                 // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
                 // read from BigEndian, i.e. the most significant byte is at first and the least one is at last position
-                let num_bytes = self.buf.len();
+                let num_bytes = self.tile_bytes().len();
                 let step_msb = num_bytes / 4;
                 ((self.buf[idx] as i32) << 24)
                     | ((self.buf[idx + step_msb] as i32) << 16)
                     | ((self.buf[idx + 2 * step_msb] as i32) << 8)
                     | (self.buf[idx + 3 * step_msb] as i32)
             }
-            ZCmpType::Rice { .. } => {
+            ZCmpType::Rice { .. } | ZCmpType::Hcompress1 | ZCmpType::Plio1 => {
                 // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
                 let off = 4 * idx;
                 i32::from_ne_bytes([
@@ -478,369 +677,370 @@ where
                     self.buf[off + 3],
                 ])
             }
+        };
+
+        Ok(Some(value))
+    }
+}
+
+impl<R> TryNext for It<R, I64Keywords>
+where
+    R: Read + Seek + Debug,
+{
+    type Item = i64;
+
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(idx) = self.next_pixel()? else {
+            return Ok(None);
+        };
+
+        // Tiles stored in a fallback field hold the pixel values
+        if !self.raw.is_empty() {
+            return Ok(Some(i64::from_be_bytes(gzip1_bytes(&self.raw, idx))));
+        }
+
+        let value = match self.z_cmp_type {
+            ZCmpType::Gzip1 => i64::from_be_bytes(gzip1_bytes(&self.buf, idx)),
+            ZCmpType::Gzip2 => i64::from_be_bytes(gzip2_bytes(self.tile_bytes(), idx)),
             // Not supported compression/bitpix results in parsing the binary table as normal and thus this part is not reachable
             _ => unreachable!(),
         };
-        self.desc.remaining_pixels -= 1;
 
-        Some(value)
+        Ok(Some(value))
     }
 }
 
-impl<R> Iterator for It<R, F32Keywords>
+impl<R> TryNext for It<R, F32Keywords>
 where
     R: Read + Seek + Debug,
 {
-    // Return a vec of fields because to take into account the repeat count value for that field
     type Item = f32;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // We first retrieve the whole row from the row data iterator
-        if self.desc.remaining_pixels == 0 {
-            let row_data = self.row_it.next()?;
-
-            let (_, byte_offset) = match row_data[self.data_compressed_idx] {
-                DataValue::VariableLengthArray32 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems as u64, offset_byte as u64),
-                DataValue::VariableLengthArray64 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems, offset_byte),
-                _ => unreachable!(),
-            };
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(idx) = self.next_pixel()? else {
+            return Ok(None);
+        };
 
-            let ctx = self.row_it.get_ctx();
-            let row_idx = self.row_it.get_row_idx();
-
-            // Update the tile compressed currently decompressed
-            let num_pixels = self.z_tile.iter().product::<usize>() as u64;
-            self.desc.n_pixels = num_pixels;
-            self.desc.remaining_pixels = num_pixels;
-
-            let TileDesc {
-                keywords:
-                    F32Keywords {
-                        z_scale_idx,
-                        z_zero_idx,
-                        scale,
-                        zero,
-                        z_dither_0,
-                        z_quantiz,
-                        quantiz,
-                        z_blank_idx,
-                        z_blank,
-                    },
-                ..
-            } = &mut self.desc;
-
-            *quantiz = match z_quantiz {
-                ZQuantiz::SubtractiveDither1 => {
-                    let i0 = (row_idx - 1 + ((*z_dither_0) as usize)) % 10000;
-                    let i1 = (RAND_VALUES[i0] * 500.0).floor() as usize;
-                    Quantiz::SubtractiveDither1 { i1 }
+        // Tiles stored in a fallback field hold the pixel values
+        let value = if !self.raw.is_empty() {
+            f32::from_be_bytes(gzip1_bytes(&self.raw, idx))
+        } else {
+            match self.z_cmp_type {
+                // Losslessly compressed floating point values
+                ZCmpType::Gzip1 if self.desc.keywords.z_quantiz == ZQuantiz::Lossless => {
+                    f32::from_be_bytes(gzip1_bytes(&self.buf, idx))
                 }
-                ZQuantiz::SubtractiveDither2 => {
-                    let i0 = (row_idx - 1 + (*z_dither_0 as usize)) % 10000;
-                    let i1 = (RAND_VALUES[i0] * 500.0).floor() as usize;
-                    Quantiz::SubtractiveDither2 { i1 }
+                ZCmpType::Gzip2 if self.desc.keywords.z_quantiz == ZQuantiz::Lossless => {
+                    f32::from_be_bytes(gzip2_bytes(self.tile_bytes(), idx))
                 }
-                _ => Quantiz::NoDither,
-            };
-
-            *scale = match row_data[*z_scale_idx] {
-                DataValue::Float { value, .. } => value,
-                DataValue::Double { value, .. } => value as f32,
-                _ => unreachable!(),
-            };
-
-            *zero = match row_data[*z_zero_idx] {
-                DataValue::Float { value, .. } => value,
-                DataValue::Double { value, .. } => value as f32,
-                _ => unreachable!(),
-            };
-
-            if let Some(idx) = z_blank_idx {
-                *z_blank = match row_data[*idx] {
-                    DataValue::UnsignedByte { value, .. } => Some(value as i32),
-                    DataValue::Short { value, .. } => Some(value as i32),
-                    DataValue::Integer { value, .. } => Some(value),
-                    DataValue::Long { value, .. } => Some(value as i32),
-                    _ => unreachable!(),
+                // Quantized floating point
+                ZCmpType::Gzip1 => {
+                    let value = i32::from_be_bytes(gzip1_bytes(&self.buf, idx));
+                    self.desc.keywords.unquantize(value)
                 }
+                ZCmpType::Gzip2 => {
+                    let value = i32::from_be_bytes(gzip2_bytes(self.tile_bytes(), idx));
+                    self.desc.keywords.unquantize(value)
+                }
+                ZCmpType::Rice { .. } | ZCmpType::Hcompress1 => {
+                    // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
+                    let off = 4 * idx;
+                    let value = i32::from_ne_bytes([
+                        self.buf[off],
+                        self.buf[off + 1],
+                        self.buf[off + 2],
+                        self.buf[off + 3],
+                    ]);
+
+                    self.desc.keywords.unquantize(value)
+                }
+                // Not supported compression/bitpix results in parsing the binary table as normal and thus this part is not reachable
+                _ => unreachable!(),
             }
+        };
+        // Null pixels flagged by the mask are mapped to NaN
+        let value = if self.null { f32::NAN } else { value };
 
-            // We jump to the heap at the position of the tile
-            // Then we decomp the tile and store it into out internal buf
-            // Finally we go back to the main data table location before jumping to the heap
-            let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
-            let off =
-                // go back to the beginning of the main table data block
-                - (main_data_table_offset as i64)
-                // from the beginning of the main table go to the beginning of the heap
-                + ctx.theap as i64
-                // from the beginning of the heap go to the start of the array
-                + byte_offset as i64;
-            self.jump_to_location(
-                |s| {
-                    let It { buf, row_it, .. } = s;
-
-                    let reader = row_it.get_reader();
-
-                    match s.z_cmp_type {
-                        // For GZIP2, the byte shuffling is done in the next method
-                        ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
-                            let mut gz = GzDecoder::new(reader);
-                            gz.read_exact(&mut buf[..])?;
-                        }
-                        // FIXME support bytepix
-                        ZCmpType::Rice { blocksize, .. } => {
-                            let mut rice = RICEDecoder::<_, i32>::new(
-                                reader,
-                                blocksize as i32,
-                                num_pixels as i32,
-                            );
-                            rice.read_exact(&mut buf[..])?;
-                        }
-                        // Other compression not supported, when parsing the bintable extension keywords
-                        // we ensured that z_image is `None` for other compressions than GZIP or RICE
-                        _ => unreachable!(),
-                    }
+        Ok(Some(value))
+    }
+}
 
-                    Ok(())
-                },
-                SeekFrom::Current(off),
-            )
-            .ok()?;
-        }
+impl<R> TryNext for It<R, F64Keywords>
+where
+    R: Read + Seek + Debug,
+{
+    type Item = f64;
 
-        // There is remaining pixels inside our buffer, we simply return the current one
-        let idx = (self.desc.n_pixels - self.desc.remaining_pixels) as usize;
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
+        let Some(idx) = self.next_pixel()? else {
+            return Ok(None);
+        };
 
-        let value = match self.z_cmp_type {
-            // 32-bit floating point
-            ZCmpType::Gzip1 => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                let off = 4 * idx;
-                let value = i32::from_be_bytes([
-                    self.buf[off],
-                    self.buf[off + 1],
-                    self.buf[off + 2],
-                    self.buf[off + 3],
-                ]);
-                self.desc.keywords.unquantize(value)
+        // Tiles stored in a fallback field hold the pixel values
+        let value = if !self.raw.is_empty() {
+            f64::from_be_bytes(gzip1_bytes(&self.raw, idx))
+        } else {
+            match self.z_cmp_type {
+                // Losslessly compressed floating point values
+                ZCmpType::Gzip1 if self.desc.keywords.z_quantiz == ZQuantiz::Lossless => {
+                    f64::from_be_bytes(gzip1_bytes(&self.buf, idx))
+                }
+                ZCmpType::Gzip2 if self.desc.keywords.z_quantiz == ZQuantiz::Lossless => {
+                    f64::from_be_bytes(gzip2_bytes(self.tile_bytes(), idx))
+                }
+                // Quantized floating point
+                ZCmpType::Gzip1 => {
+                    let value = i32::from_be_bytes(gzip1_bytes(&self.buf, idx));
+                    self.desc.keywords.unquantize(value)
+                }
+                ZCmpType::Gzip2 => {
+                    let value = i32::from_be_bytes(gzip2_bytes(self.tile_bytes(), idx));
+                    self.desc.keywords.unquantize(value)
+                }
+                ZCmpType::Rice { .. } | ZCmpType::Hcompress1 => {
+                    // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
+                    let off = 4 * idx;
+                    let value = i32::from_ne_bytes([
+                        self.buf[off],
+                        self.buf[off + 1],
+                        self.buf[off + 2],
+                        self.buf[off + 3],
+                    ]);
+
+                    self.desc.keywords.unquantize(value)
+                }
+                // Not supported compression/bitpix results in parsing the binary table as normal and thus this part is not reachable
+                _ => unreachable!(),
             }
-            ZCmpType::Gzip2 => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                // read from BigEndian, i.e. the most significant byte is at first and the least one is at last position
-                let num_bytes = self.buf.len();
-                let step_msb = num_bytes / 4;
-                let value = ((self.buf[idx] as i32) << 24)
-                    | ((self.buf[idx + step_msb] as i32) << 16)
-                    | ((self.buf[idx + 2 * step_msb] as i32) << 8)
-                    | (self.buf[idx + 3 * step_msb] as i32);
+        };
+        // Null pixels flagged by the mask are mapped to NaN
+        let value = if self.null { f64::NAN } else { value };
 
-                self.desc.keywords.unquantize(value)
-            }
-            // 32-bit floating point
-            ZCmpType::Rice { .. } => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                let off = 4 * idx;
-                let value = i32::from_ne_bytes([
-                    self.buf[off],
-                    self.buf[off + 1],
-                    self.buf[off + 2],
-                    self.buf[off + 3],
-                ]);
+        Ok(Some(value))
+    }
+}
 
-                self.desc.keywords.unquantize(value)
-            }
-            // Not supported compression/bitpix results in parsing the binary table as normal and thus this part is not reachable
-            _ => unreachable!(),
-        };
-        self.desc.remaining_pixels -= 1;
+/// Get the `N` big endian bytes of the pixel `idx` from a GZIP_1 decompressed tile
+fn gzip1_bytes<const N: usize>(buf: &[u8], idx: usize) -> [u8; N] {
+    let mut bytes = [0_u8; N];
+    bytes.copy_from_slice(&buf[N * idx..N * (idx + 1)]);
+
+    bytes
+}
+
+/// Get the number of elements and the heap offset of a non empty variable length array
+pub(super) fn non_empty_array_descriptor(value: &DataValue) -> Option<(u64, u64)> {
+    let (num_elems, offset_byte) = match *value {
+        DataValue::VariableLengthArray32 {
+            num_elems,
+            offset_byte,
+        } => (num_elems as u64, offset_byte as u64),
+        DataValue::VariableLengthArray64 {
+            num_elems,
+            offset_byte,
+        } => (num_elems, offset_byte),
+        _ => return None,
+    };
+
+    (num_elems > 0).then_some((num_elems, offset_byte))
+}
 
-        Some(value)
+/// Get the `N` big endian bytes of the pixel `idx` from a GZIP_2 decompressed tile.
+/// The bytes are shuffled, i.e. the most significant bytes of all the pixels come first
+fn gzip2_bytes<const N: usize>(buf: &[u8], idx: usize) -> [u8; N] {
+    let step = buf.len() / N;
+    let mut bytes = [0_u8; N];
+    for (k, b) in bytes.iter_mut().enumerate() {
+        *b = buf[k * step + idx];
     }
+
+    bytes
 }
 
-impl<R> Iterator for It<R, F64Keywords>
+impl<R, K> It<R, K>
 where
-    R: Read + Seek + Debug,
+    R: Read + Seek,
+    K: Keywords,
 {
-    // Return a vec of fields because to take into account the repeat count value for that field
-    type Item = f32;
+    /// Descriptor of the current tile in the COMPRESSED_DATA field, `None` if the tile is
+    /// stored in one of the fallback fields
+    fn compressed_tile_descriptor(&self, row_data: &[DataValue]) -> Option<(u64, u64)> {
+        self.data_compressed_idx
+            .and_then(|idx| non_empty_array_descriptor(&row_data[idx]))
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        // We first retrieve the whole row from the row data iterator
-        if self.desc.remaining_pixels == 0 {
-            let row_data = self.row_it.next()?;
-
-            let (_, byte_offset) = match row_data[self.data_compressed_idx] {
-                DataValue::VariableLengthArray32 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems as u64, offset_byte as u64),
-                DataValue::VariableLengthArray64 {
-                    num_elems,
-                    offset_byte,
-                } => (num_elems, offset_byte),
-                _ => unreachable!(),
+    /// Read the current tile from the GZIP_COMPRESSED_DATA or UNCOMPRESSED_DATA field
+    ///
+    /// Its big endian pixel values are stored into `raw`
+    fn read_raw_tile(&mut self, row_data: &[DataValue]) -> Result<(), Error> {
+        let descriptor =
+            |idx: Option<usize>| idx.and_then(|idx| non_empty_array_descriptor(&row_data[idx]));
+
+        let (gzip, byte_offset) =
+            if let Some((_, byte_offset)) = descriptor(self.gzip_compressed_data_idx) {
+                (true, byte_offset)
+            } else if let Some((_, byte_offset)) = descriptor(self.uncompressed_data_idx) {
+                (false, byte_offset)
+            } else {
+                return Err(Error::StaticError(
+                "Tile is not stored in COMPRESSED_DATA, GZIP_COMPRESSED_DATA or UNCOMPRESSED_DATA",
+            ));
             };
 
-            let ctx = self.row_it.get_ctx();
-            let row_idx = self.row_it.get_row_idx();
-
-            // Update the tile compressed currently decompressed
-            let num_pixels = self.z_tile.iter().product::<usize>() as u64;
-            self.desc.n_pixels = num_pixels;
-            self.desc.remaining_pixels = num_pixels;
-
-            let TileDesc {
-                keywords:
-                    F64Keywords {
-                        z_scale_idx,
-                        z_zero_idx,
-                        scale,
-                        zero,
-                        z_dither_0,
-                        z_quantiz,
-                        quantiz,
-                        z_blank_idx,
-                        z_blank,
-                    },
-                ..
-            } = &mut self.desc;
-
-            *quantiz = match z_quantiz {
-                ZQuantiz::SubtractiveDither1 => {
-                    let i0 = (row_idx - 1 + ((*z_dither_0) as usize)) % 10000;
-                    let i1 = (RAND_VALUES[i0] * 500.0).floor() as usize;
-                    Quantiz::SubtractiveDither1 { i1 }
-                }
-                ZQuantiz::SubtractiveDither2 => {
-                    let i0 = (row_idx - 1 + (*z_dither_0 as usize)) % 10000;
-                    let i1 = (RAND_VALUES[i0] * 500.0).floor() as usize;
-                    Quantiz::SubtractiveDither2 { i1 }
+        // The tile currently read
+        let row_idx = self.row_it.get_row_idx();
+        let tile = Tile::new(row_idx - 1, &self.z_tile, &self.z_naxis);
+        let num_pixels = tile.num_pixels();
+        self.raw.resize(num_pixels * self.raw_bytes_per_pixel, 0);
+
+        let ctx = self.row_it.get_ctx();
+        let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
+        let off = -(main_data_table_offset as i64) + ctx.theap as i64 + byte_offset as i64;
+
+        self.jump_to_location(
+            |s| {
+                let It { raw, row_it, .. } = s;
+                let reader = row_it.get_reader();
+
+                if gzip {
+                    GzDecoder::new(reader).read_exact(raw)?;
+                } else {
+                    reader.read_exact(raw)?;
                 }
-                _ => Quantiz::NoDither,
-            };
 
-            *scale = match row_data[*z_scale_idx] {
-                DataValue::Float { value, .. } => value,
-                DataValue::Double { value, .. } => value as f32,
-                _ => unreachable!(),
-            };
+                Ok(())
+            },
+            SeekFrom::Current(off),
+        )?;
 
-            *zero = match row_data[*z_zero_idx] {
-                DataValue::Float { value, .. } => value,
-                DataValue::Double { value, .. } => value as f32,
-                _ => unreachable!(),
-            };
+        self.read_null_pixel_mask(row_data, num_pixels)?;
 
-            if let Some(idx) = z_blank_idx {
-                *z_blank = match row_data[*idx] {
-                    DataValue::UnsignedByte { value, .. } => Some(value as i32),
-                    DataValue::Short { value, .. } => Some(value as i32),
-                    DataValue::Integer { value, .. } => Some(value),
-                    DataValue::Long { value, .. } => Some(value as i32),
-                    _ => unreachable!(),
-                }
-            }
+        self.tile = tile;
+        self.desc.n_pixels = num_pixels as u64;
+        self.desc.remaining_pixels = num_pixels as u64;
 
-            // We jump to the heap at the position of the tile
-            // Then we decomp the tile and store it into out internal buf
-            // Finally we go back to the main data table location before jumping to the heap
-            let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
-            let off =
-                // go back to the beginning of the main table data block
-                - (main_data_table_offset as i64)
-                // from the beginning of the main table go to the beginning of the heap
-                + ctx.theap as i64
-                // from the beginning of the heap go to the start of the array
-                + byte_offset as i64;
-            self.jump_to_location(
-                |s| {
-                    let It { buf, row_it, .. } = s;
-
-                    let reader = row_it.get_reader();
-
-                    match s.z_cmp_type {
-                        // For GZIP2, the byte shuffling is done in the next method
-                        ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
-                            let mut gz = GzDecoder::new(reader);
-                            gz.read_exact(&mut buf[..])?;
-                        }
-                        // FIXME support bytepix
-                        ZCmpType::Rice { blocksize, .. } => {
-                            let mut rice = RICEDecoder::<_, i32>::new(
-                                reader,
-                                blocksize as i32,
-                                num_pixels as i32,
-                            );
-                            rice.read_exact(&mut buf[..])?;
-                        }
-                        // Other compression not supported, when parsing the bintable extension keywords
-                        // we ensured that z_image is `None` for other compressions than GZIP or RICE
-                        _ => unreachable!(),
-                    }
+        Ok(())
+    }
 
-                    Ok(())
-                },
-                SeekFrom::Current(off),
-            )
-            .ok()?;
-        }
+    /// Read and decompress the null pixel mask of the current tile if there is one
+    fn read_null_pixel_mask(
+        &mut self,
+        row_data: &[DataValue],
+        num_pixels: usize,
+    ) -> Result<(), Error> {
+        self.mask = Box::default();
 
-        // There is remaining pixels inside our buffer, we simply return the current one
-        let idx = (self.desc.n_pixels - self.desc.remaining_pixels) as usize;
+        let Some((null_pixel_mask_idx, z_mask_cmp)) = self.null_pixel_mask else {
+            return Ok(());
+        };
 
-        let value = match self.z_cmp_type {
-            // 32-bit floating point
-            ZCmpType::Gzip1 => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                let off = 4 * idx;
-                let value = i32::from_be_bytes([
-                    self.buf[off],
-                    self.buf[off + 1],
-                    self.buf[off + 2],
-                    self.buf[off + 3],
-                ]);
-                self.desc.keywords.unquantize(value)
+        let (num_elems, byte_offset) = match row_data[null_pixel_mask_idx] {
+            DataValue::VariableLengthArray32 {
+                num_elems,
+                offset_byte,
+            } => (num_elems as u64, offset_byte as u64),
+            DataValue::VariableLengthArray64 {
+                num_elems,
+                offset_byte,
+            } => (num_elems, offset_byte),
+            _ => {
+                return Err(Error::StaticError(
+                    "NULL_PIXEL_MASK field must be a variable length array",
+                ))
             }
-            ZCmpType::Gzip2 => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                // read from BigEndian, i.e. the most significant byte is at first and the least one is at last position
-                let num_bytes = self.buf.len();
-                let step_msb = num_bytes / 4;
-                let value = ((self.buf[idx] as i32) << 24)
-                    | ((self.buf[idx + step_msb] as i32) << 16)
-                    | ((self.buf[idx + 2 * step_msb] as i32) << 8)
-                    | (self.buf[idx + 3 * step_msb] as i32);
+        };
+        // An empty mask means that the tile does not contain null pixels
+        if num_elems == 0 {
+            return Ok(());
+        }
 
-                self.desc.keywords.unquantize(value)
+        let ctx = self.row_it.get_ctx();
+        let row_idx = self.row_it.get_row_idx();
+        let main_data_table_offset = row_idx * (ctx.naxis1 as usize);
+        let off = -(main_data_table_offset as i64) + ctx.theap as i64 + byte_offset as i64;
+        let max_tile_byte_size = ctx.max_tile_byte_size;
+
+        self.jump_to_location(
+            |s| {
+                let reader = s.row_it.get_reader();
+                s.mask = decompress_null_pixel_mask(
+                    reader,
+                    z_mask_cmp,
+                    num_elems as usize,
+                    num_pixels,
+                    max_tile_byte_size,
+                )?;
+
+                Ok(())
+            },
+            SeekFrom::Current(off),
+        )
+    }
+}
+
+/// Decompress a null pixel mask, a non zero value flags a null pixel
+fn decompress_null_pixel_mask<R: Read>(
+    reader: &mut R,
+    z_mask_cmp: ZCmpType,
+    num_elems: usize,
+    num_pixels: usize,
+    max_tile_byte_size: u64,
+) -> Result<Box<[bool]>, Error> {
+    let mut buf = vec![0_u8; num_pixels * std::mem::size_of::<u32>()];
+
+    match z_mask_cmp {
+        ZCmpType::Gzip1 | ZCmpType::Gzip2 => {
+            // The mask values can be stored on 1, 2 or 4 bytes. The byte read past the
+            // largest mask is enough to reject a stream inflating to more bytes
+            let mut bytes = vec![];
+            GzDecoder::new(reader)
+                .take(4 * num_pixels as u64 + 1)
+                .read_to_end(&mut bytes)?;
+            if bytes.len() as u64 > max_tile_byte_size {
+                return Err(Error::LimitExceeded(
+                    "Null pixel mask size in bytes",
+                    bytes.len() as u64,
+                    max_tile_byte_size,
+                ));
             }
-            // 32-bit floating point
-            ZCmpType::Rice { .. } => {
-                // We need to get the byte index in the buffer storing u32, i.e. 4 bytes per elements
-                let off = 4 * idx;
-                let value = i32::from_ne_bytes([
-                    self.buf[off],
-                    self.buf[off + 1],
-                    self.buf[off + 2],
-                    self.buf[off + 3],
-                ]);
 
-                self.desc.keywords.unquantize(value)
+            let bytepix = bytes.len() / num_pixels.max(1);
+            if !matches!(bytepix, 1 | 2 | 4) || bytes.len() != bytepix * num_pixels {
+                return Err(Error::StaticError(
+                    "Null pixel mask size does not match ZTILEn",
+                ));
             }
-            // Not supported compression/bitpix results in parsing the binary table as normal and thus this part is not reachable
-            _ => unreachable!(),
-        };
-        self.desc.remaining_pixels -= 1;
 
-        Some(value)
+            let mask = (0..num_pixels)
+                .map(|i| {
+                    if z_mask_cmp == ZCmpType::Gzip1 {
+                        bytes[i * bytepix..(i + 1) * bytepix]
+                            .iter()
+                            .any(|&b| b != 0)
+                    } else {
+                        // Bytes are shuffled
+                        (0..bytepix).any(|k| bytes[k * num_pixels + i] != 0)
+                    }
+                })
+                .collect();
+
+            return Ok(mask);
+        }
+        ZCmpType::Rice { blocksize, bytepix } => {
+            rice::decompress_tile(reader, blocksize, bytepix, &mut buf[..])?;
+        }
+        ZCmpType::Hcompress1 => {
+            hcompress::decompress_tile(reader, num_elems, &mut buf[..])?;
+        }
+        ZCmpType::Plio1 => {
+            plio::decompress_tile(reader, num_elems, &mut buf[..])?;
+        }
     }
+
+    Ok(buf
+        .chunks_exact(4)
+        .map(|b| b.iter().any(|&b| b != 0))
+        .collect())
 }
 
 impl<R, K> It<R, K>
@@ -856,9 +1056,10 @@ where
         let old_pos = SeekFrom::Start(self.get_reader().stream_position()?);
 
         self.get_reader().seek(pos)?;
-        f(self)?;
-        let _ = self.get_reader().seek(old_pos)?;
+        let result = f(self);
+        // Go back even if the operation failed, so that the reader stays in the main table
+        self.get_reader().seek(old_pos)?;
 
-        Ok(())
+        result
     }
 }
@@ -0,0 +1,229 @@
+//! This code is a port in Rust of CFITSIO's pliocomp.c
+//!
+//! The original code can be found here: <https://github.com/HEASARC/cfitsio/blob/develop/pliocomp.c>
+//! It decodes the line lists of the IRAF PLIO (pixel list) package. A line list is
+//! a sequence of 16-bit instructions, each made of a 4-bit opcode and 12 bits of data.
+//! Only positive pixel values up to 2^24 can be encoded.
+
+use crate::error::Error;
+use std::io::Read;
+
+/// Zero run of `data` pixels
+const M_ZN: i16 = 0;
+/// Set the high value, its 12 most significant bits are given by the next instruction
+const M_SH: i16 = 1;
+/// Increment the high value
+const M_IH: i16 = 2;
+/// Decrement the high value
+const M_DH: i16 = 3;
+/// High value run of `data` pixels
+const M_HN: i16 = 4;
+/// Zero run of `data - 1` pixels followed by one high value pixel
+const M_PN: i16 = 5;
+/// Increment the high value and output one pixel
+const M_IS: i16 = 6;
+/// Decrement the high value and output one pixel
+const M_DS: i16 = 7;
+
+/// Number of words of the line list header, in the new format
+const LL_HEADER_LEN: usize = 7;
+/// Largest number of words a pixel takes in a line list, i.e. setting the high value
+/// on two words and outputting the pixel
+const LL_MAX_WORDS_PER_PIXEL: usize = 3;
+
+/// Read and decompress a PLIO tile, writing its pixels as native endian `i32` into `buf`
+///
+/// # Params
+///
+/// * `reader` - A reader located at the start of the compressed tile
+/// * `num_elems` - The number of 16-bit big endian instructions of the line list
+/// * `buf` - The output buffer, storing 4 bytes per pixel
+pub(crate) fn decompress_tile<R: Read>(
+    reader: &mut R,
+    num_elems: usize,
+    buf: &mut [u8],
+) -> Result<(), Error> {
+    // The number of instructions comes from the file, it is checked against the size
+    // of the tile before allocating
+    let num_pixels = buf.len() / 4;
+    if num_elems > LL_HEADER_LEN + LL_MAX_WORDS_PER_PIXEL * num_pixels {
+        return Err(Error::StaticError(
+            "PLIO line list is longer than the tile can need",
+        ));
+    }
+
+    let mut bytes = vec![0_u8; 2 * num_elems];
+    reader.read_exact(&mut bytes)?;
+
+    let ll_src = bytes
+        .chunks_exact(2)
+        .map(|b| i16::from_be_bytes([b[0], b[1]]))
+        .collect::<Vec<_>>();
+
+    let mut pixels = vec![0_i32; num_pixels];
+    pl_l2pi(&ll_src, &mut pixels)?;
+
+    for (dst, v) in buf.chunks_exact_mut(4).zip(pixels) {
+        dst.copy_from_slice(&v.to_ne_bytes());
+    }
+
+    Ok(())
+}
+
+/// Translate a PLIO line list into an integer pixel array
+///
+/// # Params
+///
+/// * `ll_src` - The encoded line list
+/// * `px_dst` - The output pixels. Pixels not covered by the line list are set to 0
+fn pl_l2pi(ll_src: &[i16], px_dst: &mut [i32]) -> Result<(), Error> {
+    let header = |i: usize| {
+        ll_src.get(i).map(|&v| v as i32).ok_or(Error::StaticError(
+            "PLIO line list is too short to contain its header",
+        ))
+    };
+
+    // The old format stores the length in the third word, the new one sets it to a negative
+    // version number and stores the length in two words
+    let (lllen, llfirt) = if header(2)? > 0 {
+        (header(2)?, 3)
+    } else {
+        ((header(4)? << 15) + header(3)?, header(1)?)
+    };
+
+    px_dst.fill(0);
+    if px_dst.is_empty() || lllen <= 0 {
+        return Ok(());
+    }
+    if llfirt < 0 || lllen as usize > ll_src.len() {
+        return Err(Error::StaticError(
+            "PLIO line list length exceeds the compressed tile",
+        ));
+    }
+
+    let npix = px_dst.len();
+    // Index of the next pixel to output
+    let mut op = 0;
+    // Current high value
+    let mut pv = 1_i32;
+
+    let mut ip = llfirt as usize;
+    while ip < lllen as usize && op < npix {
+        let opcode = (ll_src[ip] >> 12) & 0xf;
+        let data = (ll_src[ip] & 0xfff) as usize;
+
+        match opcode {
+            M_ZN => {
+                // Output pixels are already set to 0
+                op += data;
+            }
+            M_SH => {
+                ip += 1;
+                let high = *ll_src.get(ip).ok_or(Error::StaticError(
+                    "PLIO line list ends in the middle of an instruction",
+                ))?;
+                pv = ((high as i32) << 12) + data as i32;
+            }
+            M_IH => pv = pv.wrapping_add(data as i32),
+            M_DH => pv = pv.wrapping_sub(data as i32),
+            M_HN => {
+                let end = npix.min(op + data);
+                px_dst[op..end].fill(pv);
+                op += data;
+            }
+            M_PN => {
+                op += data;
+                if data > 0 && op <= npix {
+                    px_dst[op - 1] = pv;
+                }
+            }
+            M_IS | M_DS => {
+                if opcode == M_IS {
+                    pv = pv.wrapping_add(data as i32);
+                } else {
+                    pv = pv.wrapping_sub(data as i32);
+                }
+                px_dst[op] = pv;
+                op += 1;
+            }
+            // Unknown opcodes are skipped
+            _ => (),
+        }
+
+        ip += 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decompress_tile, pl_l2pi};
+
+    #[test]
+    fn test_pl_l2pi() {
+        // New format header: 7 words, the line list spanning 14 words
+        let ll_src = [
+            0,
+            7,
+            -100,
+            14,
+            0,
+            0,
+            0,
+            // 2 zeros
+            0x0002,
+            // 3 pixels at 1
+            0x4003,
+            // pv = 5000
+            0x1000 | (5000 & 0xfff),
+            5000 >> 12,
+            // 1 zero and one pixel at 5000
+            0x5002,
+            // pixel at 5002
+            0x6002,
+            // pixel at 4999
+            0x7003,
+        ];
+
+        let mut pixels = [-1; 12];
+        pl_l2pi(&ll_src, &mut pixels).unwrap();
+        assert_eq!(pixels, [0, 0, 1, 1, 1, 0, 5000, 5002, 4999, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_pl_l2pi_old_format() {
+        // Old format header: 3 words, the line list spanning 5 words
+        let ll_src = [0, 0, 5, 0x4002, 0x0001];
+
+        let mut pixels = [0; 4];
+        pl_l2pi(&ll_src, &mut pixels).unwrap();
+        assert_eq!(pixels, [1, 1, 0, 0]);
+
+        assert!(pl_l2pi(&ll_src[..2], &mut pixels).is_err());
+    }
+
+    #[test]
+    fn test_pl_l2pi_high_value_overflow() {
+        // Set the high value to its largest one and increment it until it wraps
+        let mut ll_src = vec![0, 7, -100, 0, 0, 0, 0, 0x1fff, i16::MAX];
+        ll_src.resize(ll_src.len() + 500_000, 0x2fff);
+        ll_src.push(0x6000);
+        let len = ll_src.len() as i32;
+        ll_src[3] = (len & 0x7fff) as i16;
+        ll_src[4] = (len >> 15) as i16;
+
+        let mut pixels = [0; 1];
+        pl_l2pi(&ll_src, &mut pixels).unwrap();
+        let pv = ((i16::MAX as i32) << 12) + 0xfff;
+        assert_eq!(pixels, [pv.wrapping_add(500_000 * 0xfff)]);
+    }
+
+    #[test]
+    fn test_decompress_tile_too_long() {
+        // A 2 pixel tile cannot need more than 13 instructions, nothing is read
+        let mut buf = [0; 8];
+        assert!(decompress_tile(&mut &[][..], 14, &mut buf).is_err());
+        assert!(decompress_tile(&mut &[0; 26][..], 13, &mut buf).is_ok());
+    }
+}
@@ -57,6 +57,57 @@ enum RICEState {
     },
 }
 
+/// Read and decompress a RICE tile, writing its pixels as native endian `i32` into `buf`
+///
+/// # Params
+///
+/// * `reader` - A reader located at the start of the compressed tile
+/// * `blocksize` - coding block size, usually 32 is given
+/// * `bytepix` - The number of bytes of the pixels that have been compressed, i.e. 1, 2 or 4
+/// * `buf` - The output buffer, storing 4 bytes per pixel
+pub(crate) fn decompress_tile<R: Read>(
+    reader: R,
+    blocksize: u8,
+    bytepix: u8,
+    buf: &mut [u8],
+) -> Result<(), crate::error::Error> {
+    let num_pixels = buf.len() / 4;
+
+    // Pixels are decoded at the beginning of the buffer and then widened to 4 bytes,
+    // starting from the last one so that the ones not already widened are never overwritten
+    match bytepix {
+        1 => {
+            RICEDecoder::<_, u8>::new(reader, blocksize as i32, num_pixels as i32)
+                .read_exact(&mut buf[..num_pixels])?;
+
+            for i in (0..num_pixels).rev() {
+                let value = buf[i] as i32;
+                buf[4 * i..4 * (i + 1)].copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+        2 => {
+            RICEDecoder::<_, i16>::new(reader, blocksize as i32, num_pixels as i32)
+                .read_exact(&mut buf[..2 * num_pixels])?;
+
+            for i in (0..num_pixels).rev() {
+                let value = i16::from_ne_bytes([buf[2 * i], buf[2 * i + 1]]) as i32;
+                buf[4 * i..4 * (i + 1)].copy_from_slice(&value.to_ne_bytes());
+            }
+        }
+        4 => {
+            RICEDecoder::<_, i32>::new(reader, blocksize as i32, num_pixels as i32)
+                .read_exact(buf)?;
+        }
+        _ => {
+            return Err(crate::error::Error::StaticError(
+                "RICE compression only supports BYTEPIX of 1, 2 or 4",
+            ))
+        }
+    }
+
+    Ok(())
+}
+
 use std::marker::PhantomData;
 #[derive(Debug)]
 pub(crate) struct RICEDecoder<R, T> {
@@ -370,3 +421,34 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::decompress_tile;
+    use test_case::test_case;
+
+    fn to_i32(buf: &[u8]) -> Vec<i32> {
+        buf.chunks_exact(4)
+            .map(|b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    // Low entropy block: all the pixels equal the first one
+    #[test_case(&[7, 0x00], 1, [7, 7, 7, 7])]
+    #[test_case(&[0x01, 0x02, 0x00], 2, [258, 258, 258, 258])]
+    // High entropy block: differences are stored on bytepix bytes
+    #[test_case(&[7, 0xe0, 0x00, 0x81, 0x20, 0x00], 1, [7, 9, 4, 4])]
+    #[test_case(&[0xff, 0xfd, 0xf0, 0x00, 0x00, 0x25, 0xe0, 0x00, 0x00, 0x25, 0xd0], 2, [-3, 300, 300, -3])]
+    fn test_rice_bytepix(input: &[u8], bytepix: u8, expected: [i32; 4]) {
+        let mut buf = [0_u8; 16];
+        decompress_tile(input, 32, bytepix, &mut buf).unwrap();
+
+        assert_eq!(to_i32(&buf), expected);
+    }
+
+    #[test]
+    fn test_rice_bad_bytepix() {
+        let mut buf = [0_u8; 16];
+        assert!(decompress_tile(&[0_u8; 8][..], 32, 3, &mut buf).is_err());
+    }
+}
@@ -0,0 +1,391 @@
+//! Decompression of the tables following the tiled table compression convention
+//!
+//! Each row of the compressed table stores a tile of ZTILELEN rows of the original table.
+//! Every field of a tile is compressed separately, the values of the rows of the tile being
+//! concatenated before being compressed with the ZCTYPn algorithm.
+
+use flate2::read::GzDecoder;
+use std::fmt::Debug;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use super::rice;
+use crate::error::Error;
+use crate::hdu::data::bintable::data::TableData;
+use crate::hdu::data::bintable::DataValue;
+use crate::hdu::data::iter::TryNext;
+use crate::hdu::header::extension::bintable::{BinTable, TFormType, TileCompressedTable, ZCmpType};
+
+/// The data of a tile compressed table
+#[derive(Debug)]
+pub struct TileCompressedTableData<R> {
+    /// The compressed table
+    pub(crate) data: TableData<R>,
+    /// Context of the original table
+    ctx: BinTable,
+    /// Number of rows in each tile
+    z_tilelen: usize,
+    /// Compression algorithm of each field
+    z_ctypes: Vec<ZCmpType>,
+}
+
+impl<R> TileCompressedTableData<R> {
+    pub(crate) fn new(mut data: TableData<R>, config: &TileCompressedTable) -> Self {
+        let ctx = data.get_ctx().uncompressed_table(config);
+
+        // do not read the heap, we will manage our way decompressing the tiles
+        data.read_the_heap(false);
+        Self {
+            data,
+            ctx,
+            z_tilelen: config.z_tilelen,
+            z_ctypes: config.z_ctypes.clone(),
+        }
+    }
+
+    /// Context of the original table, i.e. with the original TFORMs
+    pub fn get_ctx(&self) -> &BinTable {
+        &self.ctx
+    }
+}
+
+impl<R> TileCompressedTableData<R>
+where
+    R: Read + Seek + Debug,
+{
+    /// Decompress all the tiles into memory
+    ///
+    /// The returned table data can be read as an ordinary binary table having the
+    /// original formats of the fields
+    pub fn decompress(self) -> Result<TableData<Cursor<Box<[u8]>>>, Error> {
+        let Self {
+            data,
+            ctx,
+            z_tilelen,
+            z_ctypes,
+        } = self;
+
+        let compressed_ctx = data.get_ctx().clone();
+        let num_fields = z_ctypes.len();
+
+        // Read the descriptors of the compressed fields of each tile
+        let mut rows = data.row_iter();
+        let mut tiles = vec![];
+        while let Some(row_data) = rows.try_next()? {
+            let descriptors = row_data
+                .iter()
+                .map(|value| match *value {
+                    DataValue::VariableLengthArray32 {
+                        num_elems,
+                        offset_byte,
+                    } => Ok((num_elems as u64, offset_byte as u64)),
+                    DataValue::VariableLengthArray64 {
+                        num_elems,
+                        offset_byte,
+                    } => Ok((num_elems, offset_byte)),
+                    _ => Err(Error::StaticError(
+                        "Tile compressed table fields must be variable length arrays",
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            tiles.push(descriptors);
+        }
+
+        // The reader is now located at the end of the main data table
+        let reader = rows.get_reader();
+        let main_data_table_end = reader.stream_position()?;
+        let heap_start = main_data_table_end - compressed_ctx.naxis1 * compressed_ctx.naxis2
+            + compressed_ctx.theap as u64;
+
+        let naxis1 = ctx.naxis1 as usize;
+        let naxis2 = ctx.naxis2 as usize;
+        let mut bytes = vec![0_u8; naxis1 * naxis2];
+
+        for (tile_idx, descriptors) in tiles.iter().enumerate() {
+            if descriptors.len() != num_fields {
+                return Err(Error::StaticError(
+                    "Tile compressed table rows must store one compressed array per field",
+                ));
+            }
+
+            // The last tile may contain fewer rows
+            let first_row = tile_idx * z_tilelen;
+            let num_rows = z_tilelen.min(naxis2.saturating_sub(first_row));
+
+            let mut col_byte_offset = 0;
+            for ((&(num_bytes, offset_byte), tform), z_ctype) in
+                descriptors.iter().zip(&ctx.tforms).zip(&z_ctypes)
+            {
+                let num_bytes_field = tform.num_bytes_field();
+                let mut buf = vec![0_u8; num_rows * num_bytes_field];
+
+                reader.seek(SeekFrom::Start(heap_start + offset_byte))?;
+                decompress_field(
+                    (&mut *reader).take(num_bytes),
+                    *z_ctype,
+                    elem_byte_size(tform),
+                    &mut buf,
+                )?;
+
+                // Scatter the values of the field into the rows of the tile
+                for (row, values) in buf.chunks_exact(num_bytes_field).enumerate() {
+                    let start = (first_row + row) * naxis1 + col_byte_offset;
+                    bytes[start..(start + num_bytes_field)].copy_from_slice(values);
+                }
+
+                col_byte_offset += num_bytes_field;
+            }
+        }
+
+        // go back to the end of the main data table so that the next HDU can be reached
+        reader.seek(SeekFrom::Start(main_data_table_end))?;
+
+        Ok(TableData::from_ctx(
+            Cursor::new(bytes.into_boxed_slice()),
+            ctx,
+            0,
+        ))
+    }
+}
+
+/// Size in bytes of the elements of a field. GZIP 2 shuffles the bytes of the elements
+fn elem_byte_size(tform: &TFormType) -> usize {
+    match tform {
+        TFormType::I { .. } => 2,
+        TFormType::J { .. } | TFormType::E { .. } | TFormType::C { .. } => 4,
+        TFormType::K { .. } | TFormType::D { .. } | TFormType::M { .. } => 8,
+        _ => 1,
+    }
+}
+
+/// Decompress the values of a field for all the rows of a tile
+///
+/// # Params
+///
+/// * `reader` - A reader on the compressed bytes
+/// * `z_ctype` - The compression algorithm of the field
+/// * `elem_byte_size` - The size in bytes of the elements of the field
+/// * `buf` - The output buffer, storing the big endian values of the rows of the tile
+fn decompress_field<R: Read>(
+    reader: R,
+    z_ctype: ZCmpType,
+    elem_byte_size: usize,
+    buf: &mut [u8],
+) -> Result<(), Error> {
+    match z_ctype {
+        ZCmpType::Gzip1 => {
+            GzDecoder::new(reader).read_exact(buf)?;
+        }
+        ZCmpType::Gzip2 => {
+            // The most significant bytes of all the elements come first
+            let mut shuffled = vec![0_u8; buf.len()];
+            GzDecoder::new(reader).read_exact(&mut shuffled)?;
+
+            let num_elems = buf.len() / elem_byte_size;
+            for (i, elem) in buf.chunks_exact_mut(elem_byte_size).enumerate() {
+                for (j, byte) in elem.iter_mut().enumerate() {
+                    *byte = shuffled[j * num_elems + i];
+                }
+            }
+        }
+        ZCmpType::Rice { blocksize, bytepix } => {
+            let bytepix = bytepix as usize;
+            let mut values = vec![0_u8; 4 * (buf.len() / bytepix)];
+            rice::decompress_tile(reader, blocksize, bytepix as u8, &mut values)?;
+
+            // Keep the least significant bytes of the decoded values
+            for (elem, value) in buf.chunks_exact_mut(bytepix).zip(values.chunks_exact(4)) {
+                let value = i32::from_ne_bytes([value[0], value[1], value[2], value[3]]);
+                elem.copy_from_slice(&value.to_be_bytes()[(4 - bytepix)..]);
+            }
+        }
+        // Other compressions are discarded when parsing the header
+        ZCmpType::Plio1 | ZCmpType::Hcompress1 => unreachable!(),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::hdu::data::bintable::data::BinaryTableData;
+    use crate::hdu::data::bintable::{ColumnId, DataValue};
+    use crate::mock::MockFits;
+    use crate::{Fits, Limits, ParseOptions, HDU};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::{Cursor, Read, Write};
+
+    /// Build a FITS file storing a table of 3 rows compressed in tiles of 2 rows
+    fn mock_tile_compressed_table() -> Vec<u8> {
+        let gzip = |bytes: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(bytes).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        // ID: 1I compressed with RICE_1. Low entropy blocks storing only their first value
+        // FLUX: 1E compressed with GZIP_2, the bytes of the values being shuffled
+        // NAME: 3A compressed with GZIP_1
+        let shuffle = |values: &[f32]| {
+            let bytes = values.iter().map(|v| v.to_be_bytes()).collect::<Vec<_>>();
+            (0..4)
+                .flat_map(|i| bytes.iter().map(move |b| b[i]))
+                .collect::<Vec<_>>()
+        };
+        let tiles = [
+            [
+                vec![0x01, 0x02, 0x00],
+                gzip(&shuffle(&[1.5, -2.0])),
+                gzip(b"abcde "),
+            ],
+            [
+                vec![0x00, 0x05, 0x00],
+                gzip(&shuffle(&[0.25])),
+                gzip(b"xyz"),
+            ],
+        ];
+
+        let mut descriptors = vec![];
+        let mut heap: Vec<u8> = vec![];
+        for tile in &tiles {
+            for field in tile {
+                descriptors.push((field.len(), heap.len()));
+                heap.extend(field);
+            }
+        }
+
+        let main_table = descriptors
+            .iter()
+            .flat_map(|&(num_elems, offset)| {
+                [
+                    (num_elems as i32).to_be_bytes(),
+                    (offset as i32).to_be_bytes(),
+                ]
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let pcount = format!("PCOUNT  = {:>20}", heap.len());
+        MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "EXTEND  =                    T",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                   24",
+                "NAXIS2  =                    2",
+                &pcount,
+                "GCOUNT  =                    1",
+                "TFIELDS =                    3",
+                "TTYPE1  = 'ID      '",
+                "TFORM1  = '1PB     '",
+                "TTYPE2  = 'FLUX    '",
+                "TFORM2  = '1PB     '",
+                "TTYPE3  = 'NAME    '",
+                "TFORM3  = '1PB     '",
+                "ZTABLE  =                    T",
+                "ZNAXIS1 =                    9",
+                "ZNAXIS2 =                    3",
+                "ZTILELEN=                    2",
+                "ZFORM1  = '1I      '",
+                "ZCTYP1  = 'RICE_1  '",
+                "ZFORM2  = '1E      '",
+                "ZCTYP2  = 'GZIP_2  '",
+                "ZFORM3  = '3A      '",
+                "ZCTYP3  = 'GZIP_1  '",
+                "END",
+            ])
+            .data(&main_table)
+            .data(&heap)
+            .build()
+    }
+
+    #[test]
+    fn test_decompress_tile_compressed_table() {
+        let buf = mock_tile_compressed_table();
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+
+        assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("Should contain a binary table extension");
+        };
+        let BinaryTableData::TileCompressedTable(table) = hdu_list.get_data(&hdu) else {
+            panic!("Should be a tile compressed table");
+        };
+        assert_eq!(table.get_ctx().get_num_rows(), 3);
+
+        let data = table.decompress().unwrap();
+        let mut expected = vec![];
+        for (id, flux, name) in [
+            (258_i16, 1.5_f32, b"abc"),
+            (258, -2.0, b"de "),
+            (5, 0.25, b"xyz"),
+        ] {
+            expected.extend(id.to_be_bytes());
+            expected.extend(flux.to_be_bytes());
+            expected.extend(name);
+        }
+        let mut bytes = vec![];
+        data.bytes().read_to_end(&mut bytes).unwrap();
+        assert_eq!(bytes, expected);
+
+        // The decompressed table is read with the original formats
+        let mut data = table_data(&buf);
+        data.select_fields(&[ColumnId::Name("FLUX")]);
+        let fluxes = data
+            .map(|value| match value {
+                DataValue::Float { value, .. } => value,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(fluxes, [1.5, -2.0, 0.25]);
+
+        // The next HDU can still be reached
+        assert!(hdu_list.next().is_none());
+    }
+
+    #[test]
+    fn test_uncompressed_table_limit() {
+        let mut buf = mock_tile_compressed_table();
+        let card = b"ZNAXIS2 =                    3";
+        let idx = buf.windows(card.len()).position(|w| w == card).unwrap();
+        buf[idx..idx + card.len()].copy_from_slice(b"ZNAXIS2 =              1000000");
+
+        let options = ParseOptions {
+            limits: Limits {
+                max_data_unit_byte_size: 1_000_000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut hdu_list = Fits::from_reader_with(Cursor::new(&buf[..]), options);
+        assert!(matches!(hdu_list.next(), Some(Ok(HDU::Primary(_)))));
+        let err = hdu_list
+            .next()
+            .unwrap()
+            .expect_err("The table is too large");
+        assert_eq!(
+            err.inner(),
+            &Error::LimitExceeded("Uncompressed table size in bytes", 9_000_000, 1_000_000)
+        );
+    }
+
+    fn table_data(buf: &[u8]) -> crate::TableData<Cursor<Box<[u8]>>> {
+        let mut hdu_list = Fits::from_reader(Cursor::new(buf));
+        hdu_list.next();
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            unreachable!();
+        };
+        let BinaryTableData::TileCompressedTable(table) = hdu_list.get_data(&hdu) else {
+            unreachable!();
+        };
+        table.decompress().unwrap()
+    }
+}
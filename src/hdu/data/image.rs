@@ -1,7 +1,8 @@
 //pub use super::Access;
 //use super::DataAsyncBufRead;
 
-use crate::hdu::data::iter::It;
+use crate::error::Error;
+use crate::hdu::data::iter::{It, Value};
 use crate::hdu::header::{Bitpix, Header, Xtension};
 use async_trait::async_trait;
 use futures::AsyncReadExt;
@@ -29,6 +30,8 @@ where
 pub struct ImageData<R> {
     start_pos: u64,
     num_bytes_data_block: u64,
+    /// The size of each axis given by the "NAXISn" cards
+    pub(crate) naxis: Box<[u64]>,
     pixels: Pixels<R>,
 }
 
@@ -68,6 +71,7 @@ where
         Self {
             start_pos,
             num_bytes_data_block: limit,
+            naxis: ctx.get_naxis().into(),
             pixels,
         }
     }
@@ -78,6 +82,124 @@ where
     }
 }
 
+/// Pixel types that can be read in bulk from [Pixels]
+pub trait PixelType: Value {
+    /// The BITPIX value of an image storing pixels of that type
+    const BITPIX: Bitpix;
+
+    /// Get the pixel iterator if its type matches `Self`
+    fn it_mut<R>(pixels: &mut Pixels<R>) -> Option<&mut It<R, Self>>;
+}
+
+impl PixelType for u8 {
+    const BITPIX: Bitpix = Bitpix::U8;
+
+    fn it_mut<R>(pixels: &mut Pixels<R>) -> Option<&mut It<R, Self>> {
+        if let Pixels::U8(it) = pixels {
+            Some(it)
+        } else {
+            None
+        }
+    }
+}
+impl PixelType for i16 {
+    const BITPIX: Bitpix = Bitpix::I16;
+
+    fn it_mut<R>(pixels: &mut Pixels<R>) -> Option<&mut It<R, Self>> {
+        if let Pixels::I16(it) = pixels {
+            Some(it)
+        } else {
+            None
+        }
+    }
+}
+impl PixelType for i32 {
+    const BITPIX: Bitpix = Bitpix::I32;
+
+    fn it_mut<R>(pixels: &mut Pixels<R>) -> Option<&mut It<R, Self>> {
+        if let Pixels::I32(it) = pixels {
+            Some(it)
+        } else {
+            None
+        }
+    }
+}
+impl PixelType for i64 {
+    const BITPIX: Bitpix = Bitpix::I64;
+
+    fn it_mut<R>(pixels: &mut Pixels<R>) -> Option<&mut It<R, Self>> {
+        if let Pixels::I64(it) = pixels {
+            Some(it)
+        } else {
+            None
+        }
+    }
+}
+impl PixelType for f32 {
+    const BITPIX: Bitpix = Bitpix::F32;
+
+    fn it_mut<R>(pixels: &mut Pixels<R>) -> Option<&mut It<R, Self>> {
+        if let Pixels::F32(it) = pixels {
+            Some(it)
+        } else {
+            None
+        }
+    }
+}
+impl PixelType for f64 {
+    const BITPIX: Bitpix = Bitpix::F64;
+
+    fn it_mut<R>(pixels: &mut Pixels<R>) -> Option<&mut It<R, Self>> {
+        if let Pixels::F64(it) = pixels {
+            Some(it)
+        } else {
+            None
+        }
+    }
+}
+
+impl<R> Pixels<R>
+where
+    R: Read,
+{
+    /// Read the next pixels into a caller provided buffer
+    ///
+    /// `T` must match the BITPIX of the image, e.g. `f32` for BITPIX = -32.
+    /// Returns the number of pixels read. See [It::read_into].
+    pub fn read_into<T: PixelType>(&mut self, dst: &mut [T]) -> Result<usize, Error> {
+        T::it_mut(self)
+            .ok_or(Error::StaticError(
+                "The pixel type asked does not match the BITPIX of the image",
+            ))?
+            .read_into(dst)
+    }
+
+    /// Read all the remaining pixels into a vector
+    ///
+    /// `T` must match the BITPIX of the image, e.g. `f32` for BITPIX = -32.
+    /// See [It::read_to_vec].
+    pub fn read_to_vec<T: PixelType>(&mut self) -> Result<Vec<T>, Error> {
+        T::it_mut(self)
+            .ok_or(Error::StaticError(
+                "The pixel type asked does not match the BITPIX of the image",
+            ))?
+            .read_to_vec()
+    }
+
+    /// Read all the remaining pixels available in the stream into a vector,
+    /// with the number of the pixels missing in a truncated file
+    ///
+    /// `T` must match the BITPIX of the image, e.g. `f32` for BITPIX = -32.
+    /// See [It::read_available].
+    pub fn read_available<T: PixelType>(&mut self) -> Result<(Vec<T>, usize), Error> {
+        T::it_mut(self)
+            .ok_or(Error::StaticError(
+                "The pixel type asked does not match the BITPIX of the image",
+            ))?
+            .read_available()
+    }
+}
+
 use std::io::Cursor;
 impl<'a, R> ImageData<&'a mut Cursor<R>>
 where
@@ -7,38 +7,136 @@ use serde::Serialize;
 use byteorder::ReadBytesExt;
 
 use crate::hdu::Error;
-pub trait Value: Sized {
+pub trait Value: Sized + Copy + Default {
     fn read_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error>;
+
+    /// View a slice of values as its raw bytes so that a reader can directly
+    /// fill it
+    fn as_bytes_mut(values: &mut [Self]) -> &mut [u8];
+
+    /// Convert in place values whose bytes are stored in the FITS big endian
+    /// order to the native byte order
+    ///
+    /// This is written as a plain loop over the slice so that the compiler can
+    /// auto-vectorize it
+    fn from_be_slice(values: &mut [Self]);
+}
+
+/// Reinterpret a slice of primitive numbers as bytes
+///
+/// Only called with the primitive integer and floating point types below for which
+/// every bit pattern is valid and that do not have any padding
+macro_rules! as_bytes_mut {
+    ($values:expr, $t:ty) => {{
+        let values: &mut [$t] = $values;
+        let len = std::mem::size_of_val(values);
+        // SAFETY: `$t` is a primitive number type without padding and for which any
+        // byte pattern is a valid value. The returned slice borrows `values` mutably
+        // and covers exactly its memory.
+        unsafe { std::slice::from_raw_parts_mut(values.as_mut_ptr() as *mut u8, len) }
+    }};
 }
+
 impl Value for u8 {
     fn read_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
         Ok(reader.read_u8()?)
     }
+
+    fn as_bytes_mut(values: &mut [Self]) -> &mut [u8] {
+        values
+    }
+
+    fn from_be_slice(_values: &mut [Self]) {
+        // a byte has no endianness
+    }
 }
 impl Value for i16 {
     fn read_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
         Ok(reader.read_i16::<BigEndian>()?)
     }
+
+    fn as_bytes_mut(values: &mut [Self]) -> &mut [u8] {
+        as_bytes_mut!(values, i16)
+    }
+
+    fn from_be_slice(values: &mut [Self]) {
+        for v in values {
+            *v = i16::from_be(*v);
+        }
+    }
 }
 impl Value for i32 {
     fn read_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
         Ok(reader.read_i32::<BigEndian>()?)
     }
+
+    fn as_bytes_mut(values: &mut [Self]) -> &mut [u8] {
+        as_bytes_mut!(values, i32)
+    }
+
+    fn from_be_slice(values: &mut [Self]) {
+        for v in values {
+            *v = i32::from_be(*v);
+        }
+    }
 }
 impl Value for i64 {
     fn read_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
         Ok(reader.read_i64::<BigEndian>()?)
     }
+
+    fn as_bytes_mut(values: &mut [Self]) -> &mut [u8] {
+        as_bytes_mut!(values, i64)
+    }
+
+    fn from_be_slice(values: &mut [Self]) {
+        for v in values {
+            *v = i64::from_be(*v);
+        }
+    }
 }
 impl Value for f32 {
     fn read_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
         Ok(reader.read_f32::<BigEndian>()?)
     }
+
+    fn as_bytes_mut(values: &mut [Self]) -> &mut [u8] {
+        as_bytes_mut!(values, f32)
+    }
+
+    fn from_be_slice(values: &mut [Self]) {
+        for v in values {
+            *v = f32::from_bits(u32::from_be(v.to_bits()));
+        }
+    }
 }
 impl Value for f64 {
     fn read_be<R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
         Ok(reader.read_f64::<BigEndian>()?)
     }
+
+    fn as_bytes_mut(values: &mut [Self]) -> &mut [u8] {
+        as_bytes_mut!(values, f64)
+    }
+
+    fn from_be_slice(values: &mut [Self]) {
+        for v in values {
+            *v = f64::from_bits(u64::from_be(v.to_bits()));
+        }
+    }
+}
+
+/// Fallible iteration over the data of a HDU
+///
+/// The [Iterator] implementations of the data iterators stop on the first error, e.g.
+/// when the reader fails or the stream ends before the end of the data unit, which
+/// cannot be told apart from the end of the data. [TryNext::try_next] returns the
+/// error instead, and `Ok(None)` once all the data has been read.
+pub trait TryNext {
+    type Item;
+
+    /// Read the next item
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error>;
 }
 
 /// An iterator over the data.
@@ -53,6 +151,9 @@ pub struct It<R, T> {
     num_items: usize,
     /// Number of item read
     cur_idx: usize,
+    /// Bytes of the item at `cur_idx` already consumed from the reader when the stream
+    /// ended in the middle of it
+    partial: Vec<u8>,
     /// The type of element read from the reader
     _t: std::marker::PhantomData<T>,
 }
@@ -98,12 +199,138 @@ where
         Self {
             reader,
             cur_idx: 0,
+            partial: Vec::new(),
             num_items,
             _t: std::marker::PhantomData,
         }
     }
 }
 
+/// Number of bytes decoded per block by [It::read_to_vec]
+const READ_BLOCK_NUM_BYTES: usize = 1 << 20;
+
+impl<R, T> It<R, T>
+where
+    R: Read,
+    T: Value,
+{
+    /// Number of items that remain to be read from the data unit
+    pub fn num_remaining_items(&self) -> usize {
+        self.num_items - self.cur_idx
+    }
+
+    /// Read the next values of the data unit into `dst`
+    ///
+    /// The bytes are read by blocks directly into `dst` and then converted from the FITS
+    /// big endian order in place, which is much faster than calling [Iterator::next] for
+    /// every value.
+    ///
+    /// Returns the number of items read, i.e. the minimum between `dst.len()` and the number
+    /// of items remaining in the data unit.
+    ///
+    /// # Errors
+    ///
+    /// If the reader reaches EOF before all those items could be read, an
+    /// [Error::Truncated] error giving the number of bytes missing until the end of
+    /// the data unit is returned. The items fully read before EOF are still written
+    /// and converted at the beginning of `dst`.
+    ///
+    /// The iterator is then positioned after those items. The bytes of an item partially
+    /// read are kept, so that reading again, e.g. once more bytes are available in the
+    /// stream, resumes at the right position.
+    pub fn read_into(&mut self, dst: &mut [T]) -> Result<usize, Error> {
+        let num_items = dst.len().min(self.num_remaining_items());
+        let dst = &mut dst[..num_items];
+
+        let bytes = T::as_bytes_mut(dst);
+        // Start with the bytes of an item partially read before
+        let mut num_bytes_read = 0;
+        if !bytes.is_empty() {
+            num_bytes_read = self.partial.len();
+            bytes[..num_bytes_read].copy_from_slice(&self.partial);
+            self.partial.clear();
+        }
+        let res = loop {
+            if num_bytes_read == bytes.len() {
+                break Ok(num_items);
+            }
+
+            match self.reader.read(&mut bytes[num_bytes_read..]) {
+                Ok(0) => {
+                    let num_remaining_bytes = self.num_remaining_items() * std::mem::size_of::<T>();
+                    break Err(Error::Truncated(
+                        (num_remaining_bytes - num_bytes_read) as u64,
+                    ));
+                }
+                Ok(n) => num_bytes_read += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(e) => break Err(e.into()),
+            }
+        };
+
+        // Only convert the items that have been entirely read
+        let num_items_read = num_bytes_read / std::mem::size_of::<T>();
+        self.partial
+            .extend_from_slice(&bytes[num_items_read * std::mem::size_of::<T>()..num_bytes_read]);
+        T::from_be_slice(&mut dst[..num_items_read]);
+        self.cur_idx += num_items_read;
+
+        res
+    }
+
+    /// Read all the remaining values of the data unit into a vector
+    ///
+    /// See [It::read_into] for more details. Values are read by blocks of 1MB.
+    pub fn read_to_vec(&mut self) -> Result<Vec<T>, Error> {
+        let num_items = self.num_remaining_items();
+        let block_num_items = (READ_BLOCK_NUM_BYTES / std::mem::size_of::<T>()).max(1);
+
+        // The vector grows one block at a time, a header may claim more values than the stream holds
+        let mut values = Vec::new();
+        while values.len() < num_items {
+            let start = values.len();
+            let end = (start + block_num_items).min(num_items);
+
+            values.reserve(end - start);
+            values.resize(end, T::default());
+            self.read_into(&mut values[start..])?;
+        }
+
+        Ok(values)
+    }
+
+    /// Read all the remaining values of the data unit available in the stream
+    ///
+    /// Contrary to [It::read_to_vec], a truncated data unit is not an error: the values
+    /// entirely read are returned with the number of the missing ones.
+    pub fn read_available(&mut self) -> Result<(Vec<T>, usize), Error> {
+        let num_items = self.num_remaining_items();
+        let block_num_items = (READ_BLOCK_NUM_BYTES / std::mem::size_of::<T>()).max(1);
+
+        // A truncated data unit may hold far fewer values than its header claims
+        let mut values = Vec::new();
+        while values.len() < num_items {
+            let start = values.len();
+            let end = (start + block_num_items).min(num_items);
+
+            values.reserve(end - start);
+            values.resize(end, T::default());
+            match self.read_into(&mut values[start..]) {
+                Ok(_) => (),
+                Err(Error::Truncated(_)) => {
+                    // Drop the values not read
+                    let num_items_read = num_items - self.num_remaining_items();
+                    values.truncate(num_items_read);
+                    break;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok((values, self.num_remaining_items()))
+    }
+}
+
 impl<R, T> Iterator for It<R, T>
 where
     R: Read,
@@ -112,13 +339,25 @@ where
     type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().ok().flatten()
+    }
+}
+
+impl<R, T> TryNext for It<R, T>
+where
+    R: Read,
+    T: Value,
+{
+    type Item = T;
+
+    fn try_next(&mut self) -> Result<Option<Self::Item>, Error> {
         if self.cur_idx == self.num_items {
-            None
+            Ok(None)
         } else {
-            let byte = T::read_be(&mut self.reader);
-            self.cur_idx += 1;
+            let mut value = [T::default()];
+            self.read_into(&mut value)?;
 
-            byte.ok()
+            Ok(Some(value[0]))
         }
     }
 }
@@ -140,12 +379,13 @@ where
         } else {
             // Get the position of the reader since the start of the stream
             let t_bytes = std::mem::size_of::<T>() as i64;
-            let off = (idx as i64 - self.cur_idx as i64) * t_bytes;
+            let off = (idx as i64 - self.cur_idx as i64) * t_bytes - self.partial.len() as i64;
 
             self.reader.seek_relative(off)?;
             self.cur_idx = idx;
+            self.partial.clear();
 
-            self.next()
+            self.try_next()?
                 .ok_or(Error::StaticError("Value to retrieve is out of bounds"))
         }
     }
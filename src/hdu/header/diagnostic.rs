@@ -0,0 +1,69 @@
+//! Problems encountered while parsing a header that did not prevent its parsing
+//!
+//! They are logged as warnings and kept on the [Header](super::Header), so that
+//! they can be retrieved with [Header::diagnostics](super::Header::diagnostics).
+
+use std::fmt;
+
+use log::warn;
+use serde::Serialize;
+
+/// Kind of a problem found in a header
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+pub enum DiagnosticKind {
+    /// A card that could not be parsed, kept as a [Card::Undefined](crate::card::Card::Undefined)
+    UndefinedCard,
+    /// A CONTINUE card not preceded by a string ending with '&', read as a comment
+    OrphanedContinue,
+    /// A table field whose TFORMn, TBCOLn or ZFORMn is missing or invalid. The field is discarded
+    DiscardedField,
+    /// A table field without a TTYPEn name
+    MissingFieldName,
+    /// A TDIMn keyword that is not valid. It is ignored
+    InvalidDimensions,
+    /// A tile compression keyword that is missing or not valid, e.g. ZCMPTYPE
+    InvalidCompression,
+}
+
+/// A problem found while parsing a header
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    /// Index of the card the problem is about, if any
+    pub card_idx: Option<usize>,
+    /// Keyword the problem is about, if any
+    pub keyword: Option<String>,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.card_idx, &self.keyword) {
+            (Some(card_idx), Some(keyword)) => write!(f, "card {card_idx} ({keyword}): ")?,
+            (Some(card_idx), None) => write!(f, "card {card_idx}: ")?,
+            (None, Some(keyword)) => write!(f, "{keyword}: ")?,
+            (None, None) => (),
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Log a problem and add it to the diagnostics of the header being parsed
+pub(crate) fn report(
+    diagnostics: &mut Vec<Diagnostic>,
+    kind: DiagnosticKind,
+    card_idx: Option<usize>,
+    keyword: Option<&str>,
+    message: String,
+) {
+    warn!("{message}");
+
+    diagnostics.push(Diagnostic {
+        kind,
+        card_idx,
+        keyword: keyword.map(ToOwned::to_owned),
+        message,
+    });
+}
@@ -1,10 +1,10 @@
 use async_trait::async_trait;
 
-use log::warn;
 use serde::Serialize;
 
-use crate::error::Error;
+use crate::error::{Error, Location};
 
+use crate::hdu::header::diagnostic::{self, Diagnostic, DiagnosticKind};
 use crate::hdu::header::Bitpix;
 
 use crate::hdu::header::ValueMap;
@@ -98,17 +98,19 @@ impl Xtension for AsciiTable {
         self.naxis1 * self.naxis2
     }
 
-    fn parse(values: &ValueMap) -> Result<Self, Error> {
+    fn parse(values: &ValueMap, diagnostics: &mut Vec<Diagnostic>) -> Result<Self, Error> {
         // BITPIX
         let bitpix = values.check_for_bitpix()?;
         if bitpix != Bitpix::U8 {
-            return Err(Error::StaticError("Ascii Table HDU must have a BITPIX = 8"));
+            return Err(Error::StaticError("Ascii Table HDU must have a BITPIX = 8")
+                .at(Location::keyword("BITPIX")));
         }
 
         // NAXIS
         let naxis = values.check_for_naxis()?;
         if naxis != 2 {
-            return Err(Error::StaticError("Ascii Table HDU must have NAXIS = 2"));
+            return Err(Error::StaticError("Ascii Table HDU must have NAXIS = 2")
+                .at(Location::keyword("NAXIS")));
         }
 
         // NAXIS1
@@ -118,13 +120,15 @@ impl Xtension for AsciiTable {
         // PCOUNT
         let pcount = values.check_for_pcount()?;
         if pcount != 0 {
-            return Err(Error::StaticError("Ascii Table HDU must have PCOUNT = 0"));
+            return Err(Error::StaticError("Ascii Table HDU must have PCOUNT = 0")
+                .at(Location::keyword("PCOUNT")));
         }
 
         // GCOUNT
         let gcount = values.check_for_gcount()?;
         if gcount != 1 {
-            return Err(Error::StaticError("Ascii Table HDU must have GCOUNT = 1"));
+            return Err(Error::StaticError("Ascii Table HDU must have GCOUNT = 1")
+                .at(Location::keyword("GCOUNT")));
         }
 
         // FIELDS
@@ -137,7 +141,13 @@ impl Xtension for AsciiTable {
             let tbcol = match values.get_parsed(&format!("TBCOL{idx_field}")) {
                 Ok(tbcol) => tbcol,
                 Err(err) => {
-                    warn!("Discard field {idx_field}: {err}");
+                    diagnostic::report(
+                        diagnostics,
+                        DiagnosticKind::DiscardedField,
+                        None,
+                        Some(&format!("TBCOL{idx_field}")),
+                        format!("Discard field {idx_field}: {err}"),
+                    );
                     continue;
                 }
             };
@@ -145,7 +155,13 @@ impl Xtension for AsciiTable {
             let tform = match values.get_parsed(&format!("TFORM{idx_field}")) {
                 Ok(tform) => tform,
                 Err(err) => {
-                    warn!("Discard field {idx_field}: {err}");
+                    diagnostic::report(
+                        diagnostics,
+                        DiagnosticKind::DiscardedField,
+                        None,
+                        Some(&format!("TFORM{idx_field}")),
+                        format!("Discard field {idx_field}: {err}"),
+                    );
                     continue;
                 }
             };
@@ -184,6 +200,19 @@ pub enum TFormAsciiTable {
     DFloatingPointExp { w: usize, d: usize },
 }
 
+impl TFormAsciiTable {
+    /// Width in characters of the field
+    pub fn width(&self) -> usize {
+        match *self {
+            TFormAsciiTable::Character { w }
+            | TFormAsciiTable::DecimalInteger { w }
+            | TFormAsciiTable::FloatingPointFixed { w, .. }
+            | TFormAsciiTable::EFloatingPointExp { w, .. }
+            | TFormAsciiTable::DFloatingPointExp { w, .. } => w,
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for TFormAsciiTable {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -1,15 +1,20 @@
 use std::fmt::Debug;
 
-use crate::error::Error;
-use crate::hdu::header::Bitpix;
+use crate::card::Card;
+use crate::error::{Error, Location};
+use crate::hdu::data::bintable::tile_compressed::pixels::Tile;
+use crate::hdu::header::diagnostic::{self, Diagnostic, DiagnosticKind};
+use crate::hdu::header::extension::image::Image;
+use crate::hdu::header::extension::XtensionType;
+use crate::hdu::header::{Bitpix, Header};
 use crate::hdu::Value;
 use async_trait::async_trait;
 use serde::Serialize;
 
 use super::Xtension;
+use crate::fits::Limits;
 
 use crate::hdu::header::ValueMap;
-use log::warn;
 use serde::Deserialize;
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
@@ -51,6 +56,13 @@ pub struct BinTable {
     /// should be interpreted as the same name).
     pub(crate) ttypes: Vec<Option<String>>,
 
+    /// TDIMn keywords. The value field of this indexed keyword shall contain a
+    /// character string describing how to interpret the contents of field n as a
+    /// multidimensional array with a format of '(l,m,n...)' where l, m, n,... are
+    /// the dimensions of the array. The data are ordered such that the array index
+    /// of the first dimension given (l) is the most rapidly varying.
+    pub(crate) tdims: Vec<Option<Box<[usize]>>>,
+
     /// The value field shall contain the number of
     /// bytes that follow the table in the supplemental data area called
     /// the heap.
@@ -63,6 +75,18 @@ pub struct BinTable {
     /// FITS binary table extension contains a compressed image and that logically this extension
     /// should be interpreted as an image and not as a table.
     pub(crate) z_image: Option<TileCompressedImage>,
+
+    /// ZTABLE (required keyword) This keyword must have the logical value T. It indicates that the
+    /// FITS binary table extension contains a compressed table, each row storing a tile of rows of
+    /// the original table, compressed column by column.
+    pub(crate) z_table: Option<Box<TileCompressedTable>>,
+
+    /// Maximum number of elements of a variable length array, see [Limits]
+    #[serde(skip)]
+    pub(crate) max_vla_len: u64,
+    /// Maximum size in bytes of a decompressed tile, see [Limits]
+    #[serde(skip)]
+    pub(crate) max_tile_byte_size: u64,
 }
 
 fn find_field_by_ttype(ttypes: &[Option<String>], ttype: &str) -> Option<usize> {
@@ -92,6 +116,53 @@ impl BinTable {
     pub fn get_z_image(&self) -> &Option<TileCompressedImage> {
         &self.z_image
     }
+
+    pub fn get_z_table(&self) -> Option<&TileCompressedTable> {
+        self.z_table.as_deref()
+    }
+
+    /// Context of the original table stored in a tile compressed table
+    ///
+    /// The fields keep their names and dimensions but get back their original formats
+    pub(crate) fn uncompressed_table(&self, z_table: &TileCompressedTable) -> BinTable {
+        let naxis1 = z_table.z_naxis1;
+        let naxis2 = z_table.z_naxis2;
+
+        BinTable {
+            bitpix: Bitpix::U8,
+            naxis1,
+            naxis2,
+            tfields: self.tfields,
+            theap: (naxis1 * naxis2) as usize,
+            tforms: z_table.z_forms.clone(),
+            ttypes: self.ttypes.clone(),
+            tdims: self.tdims.clone(),
+            pcount: 0,
+            gcount: 1,
+            z_image: None,
+            z_table: None,
+            max_vla_len: self.max_vla_len,
+            max_tile_byte_size: self.max_tile_byte_size,
+        }
+    }
+
+    /// Get the dimensions of a field given by its "TDIMn" card
+    ///
+    /// The first dimension is the most rapidly varying one.
+    /// Returns `None` if the field has no TDIMn card.
+    pub fn get_tdim(&self, col_idx: usize) -> Option<&[usize]> {
+        self.tdims.get(col_idx)?.as_deref()
+    }
+}
+
+/// Parse a TDIMn value of the form '(l,m,n...)'
+fn parse_tdim(tdim: &str) -> Option<Box<[usize]>> {
+    tdim.trim()
+        .strip_prefix('(')?
+        .strip_suffix(')')?
+        .split(',')
+        .map(|dim| dim.trim().parse::<usize>().ok())
+        .collect()
 }
 
 #[derive(Debug, PartialEq, Serialize, Clone)]
@@ -135,12 +206,101 @@ pub struct TileCompressedImage {
     /// further discussion of this keyword.
     pub(crate) z_dither_0: Option<i64>,
 
-    /// Mandatory fields. Data compressed idx of the field
-    pub(crate) data_compressed_idx: usize,
+    /// Idx of the COMPRESSED_DATA field storing the tiles compressed with ZCMPTYPE
+    pub(crate) data_compressed_idx: Option<usize>,
+
+    /// Idx of the GZIP_COMPRESSED_DATA field storing the tiles that could not be compressed with
+    /// ZCMPTYPE, e.g. floating point tiles that cannot be quantized. They are compressed with GZIP 1
+    pub(crate) gzip_compressed_data_idx: Option<usize>,
+
+    /// Idx of the UNCOMPRESSED_DATA field storing the tiles that could not be compressed at all
+    pub(crate) uncompressed_data_idx: Option<usize>,
+
+    /// Idx of the NULL_PIXEL_MASK field storing, for each tile, the compressed mask flagging
+    /// its null pixels, along with the algorithm used to compress it. The latter is given by
+    /// the ZMASKCMP (optional keyword), its default value is RICE 1.
+    pub(crate) null_pixel_mask: Option<(usize, ZCmpType)>,
+}
+
+impl TileCompressedImage {
+    /// Get the compression algorithm of the tiles, given by ZCMPTYPE
+    pub fn get_z_cmp_type(&self) -> ZCmpType {
+        self.z_cmp_type
+    }
+
+    /// Get the number of pixels of the tiles along each axis, given by the ZTILEn keywords
+    pub fn get_z_tilen(&self) -> &[usize] {
+        &self.z_tilen
+    }
+
+    /// Get the dimensions of the uncompressed image, given by the ZNAXISn keywords
+    pub fn get_z_naxisn(&self) -> &[u64] {
+        &self.z_naxisn
+    }
+
+    /// Get the number of tiles along each axis of the image
+    pub fn get_tile_grid(&self) -> Box<[usize]> {
+        self.z_tilen
+            .iter()
+            .zip(self.z_naxisn.iter())
+            .map(|(&tile, &naxis)| (naxis as usize).div_ceil(tile))
+            .collect()
+    }
+
+    /// Get the total number of tiles, i.e. the number of rows of the binary table
+    pub fn get_num_tiles(&self) -> usize {
+        self.get_tile_grid().iter().product()
+    }
+
+    /// Get the pixel bounds of the tile stored in the row `idx` of the binary table
+    ///
+    /// Returns `None` if the image has less tiles
+    pub fn get_tile(&self, idx: usize) -> Option<Tile> {
+        (idx < self.get_num_tiles()).then(|| {
+            let z_naxis = self
+                .z_naxisn
+                .iter()
+                .map(|&naxis| naxis as usize)
+                .collect::<Vec<_>>();
+            Tile::new(idx, &self.z_tilen, &z_naxis)
+        })
+    }
+
+    /// Get the size in bytes of the uncompressed image
+    pub fn get_uncompressed_byte_size(&self) -> u64 {
+        self.z_naxisn.iter().product::<u64>() * (self.z_bitpix.byte_size() as u64)
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Clone)]
+pub struct TileCompressedTable {
+    /// ZNAXIS1 (required keyword) The value field of this keyword shall contain an integer that gives
+    /// the width in bytes of the rows of the uncompressed table.
+    pub z_naxis1: u64,
+
+    /// ZNAXIS2 (required keyword) The value field of this keyword shall contain an integer that gives
+    /// the number of rows of the uncompressed table.
+    pub z_naxis2: u64,
+
+    /// ZTILELEN (required keyword) The value field of this keyword shall contain an integer giving the
+    /// number of rows in each tile. The last tile may contain fewer rows.
+    pub z_tilelen: usize,
+
+    /// ZFORMn (required keywords) The value field of these keywords shall contain the TFORMn value
+    /// of the fields of the uncompressed table.
+    pub(crate) z_forms: Vec<TFormType>,
+
+    /// ZCTYPn (required keywords) The value field of these keywords shall contain the name of the
+    /// algorithm used to compress the fields. GZIP 1 and GZIP 2 are supported on every field,
+    /// RICE 1 only on byte and integer fields.
+    pub(crate) z_ctypes: Vec<ZCmpType>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
 pub(crate) enum ZQuantiz {
+    /// Floating point values are not quantized but losslessly compressed
+    #[serde(rename = "NONE")]
+    Lossless,
     #[serde(rename = "NO_DITHER")]
     NoDither,
     #[serde(rename = "SUBTRACTIVE_DITHER_1")]
@@ -149,8 +309,9 @@ pub(crate) enum ZQuantiz {
     SubtractiveDither2,
 }
 
+/// Compression algorithm of the tiles, given by ZCMPTYPE, ZMASKCMP or ZCTYPn
 #[derive(Debug, PartialEq, Serialize, Clone, Copy)]
-pub(crate) enum ZCmpType {
+pub enum ZCmpType {
     Gzip1,
     Gzip2,
     Rice {
@@ -161,10 +322,402 @@ pub(crate) enum ZCmpType {
         /// Should be recorded in the compressed image header with ZNAMEn = `BYTEPIX`
         bytepix: u8,
     },
-    PLI0_1,
+    Plio1,
+    /// The digitization scale factor recorded with ZNAMEn = `SCALE` is not kept: the one
+    /// stored in each compressed tile is authoritative and used for its decompression
     Hcompress1,
 }
 
+/// Parse the name of a compression algorithm given by ZCMPTYPE or ZMASKCMP
+fn parse_z_cmp_type(values: &ValueMap, name: &str) -> Option<ZCmpType> {
+    match name.trim_ascii_end() {
+        "GZIP_1" => Some(ZCmpType::Gzip1),
+        "GZIP_2" => Some(ZCmpType::Gzip2),
+        "RICE_1" | "RICE_ONE" => {
+            // Retrieve the block size
+            let blocksize = find_compression_parameter(values, "BLOCKSIZE")
+                // Default value: 32
+                .unwrap_or(32);
+
+            // Retrieve the number of bytes of the original pixels
+            let bytepix = find_compression_parameter(values, "BYTEPIX")
+                // Default value: 4
+                .unwrap_or(4);
+
+            Some(ZCmpType::Rice { blocksize, bytepix })
+        }
+        "PLIO_1" => Some(ZCmpType::Plio1),
+        "HCOMPRESS_1" => Some(ZCmpType::Hcompress1),
+        _ => None,
+    }
+}
+
+/// Retrieve the value of a compression parameter recorded with the ZNAMEn/ZVALn keyword pairs
+fn find_compression_parameter<T>(values: &ValueMap, name: &str) -> Option<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    values.iter().find_map(|(zname, val)| {
+        if let Value::String { value, .. } = val {
+            if value == name && zname.starts_with("ZNAME") {
+                let zval = zname.replace("NAME", "VAL");
+
+                values.get_parsed(&zval).ok()
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    })
+}
+
+/// Parse a TFORMn value, e.g. '1J', '20A' or '1PB(120)'
+///
+/// Returns `None` and raises a warning if the format is not recognized
+pub(crate) fn parse_tform(
+    tform: &str,
+    tform_kw: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<TFormType> {
+    let count = tform
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect::<String>();
+
+    let num_count_digits = count.len();
+    let repeat_count = count.parse::<usize>().unwrap_or(1);
+    // If the field type is not found, discard it as well
+    let Some(field_ty) = tform.chars().nth(num_count_digits) else {
+        diagnostic::report(
+            diagnostics,
+            DiagnosticKind::DiscardedField,
+            None,
+            Some(tform_kw),
+            format!("Cannot extract the field type of {tform_kw}"),
+        );
+        return None;
+    };
+
+    let compute_ty_array_desc = |diagnostics: &mut Vec<Diagnostic>| {
+        // Get the type element of the stored array
+        let Some(elem_ty) = tform.chars().nth(num_count_digits + 1) else {
+            diagnostic::report(diagnostics, DiagnosticKind::DiscardedField, None, Some(tform_kw), format!("Could not extract the type from the array descriptor field. Discard {tform_kw}"));
+            return None;
+        };
+
+        let (t_byte_size, ty) = match elem_ty {
+            'L' => (L::BYTES_SIZE, VariableArrayTy::L),
+            'X' => (X::BYTES_SIZE, VariableArrayTy::X),
+            'B' => (B::BYTES_SIZE, VariableArrayTy::B),
+            'I' => (I::BYTES_SIZE, VariableArrayTy::I),
+            'J' => (J::BYTES_SIZE, VariableArrayTy::J),
+            'K' => (K::BYTES_SIZE, VariableArrayTy::K),
+            'A' => (A::BYTES_SIZE, VariableArrayTy::A),
+            'E' => (E::BYTES_SIZE, VariableArrayTy::E),
+            'D' => (D::BYTES_SIZE, VariableArrayTy::D),
+            'C' => (C::BYTES_SIZE, VariableArrayTy::C),
+            'M' => (M::BYTES_SIZE, VariableArrayTy::M),
+            _ => {
+                diagnostic::report(
+                    diagnostics,
+                    DiagnosticKind::DiscardedField,
+                    None,
+                    Some(tform_kw),
+                    format!("Type not recognized. Discard {tform_kw}"),
+                );
+                return None;
+            }
+        };
+
+        Some((t_byte_size, ty))
+    };
+
+    let tformty = match field_ty {
+        // Logical
+        'L' => TFormType::L { repeat_count },
+        // Bit
+        'X' => TFormType::X { repeat_count },
+        // Unsigned Byte
+        'B' => TFormType::B { repeat_count },
+        // 16-bit integer
+        'I' => TFormType::I { repeat_count },
+        // 32-bit integer
+        'J' => TFormType::J { repeat_count },
+        // 64-bit integer
+        'K' => TFormType::K { repeat_count },
+        // Character
+        'A' => TFormType::A { repeat_count },
+        // Single-precision floating point
+        'E' => TFormType::E { repeat_count },
+        // Double-precision floating point
+        'D' => TFormType::D { repeat_count },
+        // Single-precision complex
+        'C' => TFormType::C { repeat_count },
+        // Double-precision complex
+        'M' => TFormType::M { repeat_count },
+        // Array Descriptor 32-bit
+        'P' => {
+            let (t_byte_size, ty) = compute_ty_array_desc(diagnostics)?;
+
+            TFormType::P {
+                t_byte_size: t_byte_size as u64,
+                e_max: 999,
+                ty,
+            }
+        }
+        // Array Descriptor 64-bit
+        'Q' => {
+            let (t_byte_size, ty) = compute_ty_array_desc(diagnostics)?;
+
+            TFormType::Q {
+                t_byte_size: t_byte_size as u64,
+                e_max: 999,
+                ty,
+            }
+        }
+        _ => {
+            diagnostic::report(
+                diagnostics,
+                DiagnosticKind::DiscardedField,
+                None,
+                Some(tform_kw),
+                format!("Field type not recognized. Discard {tform_kw}"),
+            );
+            return None;
+        }
+    };
+
+    Some(tformty)
+}
+
+/// Parse the keywords of a tile compressed table
+///
+/// Returns `None` and raises a warning if the table cannot be decompressed
+fn parse_tile_compressed_table(
+    values: &ValueMap,
+    tforms: &[TFormType],
+    diagnostics: &mut Vec<Diagnostic>,
+) -> Option<TileCompressedTable> {
+    let (Ok(z_naxis1), Ok(z_naxis2), Ok(z_tilelen)) = (
+        values.get_parsed("ZNAXIS1"),
+        values.get_parsed("ZNAXIS2"),
+        values.get_parsed::<usize>("ZTILELEN"),
+    ) else {
+        diagnostic::report(
+            diagnostics,
+            DiagnosticKind::InvalidCompression,
+            None,
+            None,
+            "ZNAXIS1, ZNAXIS2 and ZTILELEN are mandatory. Tile compressed table discarded"
+                .to_owned(),
+        );
+        return None;
+    };
+
+    let (z_forms, z_ctypes) = (1..=tforms.len())
+        .map(|idx_field| {
+            let zform_kw = format!("ZFORM{idx_field}");
+            let zform = values.get_parsed::<String>(&zform_kw).ok().or_else(|| {
+                diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some(&zform_kw), format!("{zform_kw} has not been found. Tile compressed table discarded"));
+                None
+            })?;
+            let zform = parse_tform(&zform, &zform_kw, diagnostics)?;
+
+            let zctype_kw = format!("ZCTYP{idx_field}");
+            let zctype = values.get_parsed::<String>(&zctype_kw).ok().or_else(|| {
+                diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some(&zctype_kw), format!("{zctype_kw} has not been found. Tile compressed table discarded"));
+                None
+            })?;
+
+            // The RICE coded values have the size of the field elements
+            let zctype = match (parse_z_cmp_type(values, &zctype), zform) {
+                (Some(ZCmpType::Gzip1), _) => ZCmpType::Gzip1,
+                (Some(ZCmpType::Gzip2), _) => ZCmpType::Gzip2,
+                (Some(ZCmpType::Rice { .. }), TFormType::B { .. }) => ZCmpType::Rice {
+                    blocksize: 32,
+                    bytepix: 1,
+                },
+                (Some(ZCmpType::Rice { .. }), TFormType::I { .. }) => ZCmpType::Rice {
+                    blocksize: 32,
+                    bytepix: 2,
+                },
+                (Some(ZCmpType::Rice { .. }), TFormType::J { .. }) => ZCmpType::Rice {
+                    blocksize: 32,
+                    bytepix: 4,
+                },
+                _ => {
+                    diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some(&zctype_kw), format!("{zctype_kw} = {zctype:?} is not supported on {zform_kw}. Tile compressed table discarded"));
+                    return None;
+                }
+            };
+
+            if matches!(zform, TFormType::P { .. } | TFormType::Q { .. }) {
+                diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some(&zform_kw), "Variable length array fields are not supported in tile compressed tables. Tile compressed table discarded".to_owned());
+                return None;
+            }
+
+            Some((zform, zctype))
+        })
+        .collect::<Option<(Vec<_>, Vec<_>)>>()?;
+
+    // Bit fields are padded to a whole number of bytes
+    let num_bytes_per_row = z_forms
+        .iter()
+        .map(|zform| zform.num_bytes_field() as u64)
+        .sum::<u64>();
+    if num_bytes_per_row != z_naxis1 || z_tilelen == 0 {
+        diagnostic::report(
+            diagnostics,
+            DiagnosticKind::InvalidCompression,
+            None,
+            None,
+            "ZNAXIS1, ZTILELEN and ZFORMS are not consistent. Tile compressed table discarded"
+                .to_owned(),
+        );
+        return None;
+    }
+
+    Some(TileCompressedTable {
+        z_naxis1,
+        z_naxis2,
+        z_tilelen,
+        z_forms,
+        z_ctypes,
+    })
+}
+
+impl Header<BinTable> {
+    /// Synthesize the header of the image stored as a tile compressed image
+    ///
+    /// ZSIMPLE, ZTENSION, ZBITPIX, ZNAXISn, ZEXTEND, ZBLOCKED, ZPCOUNT, ZGCOUNT, ZHECKSUM and ZDATASUM
+    /// give back the keywords of the uncompressed image. The keywords describing the binary table
+    /// and the compression are removed, the other ones, e.g. the WCS keywords, are kept.
+    pub fn image_header(&self) -> Result<Header<Image>, Error> {
+        if self.get_xtension().z_image.is_none() {
+            return Err(Error::StaticError(
+                "The binary table does not store a tile compressed image",
+            ));
+        }
+
+        let z_card = |z_name: &str, name: &str| {
+            self.get(z_name)
+                .map(|value| Card::Value {
+                    name: name.to_owned(),
+                    value: value.clone(),
+                })
+                .ok_or_else(|| Error::FailFindingKeyword(z_name.to_owned()))
+        };
+
+        // The mandatory keywords come first, in the order of the FITS standard
+        let is_primary = matches!(
+            self.get("ZSIMPLE"),
+            Some(Value::Logical { value: true, .. })
+        );
+        let mut cards = vec![if is_primary {
+            z_card("ZSIMPLE", "SIMPLE")?
+        } else {
+            Card::Xtension {
+                x: XtensionType::Image,
+                comment: None,
+            }
+        }];
+        cards.push(z_card("ZBITPIX", "BITPIX")?);
+        cards.push(z_card("ZNAXIS", "NAXIS")?);
+        let z_naxis = self.get_parsed::<usize>("ZNAXIS")?;
+        for i in 1..=z_naxis {
+            cards.push(z_card(&format!("ZNAXIS{i}"), &format!("NAXIS{i}"))?);
+        }
+        if !is_primary {
+            let integer = |value| Value::Integer {
+                value,
+                comment: None,
+            };
+            cards.push(z_card("ZPCOUNT", "PCOUNT").unwrap_or(Card::Value {
+                name: "PCOUNT".to_owned(),
+                value: integer(0),
+            }));
+            cards.push(z_card("ZGCOUNT", "GCOUNT").unwrap_or(Card::Value {
+                name: "GCOUNT".to_owned(),
+                value: integer(1),
+            }));
+        }
+
+        // Whether the last value card has been removed, its CONTINUE cards are removed as well
+        let mut removed = false;
+        for card in self.cards() {
+            match card {
+                Card::Value { name, value } => {
+                    // The default extension name given by the compression tools is removed too
+                    let default_extname = name == "EXTNAME"
+                        && matches!(value, Value::String { value, .. } if value.trim_end() == "COMPRESSED_IMAGE");
+                    removed = is_compressed_image_keyword(name) || default_extname;
+
+                    let name = match name.as_str() {
+                        "ZEXTEND" => "EXTEND",
+                        "ZBLOCKED" => "BLOCKED",
+                        "ZHECKSUM" => "CHECKSUM",
+                        "ZDATASUM" => "DATASUM",
+                        name => name,
+                    };
+                    if !removed {
+                        cards.push(Card::Value {
+                            name: name.to_owned(),
+                            value: value.clone(),
+                        });
+                    }
+                }
+                Card::Continuation { .. } if removed => (),
+                Card::Xtension { .. } | Card::End => (),
+                card => cards.push(card.clone()),
+            }
+        }
+        cards.push(Card::End);
+
+        Header::parse(cards, &Limits::default())
+    }
+}
+
+/// Whether a keyword only describes the binary table storing a tile compressed image
+/// or its compression. The keywords of the uncompressed image are already added.
+fn is_compressed_image_keyword(name: &str) -> bool {
+    let is_indexed = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+    };
+
+    matches!(
+        name,
+        "ZSIMPLE"
+            | "ZTENSION"
+            | "ZBITPIX"
+            | "ZNAXIS"
+            | "ZPCOUNT"
+            | "ZGCOUNT"
+            | "BITPIX"
+            | "NAXIS"
+            | "PCOUNT"
+            | "GCOUNT"
+            | "TFIELDS"
+            | "THEAP"
+            | "CHECKSUM"
+            | "DATASUM"
+            | "ZIMAGE"
+            | "ZCMPTYPE"
+            | "ZMASKCMP"
+            | "ZQUANTIZ"
+            | "ZDITHER0"
+            | "ZSCALE"
+            | "ZZERO"
+            | "ZBLANK"
+    ) || [
+        "ZNAXIS", "NAXIS", "ZTILE", "ZNAME", "ZVAL", "TTYPE", "TFORM", "TDIM", "TUNIT", "TNULL",
+        "TSCAL", "TZERO", "TDISP",
+    ]
+    .iter()
+    .any(|prefix| is_indexed(prefix))
+}
+
 #[async_trait(?Send)]
 impl Xtension for BinTable {
     /// The table header consists of one or more 2880-byte header
@@ -186,19 +739,21 @@ impl Xtension for BinTable {
         self.naxis1 * self.naxis2 + self.pcount
     }
 
-    fn parse(values: &ValueMap) -> Result<Self, Error> {
+    fn parse(values: &ValueMap, diagnostics: &mut Vec<Diagnostic>) -> Result<Self, Error> {
         // BITPIX
         let bitpix = values.check_for_bitpix()?;
         if bitpix != Bitpix::U8 {
-            return Err(Error::StaticError(
-                "Binary Table HDU must have a BITPIX = 8",
-            ));
+            return Err(
+                Error::StaticError("Binary Table HDU must have a BITPIX = 8")
+                    .at(Location::keyword("BITPIX")),
+            );
         }
 
         // NAXIS
         let naxis = values.check_for_naxis()?;
         if naxis != 2 {
-            return Err(Error::StaticError("Binary Table HDU must have NAXIS = 2"));
+            return Err(Error::StaticError("Binary Table HDU must have NAXIS = 2")
+                .at(Location::keyword("NAXIS")));
         }
 
         // NAXIS1
@@ -212,7 +767,8 @@ impl Xtension for BinTable {
         // GCOUNT
         let gcount = values.check_for_gcount()?;
         if gcount != 1 {
-            return Err(Error::StaticError("Ascii Table HDU must have GCOUNT = 1"));
+            return Err(Error::StaticError("Ascii Table HDU must have GCOUNT = 1")
+                .at(Location::keyword("GCOUNT")));
         }
 
         // FIELDS
@@ -224,47 +780,40 @@ impl Xtension for BinTable {
             ..
         }) = values.get("ZCMPTYPE")
         {
-            match z_cmp_type.trim_ascii_end() {
-                "GZIP_1" => Some(ZCmpType::Gzip1),
-                "GZIP_2" => Some(ZCmpType::Gzip2),
-                "RICE_1" | "RICE_ONE" => {
-                    // Retrieve the block size
-                    let blocksize = values
-                        .iter()
-                        .find_map(|(zname, val)| {
-                            if let Value::String { value, .. } = val {
-                                if value == "BLOCKSIZE" && zname.starts_with("ZNAME") {
-                                    let zval = zname.replace("NAME", "VAL");
-
-                                    values.get_parsed(&zval).ok()
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            }
-                        })
-                        // Default value: 32
-                        .unwrap_or(32);
-
-                    // FIXME: implement RICE parsing for BYTEPIX != 4
-                    let bytepix = 4;
-
-                    Some(ZCmpType::Rice { blocksize, bytepix })
-                }
-                "PLI0_1" => Some(ZCmpType::PLI0_1),
-                "HCOMPRESS_1" => Some(ZCmpType::Hcompress1),
-                _ => {
-                    warn!("ZCMPTYPE is not valid. The tile compressed image column will be discarded if any");
-                    None
-                }
+            let z_cmp_type = parse_z_cmp_type(values, z_cmp_type);
+            if z_cmp_type.is_none() {
+                diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some("ZCMPTYPE"), "ZCMPTYPE is not valid. The tile compressed image column will be discarded if any".to_owned());
             }
+
+            z_cmp_type
         } else {
             None
         };
 
+        // ZMASKCMP (optional keyword) The algorithm used to compress the null pixel mask
+        let z_mask_cmp = if let Some(Value::String {
+            value: ref z_mask_cmp,
+            ..
+        }) = values.get("ZMASKCMP")
+        {
+            parse_z_cmp_type(values, z_mask_cmp).or_else(|| {
+                diagnostic::report(
+                    diagnostics,
+                    DiagnosticKind::InvalidCompression,
+                    None,
+                    Some("ZMASKCMP"),
+                    "ZMASKCMP is not valid. The null pixel mask will be discarded if any"
+                        .to_owned(),
+                );
+                None
+            })
+        } else {
+            // Default value: RICE_1
+            parse_z_cmp_type(values, "RICE_1")
+        };
+
         let z_bitpix = values.get_parsed("ZBITPIX").unwrap_or_else(|err| {
-            warn!("ZBITPIX is not valid. The tile compressed image column will be discarded if any: {err}");
+            diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some("ZBITPIX"), format!("ZBITPIX is not valid. The tile compressed image column will be discarded if any: {err}"));
             None
         });
 
@@ -295,7 +844,13 @@ impl Xtension for BinTable {
                 let znaxisn = if let Ok(value) = values.get_parsed(&format!("ZNAXIS{i}")) {
                     value
                 } else {
-                    warn!("ZNAXISN is mandatory. Tile compressed image discarded");
+                    diagnostic::report(
+                        diagnostics,
+                        DiagnosticKind::InvalidCompression,
+                        None,
+                        Some("ZNAXIS"),
+                        "ZNAXISN is mandatory. Tile compressed image discarded".to_owned(),
+                    );
                     break;
                 };
 
@@ -332,7 +887,13 @@ impl Xtension for BinTable {
         // used to quantize floating-point image pixels into integer values which are then passed to
         // the compression algorithm, as discussed further in section 4 of this document.
         let z_quantiz = values.get_parsed("ZQUANTIZ").unwrap_or_else(|err| {
-            warn!("ZQUANTIZ value not recognized: {err}");
+            diagnostic::report(
+                diagnostics,
+                DiagnosticKind::InvalidCompression,
+                None,
+                Some("ZQUANTIZ"),
+                format!("ZQUANTIZ value not recognized: {err}"),
+            );
             None
         });
 
@@ -342,15 +903,21 @@ impl Xtension for BinTable {
         // further discussion of this keyword.
         let z_dither_0 = values.get_parsed("ZDITHER0").ok();
 
-        // TFORMS & TTYPES
-        let (tforms, ttypes): (Vec<_>, Vec<_>) = (1..=tfields)
+        // TFORMS, TTYPES & TDIMS
+        let (tforms, (ttypes, tdims)): (Vec<_>, (Vec<_>, Vec<_>)) = (1..=tfields)
             .filter_map(|idx_field| {
                 // discard the tform if it was not found and raise a warning
                 let tform_kw = format!("TFORM{idx_field}");
                 let tform = if let Ok(value) = values.get_parsed::<String>(&tform_kw) {
                     Some(value)
                 } else {
-                    warn!("{tform_kw} has not been found. It will be discarded");
+                    diagnostic::report(
+                        diagnostics,
+                        DiagnosticKind::DiscardedField,
+                        None,
+                        Some(&tform_kw),
+                        format!("{tform_kw} has not been found. It will be discarded"),
+                    );
                     None
                 }?;
 
@@ -358,107 +925,52 @@ impl Xtension for BinTable {
                 let ttype = if let Ok(value) = values.get_parsed(&format!("TTYPE{idx_field}")) {
                     Some(value)
                 } else {
-                    warn!("Field {tform_kw:?} does not have a TTYPE name.");
+                    diagnostic::report(
+                        diagnostics,
+                        DiagnosticKind::MissingFieldName,
+                        None,
+                        Some(&tform_kw),
+                        format!("Field {tform_kw:?} does not have a TTYPE name."),
+                    );
                     None
                 };
 
-                let count = tform
-                    .chars()
-                    .take_while(|c| c.is_ascii_digit())
-                    .collect::<String>();
-
-                let num_count_digits = count.len();
-                let repeat_count = count.parse::<usize>().unwrap_or(1);
-                // If the field type is not found, discard it as well
-                let Some(field_ty) = tform.chars().nth(num_count_digits) else {
-                    warn!("Cannot extract the field type of {tform_kw}");
-                    return None;
-                };
-
-                let compute_ty_array_desc = || {
-                    // Get the type element of the stored array
-                    let Some(elem_ty) = tform.chars().nth(num_count_digits + 1) else {
-                        warn!("Could not extract the type from the array descriptor field. Discard {tform_kw}");
-                        return None;
-                    };
-
-                    let (t_byte_size, ty) = match elem_ty {
-                        'L' => (L::BYTES_SIZE, VariableArrayTy::L),
-                        'X' => (X::BYTES_SIZE, VariableArrayTy::X),
-                        'B' => (B::BYTES_SIZE, VariableArrayTy::B),
-                        'I' => (I::BYTES_SIZE, VariableArrayTy::I),
-                        'J' => (J::BYTES_SIZE, VariableArrayTy::J),
-                        'K' => (K::BYTES_SIZE, VariableArrayTy::K),
-                        'A' => (A::BYTES_SIZE, VariableArrayTy::A),
-                        'E' => (E::BYTES_SIZE, VariableArrayTy::E),
-                        'D' => (D::BYTES_SIZE, VariableArrayTy::D),
-                        'C' => (C::BYTES_SIZE, VariableArrayTy::C),
-                        'M' => (M::BYTES_SIZE, VariableArrayTy::M),
-                        _ => {
-                            warn!("Type not recognized. Discard {tform_kw}");
-                            return None;
-                        },
-                    };
-
-                    Some((t_byte_size, ty))
-                };
-
-                let tformty = match field_ty {
-                    // Logical
-                    'L' => TFormType::L { repeat_count },
-                    // Bit
-                    'X' => TFormType::X { repeat_count },
-                    // Unsigned Byte
-                    'B' => TFormType::B { repeat_count },
-                    // 16-bit integer
-                    'I' => TFormType::I { repeat_count },
-                    // 32-bit integer
-                    'J' => TFormType::J { repeat_count },
-                    // 64-bit integer
-                    'K' => TFormType::K { repeat_count },
-                    // Character
-                    'A' => TFormType::A { repeat_count },
-                    // Single-precision floating point
-                    'E' => TFormType::E { repeat_count },
-                    // Double-precision floating point
-                    'D' => TFormType::D { repeat_count },
-                    // Single-precision complex
-                    'C' => TFormType::C { repeat_count },
-                    // Double-precision complex
-                    'M' => TFormType::M { repeat_count },
-                    // Array Descriptor 32-bit
-                    'P' => {
-                        let (t_byte_size, ty) = compute_ty_array_desc()?;
-
-                        TFormType::P {
-                            t_byte_size: t_byte_size as u64,
-                            e_max: 999,
-                            ty,
+                // try to find a tdim (optional keyword)
+                let tdim = values
+                    .get_parsed::<String>(&format!("TDIM{idx_field}"))
+                    .ok()
+                    .and_then(|tdim| {
+                        let dims = parse_tdim(&tdim);
+                        if dims.is_none() {
+                            diagnostic::report(
+                                diagnostics,
+                                DiagnosticKind::InvalidDimensions,
+                                None,
+                                Some(&format!("TDIM{idx_field}")),
+                                format!(
+                                    "TDIM{idx_field} = {tdim:?} is not valid. It will be discarded"
+                                ),
+                            );
                         }
-                    },
-                    // Array Descriptor 64-bit
-                    'Q' => {
-                        let (t_byte_size, ty) = compute_ty_array_desc()?;
-
-                        TFormType::Q {
-                            t_byte_size: t_byte_size as u64,
-                            e_max: 999,
-                            ty,
-                        }
-                    },
-                    _ => {
-                        warn!("Field type not recognized. Discard {tform_kw}");
-                        return None;
-                    }
-                };
+                        dims
+                    });
 
-                Some((tformty, ttype))
+                let tformty = parse_tform(&tform, &tform_kw, diagnostics)?;
+
+                Some((tformty, (ttype, tdim)))
             })
             .unzip();
 
-        let data_compressed_idx = find_field_by_ttype(&ttypes, "COMPRESSED_DATA")
-            // Find for a GZIP_DATA_COMPRESSED named field
-            .or(find_field_by_ttype(&ttypes, "GZIP_COMPRESSED_DATA"));
+        // Each tile is stored in one of these fields, the two last ones being fallbacks for
+        // the tiles that could not be compressed with ZCMPTYPE
+        let data_compressed_idx = find_field_by_ttype(&ttypes, "COMPRESSED_DATA");
+        let gzip_compressed_data_idx = find_field_by_ttype(&ttypes, "GZIP_COMPRESSED_DATA");
+        let uncompressed_data_idx = find_field_by_ttype(&ttypes, "UNCOMPRESSED_DATA");
+        let has_data_field = data_compressed_idx.is_some()
+            || gzip_compressed_data_idx.is_some()
+            || uncompressed_data_idx.is_some();
+        // The null pixel mask can only be read if its compression algorithm is known
+        let null_pixel_mask = find_field_by_ttype(&ttypes, "NULL_PIXEL_MASK").zip(z_mask_cmp);
 
         // Fill the headers with these specific tile compressed image keywords
         let z_image = if let (
@@ -466,10 +978,10 @@ impl Xtension for BinTable {
             Some(z_bitpix),
             Some(z_naxisn),
             Some(z_tilen),
-            Some(data_compressed_idx),
-        ) = (z_cmp_type, z_bitpix, z_naxisn, z_tilen, data_compressed_idx)
+            true,
+        ) = (z_cmp_type, z_bitpix, z_naxisn, z_tilen, has_data_field)
         {
-            // FIXME here we only support GZIP1/GZIP2 and RICE compression
+            // FIXME here we only support GZIP1/GZIP2, RICE, HCOMPRESS and PLIO compression
             // If other compression are found, I disable the zimage
             // so that the binary table is considered as normal i.e. it does not follow
             // the tile compressed convention
@@ -481,19 +993,46 @@ impl Xtension for BinTable {
                 z_quantiz,
                 z_dither_0,
                 data_compressed_idx,
+                gzip_compressed_data_idx,
+                uncompressed_data_idx,
+                null_pixel_mask,
             };
 
+            let lossless = tile_compressed.z_quantiz == Some(ZQuantiz::Lossless);
+            // Quantized floating point values are restored with ZSCALE and ZZERO, given either
+            // for each tile in a column or for the whole image as keywords
+            if matches!(z_bitpix, Bitpix::F32 | Bitpix::F64) && !lossless {
+                for kw in ["ZSCALE", "ZZERO"] {
+                    if find_field_by_ttype(&ttypes, kw).is_none() {
+                        match values.get(kw) {
+                            None => return Err(Error::FailFindingKeyword(kw.to_owned())),
+                            // A keyword of the wrong type is not reported as missing
+                            Some(_) => {
+                                values.get_parsed::<f64>(kw)?;
+                            }
+                        }
+                    }
+                }
+            }
+
             match (z_cmp_type, z_bitpix) {
-                (ZCmpType::Hcompress1, _) => {
-                    warn!("Hcompress compression not supported");
+                (ZCmpType::Plio1, Bitpix::F32 | Bitpix::F64) => {
+                    diagnostic::report(
+                        diagnostics,
+                        DiagnosticKind::InvalidCompression,
+                        None,
+                        Some("ZCMPTYPE"),
+                        "PLIO_1 compression is only supported on integer images".to_owned(),
+                    );
                     None
                 }
-                (ZCmpType::PLI0_1, _) => {
-                    warn!("PLI0_1 compression not supported");
+                (ZCmpType::Gzip1 | ZCmpType::Gzip2, _) => Some(tile_compressed),
+                (_, Bitpix::I64) => {
+                    diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some("ZCMPTYPE"), "Only GZIP_1 and GZIP_2 compressions are supported on 64-bit integer images".to_owned());
                     None
                 }
-                (_, Bitpix::I64) => {
-                    warn!("Only bitpix u8, i16, i32, f32, f64 are supported");
+                (_, Bitpix::F32 | Bitpix::F64) if lossless => {
+                    diagnostic::report(diagnostics, DiagnosticKind::InvalidCompression, None, Some("ZCMPTYPE"), "Only GZIP_1 and GZIP_2 compressions are supported on losslessly compressed floating point images".to_owned());
                     None
                 }
                 _ => Some(tile_compressed),
@@ -502,6 +1041,13 @@ impl Xtension for BinTable {
             None
         };
 
+        // Tile compressed table parameters
+        let z_table = if values.get_parsed::<bool>("ZTABLE").unwrap_or(false) {
+            parse_tile_compressed_table(values, &tforms, diagnostics).map(Box::new)
+        } else {
+            None
+        };
+
         // update the value of theap if found
         let theap = if let Ok(value) = values.get_parsed::<usize>("THEAP") {
             value
@@ -509,14 +1055,14 @@ impl Xtension for BinTable {
             (naxis1 as usize) * (naxis2 as usize)
         };
 
-        let num_bits_per_row = tforms
+        // Bit fields are padded to a whole number of bytes
+        let num_bytes_per_row = tforms
             .iter()
-            .map(|tform| tform.num_bits_field() as u64)
+            .map(|tform| tform.num_bytes_field() as u64)
             .sum::<u64>();
 
-        let num_bytes_per_row = num_bits_per_row >> 3;
         if num_bytes_per_row != naxis1 {
-            return Err(Error::StaticError("BinTable NAXIS1 and TFORMS does not give the same amount of bytes the table should have per row."));
+            return Err(Error::StaticError("BinTable NAXIS1 and TFORMS does not give the same amount of bytes the table should have per row.").at(Location::keyword("NAXIS1")));
         }
 
         Ok(BinTable {
@@ -526,12 +1072,21 @@ impl Xtension for BinTable {
             tfields,
             tforms,
             ttypes,
+            tdims,
             pcount,
             gcount,
             theap,
             z_image,
+            z_table,
+            max_vla_len: u64::MAX,
+            max_tile_byte_size: u64::MAX,
         })
     }
+
+    fn set_limits(&mut self, limits: &Limits) {
+        self.max_vla_len = limits.max_vla_len;
+        self.max_tile_byte_size = limits.max_tile_byte_size;
+    }
 }
 
 // More Xtension are defined in the original paper https://fits.gsfc.nasa.gov/standard40/fits_standard40aa-le.pdf
@@ -853,13 +1408,113 @@ mod tests {
                     Some("QUALITY".to_owned()),
                     Some("FLUX".to_owned()),
                 ],
+                tdims: vec![None; 9],
                 theap: 11535,
                 // Should be 0
                 pcount: 0,
                 // Should be 1
                 gcount: 1,
                 z_image: None,
+                z_table: None,
+                max_vla_len: u64::MAX,
+                max_tile_byte_size: u64::MAX,
             },
         );
     }
+
+    #[test]
+    fn test_bit_field_row_size() {
+        use crate::card::{Card, CardBuf};
+        use crate::hdu::header::Header;
+        use std::convert::TryFrom;
+
+        let parse = |naxis1: &str| {
+            let cards = [
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                naxis1,
+                "NAXIS2  =                    1",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    2",
+                "TFORM1  = '1J      '",
+                "TFORM2  = '3X      '",
+                "END",
+            ]
+            .iter()
+            .map(|card| {
+                let mut buf: CardBuf = [b' '; 80];
+                buf[..card.len()].copy_from_slice(card.as_bytes());
+                Card::try_from(&buf).unwrap()
+            })
+            .collect();
+
+            Header::<BinTable>::parse(cards, &crate::Limits::default())
+        };
+
+        // The 3 bits take a whole byte
+        assert!(parse("NAXIS1  =                    5").is_ok());
+        assert!(parse("NAXIS1  =                    4").is_err());
+    }
+
+    #[test]
+    fn test_image_header() {
+        use crate::card::{Card, CardBuf};
+        use crate::hdu::header::Header;
+        use crate::hdu::Value;
+        use std::convert::TryFrom;
+
+        let cards = [
+            "XTENSION= 'BINTABLE'",
+            "BITPIX  =                    8",
+            "NAXIS   =                    2",
+            "NAXIS1  =                    8",
+            "NAXIS2  =                  100",
+            "PCOUNT  =                 4000",
+            "GCOUNT  =                    1",
+            "TFIELDS =                    1",
+            "TTYPE1  = 'COMPRESSED_DATA'",
+            "TFORM1  = '1PB(40) '",
+            "ZIMAGE  =                    T",
+            "ZSIMPLE =                    T",
+            "ZBITPIX =                  -32",
+            "ZNAXIS  =                    2",
+            "ZNAXIS1 =                  100",
+            "ZNAXIS2 =                  100",
+            "ZTILE1  =                  100",
+            "ZTILE2  =                    1",
+            "ZCMPTYPE= 'GZIP_1  '",
+            "ZQUANTIZ= 'NONE    '",
+            "ZEXTEND =                    T",
+            "EXTNAME = 'COMPRESSED_IMAGE'",
+            "CHECKSUM= 'TABLE CHECKSUM  '",
+            "ZHECKSUM= 'IMAGE CHECKSUM  '",
+            "CTYPE1  = 'RA---TAN'",
+            "END",
+        ]
+        .iter()
+        .map(|card| {
+            let mut buf: CardBuf = [b' '; 80];
+            buf[..card.len()].copy_from_slice(card.as_bytes());
+            Card::try_from(&buf).unwrap()
+        })
+        .collect();
+
+        let header = Header::<BinTable>::parse(cards, &crate::Limits::default()).unwrap();
+        let image_header = header.image_header().unwrap();
+        let image = image_header.get_xtension();
+        assert_eq!(image.get_bitpix(), Bitpix::F32);
+        assert_eq!(image.get_naxis(), [100, 100]);
+
+        let keywords = image_header.keywords().collect::<Vec<_>>();
+        assert_eq!(
+            keywords,
+            ["SIMPLE", "BITPIX", "NAXIS", "NAXIS1", "NAXIS2", "EXTEND", "CHECKSUM", "CTYPE1"]
+        );
+        assert!(matches!(
+            image_header.get("CHECKSUM"),
+            Some(Value::String { value, .. }) if value == "IMAGE CHECKSUM"
+        ));
+    }
 }
@@ -3,6 +3,7 @@ use serde::Serialize;
 
 use crate::error::Error;
 use crate::hdu::header::Bitpix;
+use crate::hdu::header::Diagnostic;
 
 use crate::hdu::header::ValueMap;
 use crate::hdu::header::Xtension;
@@ -41,7 +42,7 @@ impl Xtension for Image {
         self.bitpix.byte_size() as u64 * self.get_num_pixels()
     }
 
-    fn parse(values: &ValueMap) -> Result<Self, Error> {
+    fn parse(values: &ValueMap, _diagnostics: &mut Vec<Diagnostic>) -> Result<Self, Error> {
         // BITPIX
         let bitpix = values.check_for_bitpix()?;
         // NAXIS
@@ -7,8 +7,9 @@ use std::str::FromStr;
 use async_trait::async_trait;
 use serde::Serialize;
 
-use super::ValueMap;
+use super::{Diagnostic, ValueMap};
 use crate::error::Error;
+use crate::fits::Limits;
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize)]
 pub enum XtensionType {
@@ -52,8 +53,13 @@ pub trait Xtension {
     fn get_num_bytes_data_block(&self) -> u64;
 
     // Parse the Xtension keywords
-    // During the parsing, some checks will be made
-    fn parse(values: &ValueMap) -> Result<Self, Error>
+    // During the parsing, some checks will be made. The problems that do not prevent
+    // the parsing are added to `diagnostics`
+    fn parse(values: &ValueMap, diagnostics: &mut Vec<Diagnostic>) -> Result<Self, Error>
     where
         Self: Sized;
+
+    /// Restrict the sizes read from the data unit, e.g. the length of
+    /// the variable length arrays of a binary table
+    fn set_limits(&mut self, _limits: &Limits) {}
 }
@@ -4,12 +4,13 @@
 //! 80 ASCII characters.
 use futures::{AsyncRead, AsyncReadExt};
 use indexmap::map::{IndexMap, Keys};
-use log::warn;
 use serde::de::{value::MapDeserializer, IntoDeserializer};
 use serde::{Deserialize, Serialize};
 
+pub mod diagnostic;
 pub mod extension;
 
+pub use diagnostic::{Diagnostic, DiagnosticKind};
 pub use extension::Xtension;
 
 use std::convert::TryFrom;
@@ -18,7 +19,8 @@ use std::ops::Deref;
 
 use crate::{
     card::{self, *},
-    error::Error,
+    error::{Error, Location},
+    fits::Limits,
 };
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
@@ -104,6 +106,7 @@ impl ValueMap {
     {
         // We use `Value::Undefined` fallback to handle `T` being an `Option<_>`.
         T::deserialize(self.get(key).unwrap_or(&Value::Undefined))
+            .map_err(|e| e.at(Location::keyword(key)))
     }
 
     /// Return an iterator over all key-[value](Card::Value) pairs in the FITS
@@ -197,6 +200,9 @@ pub struct Header<X> {
     values: ValueMap,
     /// Mandatory keywords for fits ext parsing.
     xtension: X,
+    /// Problems found while parsing the header
+    #[serde(skip)]
+    diagnostics: Vec<Diagnostic>,
 }
 
 impl<X> Deref for Header<X> {
@@ -211,15 +217,61 @@ impl<X> Header<X>
 where
     X: Xtension + std::fmt::Debug,
 {
-    pub(crate) fn parse(cards: Vec<Card>) -> Result<Self, Error> {
-        let values = process_cards(&cards)?;
-
-        let xtension: X = Xtension::parse(&values)?;
+    pub(crate) fn parse(cards: Vec<Card>, limits: &Limits) -> Result<Self, Error> {
+        let mut diagnostics = vec![];
+        let result = process_cards(&cards, &mut diagnostics).and_then(|values| {
+            check_limits(&values, limits)?;
+            let xtension: X = Xtension::parse(&values, &mut diagnostics)?;
+
+            Ok((values, xtension))
+        });
+
+        let (values, mut xtension) = result.map_err(|e: Error| {
+            // Locate the card of the keyword the error is about
+            let card_idx = e
+                .location()
+                .and_then(|location| location.keyword.as_deref())
+                .and_then(|keyword| find_card(&cards, keyword));
+
+            match card_idx {
+                Some(card_idx) => e.at(Location {
+                    card_idx: Some(card_idx),
+                    ..Default::default()
+                }),
+                None => e,
+            }
+        })?;
+        xtension.set_limits(limits);
+
+        // Locate the diagnostics reported on a keyword
+        for diagnostic in &mut diagnostics {
+            if diagnostic.card_idx.is_none() {
+                diagnostic.card_idx = diagnostic
+                    .keyword
+                    .as_deref()
+                    .and_then(|keyword| find_card(&cards, keyword));
+            }
+        }
+        let undefined_cards = cards.iter().enumerate().filter_map(|(card_idx, card)| {
+            if let Card::Undefined(card) = card {
+                Some(Diagnostic {
+                    kind: DiagnosticKind::UndefinedCard,
+                    card_idx: Some(card_idx),
+                    keyword: None,
+                    message: format!("Card {card:?} could not be parsed"),
+                })
+            } else {
+                None
+            }
+        });
+        diagnostics.extend(undefined_cards);
+        diagnostics.sort_by_key(|diagnostic| diagnostic.card_idx.unwrap_or(usize::MAX));
 
         Ok(Self {
             cards,
             values,
             xtension,
+            diagnostics,
         })
     }
 
@@ -233,6 +285,14 @@ where
         self.cards.iter()
     }
 
+    /// Return the problems found while parsing the header, ordered by card
+    ///
+    /// They did not prevent the parsing but may have altered its result, e.g.
+    /// a discarded table field or an unparsable card
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
     /// Return an iterator over the processing history of the header, i.e. all
     /// [cards](Card) with the `HISTORY` keyword.
     ///
@@ -247,7 +307,130 @@ where
     }
 }
 
-fn process_cards(cards: &[Card]) -> Result<ValueMap, Error> {
+/// Find the index of the card holding the value of a keyword
+fn find_card(cards: &[Card], keyword: &str) -> Option<usize> {
+    cards.iter().position(|card| match card {
+        Card::Value { name, .. } | Card::Hierarch { name, .. } => name == keyword,
+        Card::Xtension { .. } => keyword == "XTENSION",
+        _ => false,
+    })
+}
+
+/// Check the sizes given by a header against the limits, before its parsing
+/// allocates anything from them
+fn check_limits(values: &ValueMap, limits: &Limits) -> Result<(), Error> {
+    let check = |quantity: &'static str, keyword: &str, value: u64, limit: u64| {
+        if value > limit {
+            Err(Error::LimitExceeded(quantity, value, limit).at(Location::keyword(keyword)))
+        } else {
+            Ok(())
+        }
+    };
+    let get = |keyword: &str| values.get_parsed::<u64>(keyword).ok();
+    let num_bytes_per_value = |keyword: &str| {
+        values
+            .get_parsed::<i64>(keyword)
+            .map_or(0, |bitpix| bitpix.unsigned_abs() / 8)
+    };
+    // The number of axes is at most 999
+    let axes = |prefix: &str| -> Result<Vec<u64>, Error> {
+        let naxis = get(prefix).unwrap_or(0);
+        check("Number of axes", prefix, naxis, 999)?;
+
+        Ok((1..=naxis)
+            .map(|i| get(&format!("{prefix}{i}")).unwrap_or(0))
+            .collect::<Vec<_>>())
+    };
+    // The tiles are computed by dividing ZNAXISn by ZTILEn, they must be positive if given
+    let check_positive = |keyword: &str| match values.get_parsed::<i64>(keyword) {
+        Ok(value) if value <= 0 => Err(Error::DynamicError(format!(
+            "{keyword} = {value} must be a positive integer"
+        ))
+        .at(Location::keyword(keyword))),
+        _ => Ok(()),
+    };
+    // The sizes are saturated on overflow
+    let product = |init: u64, axes: &[u64]| axes.iter().fold(init, |acc, &n| acc.saturating_mul(n));
+
+    if let Some(tfields) = get("TFIELDS") {
+        check(
+            "Number of table fields",
+            "TFIELDS",
+            tfields,
+            limits.max_tfields as u64,
+        )?;
+    }
+
+    // ZNAXIS is read even if the table is not a compressed image
+    check("Number of axes", "ZNAXIS", get("ZNAXIS").unwrap_or(0), 999)?;
+
+    let mut naxisn = axes("NAXIS")?;
+    // Random groups have NAXIS1 = 0, the other axes give the size of a group
+    if values.get_parsed::<bool>("GROUPS").unwrap_or(false) && naxisn.first() == Some(&0) {
+        naxisn.remove(0);
+    }
+    let num_values = if naxisn.is_empty() {
+        0
+    } else {
+        product(1, &naxisn)
+    };
+    let num_bytes = get("PCOUNT")
+        .unwrap_or(0)
+        .saturating_add(num_values)
+        .saturating_mul(get("GCOUNT").unwrap_or(1))
+        .saturating_mul(num_bytes_per_value("BITPIX"));
+    check(
+        "Data unit size in bytes",
+        "NAXIS",
+        num_bytes,
+        limits.max_data_unit_byte_size,
+    )?;
+
+    // The tile compressed image is read from ZNAXIS even when ZIMAGE is missing
+    if values.get_parsed::<bool>("ZIMAGE").unwrap_or(false) || values.get("ZNAXIS").is_some() {
+        let z_naxisn = axes("ZNAXIS")?;
+        for i in 1..=z_naxisn.len() {
+            check_positive(&format!("ZNAXIS{i}"))?;
+            check_positive(&format!("ZTILE{i}"))?;
+        }
+        let num_bytes_per_pixel = num_bytes_per_value("ZBITPIX");
+        check(
+            "Uncompressed image size in bytes",
+            "ZNAXIS",
+            product(num_bytes_per_pixel, &z_naxisn),
+            limits.max_data_unit_byte_size,
+        )?;
+
+        // By default, the image is compressed row by row
+        let z_tilen = z_naxisn
+            .iter()
+            .enumerate()
+            .map(|(i, &z_naxis)| {
+                get(&format!("ZTILE{}", i + 1)).unwrap_or(if i == 0 { z_naxis } else { 1 })
+            })
+            .collect::<Vec<_>>();
+        check(
+            "Tile size in bytes",
+            "ZTILE1",
+            product(num_bytes_per_pixel, &z_tilen),
+            limits.max_tile_byte_size,
+        )?;
+    }
+
+    if values.get_parsed::<bool>("ZTABLE").unwrap_or(false) {
+        let z_naxisn = [get("ZNAXIS1").unwrap_or(0), get("ZNAXIS2").unwrap_or(0)];
+        check(
+            "Uncompressed table size in bytes",
+            "ZNAXIS1",
+            product(1, &z_naxisn),
+            limits.max_data_unit_byte_size,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn process_cards(cards: &[Card], diagnostics: &mut Vec<Diagnostic>) -> Result<ValueMap, Error> {
     let mut values = IndexMap::new();
     let mut kw: Option<String> = None;
 
@@ -279,9 +462,14 @@ fn process_cards(cards: &[Card]) -> Result<ValueMap, Error> {
                     // should be interpreted as containing commentary text (similar to COMMENT keyword)
                     // We then change the card to a comment one, emitting a warning for that
                     // as it may introduce an error with repect to what the author of the file meant
-                    warn!("Orphaned CONTINUE found (i.e. which is not preceded by a '&' character).
-                          The parser will consider it as a COMMENT record as stated by the FITS document 4.2.1.2.
-                          Card is found at index {i}"
+                    diagnostic::report(
+                        diagnostics,
+                        DiagnosticKind::OrphanedContinue,
+                        Some(i),
+                        Some("CONTINUE"),
+                        "Orphaned CONTINUE found (i.e. which is not preceded by a '&' character). \
+                        The parser will consider it as a COMMENT record as stated by the FITS document 4.2.1.2."
+                            .to_owned(),
                     );
                 }
             }
@@ -334,6 +522,7 @@ mod tests {
     use super::CardBuf;
     use std::iter::Iterator;
 
+    use super::DiagnosticKind;
     use super::Value;
 
     #[test]
@@ -358,7 +547,7 @@ mod tests {
         let reader = Cursor::new(data);
         let mut fits = Fits::from_reader(reader);
         let hdu = fits.next().expect("Should contain a primary HDU");
-        if let Err(Error::DynamicError(e)) = hdu {
+        if let Some(Error::DynamicError(e)) = hdu.as_ref().err().map(Error::inner) {
             assert_eq!(
                 e,
                 "Invalid FITS file: expected `SIMPLE` keyword in first card, found `WRONGKW`"
@@ -474,7 +663,12 @@ mod tests {
         let mut fits = Fits::from_reader(reader);
         let hdu = fits.next().expect("Should contain a primary HDU");
 
-        assert_eq!(Err(Error::Io(std::io::ErrorKind::UnexpectedEof)), hdu);
+        let err = hdu.expect_err("The END card is missing");
+        assert_eq!(
+            err.inner(),
+            &Error::Io(std::io::ErrorKind::UnexpectedEof.into())
+        );
+        assert_eq!(err.location().and_then(|l| l.hdu_idx), Some(0));
         // As the primary hdu parsing failed (EOF reached), next call to fits should result in None
         assert_eq!(fits.next(), None);
     }
@@ -656,6 +850,64 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn diagnostics() {
+        let mut data = mock_fits_data([
+            b"SIMPLE  =                    T                                                  ",
+            b"BITPIX  =                    8                                                  ",
+            b"NAXIS   =                    0                                                  ",
+            b"CONTINUE  'orphaned'                                                            ",
+            b"NOVALUE  2                                                                      ",
+            b"END                                                                             ",
+        ])
+        .to_vec();
+        data.extend(mock_fits_data([
+            b"XTENSION= 'BINTABLE'                                                            ",
+            b"BITPIX  =                    8                                                  ",
+            b"NAXIS   =                    2                                                  ",
+            b"NAXIS1  =                    4                                                  ",
+            b"NAXIS2  =                    0                                                  ",
+            b"PCOUNT  =                    0                                                  ",
+            b"GCOUNT  =                    1                                                  ",
+            b"TFIELDS =                    2                                                  ",
+            b"TFORM1  = '1J      '                                                            ",
+            b"TTYPE2  = 'BAD     '                                                            ",
+            b"TFORM2  = '1Z      '                                                            ",
+            b"END                                                                             ",
+        ]));
+
+        let mut fits = Fits::from_reader(Cursor::new(data));
+        let Some(Ok(HDU::Primary(hdu))) = fits.next() else {
+            panic!("Should contain a primary HDU");
+        };
+        let diagnostics = hdu.get_header().diagnostics();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.kind, d.card_idx))
+                .collect::<Vec<_>>(),
+            [
+                (DiagnosticKind::OrphanedContinue, Some(3)),
+                (DiagnosticKind::UndefinedCard, Some(4))
+            ]
+        );
+
+        let Some(Ok(HDU::XBinaryTable(hdu))) = fits.next() else {
+            panic!("Should contain a binary table extension");
+        };
+        let diagnostics = hdu.get_header().diagnostics();
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|d| (d.kind, d.card_idx, d.keyword.as_deref()))
+                .collect::<Vec<_>>(),
+            [
+                (DiagnosticKind::MissingFieldName, Some(8), Some("TFORM1")),
+                (DiagnosticKind::DiscardedField, Some(10), Some("TFORM2"))
+            ]
+        );
+    }
+
     /// panics if N > 36
     fn mock_fits_data<const N: usize>(cards: [&CardBuf; N]) -> [u8; 2880] {
         let mut data = [b' '; 2880];
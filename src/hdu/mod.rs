@@ -15,7 +15,7 @@ use crate::card::Value;
 use crate::hdu::data::FitsRead;
 
 //use self::data::DataAsyncBufRead;
-use crate::error::Error;
+use crate::error::{Error, Location};
 
 use self::data::AsyncDataBufRead;
 use self::header::consume_next_card_async;
@@ -29,7 +29,9 @@ use crate::hdu::Value::Logical;
 //use super::AsyncHDU;
 use crate::async_fits;
 use crate::fits;
+use crate::fits::{Limits, ParseMode, ParseOptions};
 use crate::hdu::primary::consume_next_card;
+use crate::validate;
 use log::error;
 /// An enumeration of the supported FITS Header Data Unit types.
 #[derive(Debug, PartialEq)]
@@ -45,12 +47,19 @@ pub enum HDU {
 }
 
 use std::io::Read;
-fn consume_cards<R>(reader: &mut R, num_bytes_read: &mut usize) -> Result<Vec<Card>, Error>
+fn consume_cards<R>(
+    reader: &mut R,
+    num_bytes_read: &mut usize,
+    options: &ParseOptions,
+    is_primary: bool,
+) -> Result<Vec<Card>, Error>
 where
     R: Read,
 {
     let mut card_80_bytes_buf: CardBuf = [0; 80];
     let mut cards = Vec::new();
+    // The raw cards are kept in strict mode to check them once the END card is reached
+    let mut card_bufs = Vec::new();
 
     /* Consume cards until `END` is reached */
     loop {
@@ -60,6 +69,20 @@ where
                 error!("Fail reading the header without encountering the END card");
             })?;
 
+        match options.mode {
+            ParseMode::Strict => card_bufs.push(card_80_bytes_buf),
+            ParseMode::Lenient => repair_card(&mut card_80_bytes_buf),
+            ParseMode::Tolerant => (),
+        }
+
+        if cards.len() == options.limits.max_num_cards {
+            return Err(Error::LimitExceeded(
+                "Number of header cards",
+                cards.len() as u64 + 1,
+                options.limits.max_num_cards as u64,
+            ));
+        }
+
         if let Ok(card) = Card::try_from(&card_80_bytes_buf) {
             cards.push(card);
             if Some(&Card::End) == cards.last() {
@@ -73,12 +96,64 @@ where
         }
     }
 
+    if options.mode == ParseMode::Strict {
+        if let Some(finding) = validate::first_header_violation(&card_bufs, is_primary) {
+            let err = Error::DynamicError(format!("Strict parsing: {}", finding.message));
+            return Err(match finding.card_idx {
+                Some(card_idx) => {
+                    let keyword = String::from_utf8_lossy(&card_bufs[card_idx][..8]);
+                    err.at(Location::card(card_idx, keyword.trim_end()))
+                }
+                None => err,
+            });
+        }
+
+        // The padding of the header is consumed here to be checked
+        while !(*num_bytes_read).is_multiple_of(2880) {
+            consume_next_card(reader, &mut card_80_bytes_buf, num_bytes_read)?;
+            if card_80_bytes_buf.iter().any(|&b| b != b' ') {
+                return Err(Error::StaticError(
+                    "Strict parsing: the header padding after the END card must be filled with spaces",
+                )
+                .at(Location {
+                    card_idx: Some(*num_bytes_read / 80 - 1),
+                    ..Default::default()
+                }));
+            }
+        }
+    }
+
     Ok(cards)
 }
 
+/// Repair the common quirks of archive cards before parsing them
+fn repair_card(card: &mut CardBuf) {
+    // Tabs, NUL bytes or non ASCII characters
+    for b in card.iter_mut() {
+        if !(0x20..=0x7e).contains(b) {
+            *b = b' ';
+        }
+    }
+
+    card[..8].make_ascii_uppercase();
+
+    // The text of the commentary cards is kept as is
+    let is_commentary = matches!(
+        &card[..8],
+        b"COMMENT " | b"HISTORY " | b"        " | b"CONTINUE"
+    );
+
+    // `KEYWORD ='value'`, the value is shifted if the card has room for it
+    if !is_commentary && card[8] == b'=' && card[9] != b' ' && card[79] == b' ' {
+        card.copy_within(9..79, 10);
+        card[9] = b' ';
+    }
+}
+
 async fn consume_cards_async<R>(
     reader: &mut R,
     num_bytes_read: &mut usize,
+    limits: &Limits,
 ) -> Result<Vec<Card>, Error>
 where
     R: AsyncRead + std::marker::Unpin,
@@ -94,6 +169,15 @@ where
             .map_err(|_| {
                 Error::StaticError("Fail reading the header without encountering the END card")
             })?;
+
+        if cards.len() == limits.max_num_cards {
+            return Err(Error::LimitExceeded(
+                "Number of header cards",
+                cards.len() as u64 + 1,
+                limits.max_num_cards as u64,
+            ));
+        }
+
         if let Ok(card) = Card::try_from(&card_80_bytes_buf) {
             cards.push(card);
             if Some(&Card::End) == cards.last() {
@@ -114,37 +198,51 @@ impl HDU {
     pub(crate) fn new_xtension<'a, R>(
         reader: &mut R,
         num_bytes_read: &mut usize,
+        options: &ParseOptions,
     ) -> Result<Self, Error>
     where
         R: FitsRead<'a, Image> + FitsRead<'a, BinTable> + FitsRead<'a, AsciiTable> + Seek + 'a,
     {
-        let cards = consume_cards(reader, num_bytes_read)?;
-        // Check only the the first card. Even if not FITS valid we could accept
-        // it if its xtension card is down in the header.
+        let mut cards = consume_cards(reader, num_bytes_read, options, false)?;
+        // Check only the the first card, unless in lenient mode where an xtension card
+        // down in the header is moved back to the first place.
+        if options.mode == ParseMode::Lenient {
+            if let Some(idx) = cards
+                .iter()
+                .position(|card| matches!(card, Card::Xtension { .. }))
+            {
+                let card = cards.remove(idx);
+                cards.insert(0, card);
+            }
+        }
+
         match &cards[0] {
             Card::Xtension {
                 x: XtensionType::Image,
                 ..
-            } => Ok(HDU::XImage(fits::HDU::<Image>::new(
+            } => Ok(HDU::XImage(fits::HDU::<Image>::new_with_limits(
                 reader,
                 num_bytes_read,
                 cards,
+                &options.limits,
             )?)),
             Card::Xtension {
                 x: XtensionType::BinTable,
                 ..
-            } => Ok(HDU::XBinaryTable(fits::HDU::<BinTable>::new(
+            } => Ok(HDU::XBinaryTable(fits::HDU::<BinTable>::new_with_limits(
                 reader,
                 num_bytes_read,
                 cards,
+                &options.limits,
             )?)),
             Card::Xtension {
                 x: XtensionType::AsciiTable,
                 ..
-            } => Ok(HDU::XASCIITable(fits::HDU::<AsciiTable>::new(
+            } => Ok(HDU::XASCIITable(fits::HDU::<AsciiTable>::new_with_limits(
                 reader,
                 num_bytes_read,
                 cards,
+                &options.limits,
             )?)),
             _ => Err(Error::StaticError(
                 "XTENSION card has not been found in the header",
@@ -152,13 +250,13 @@ impl HDU {
         }
     }
 
-    pub(crate) fn new_primary<'a, R>(reader: &mut R) -> Result<Self, Error>
+    pub(crate) fn new_primary<'a, R>(reader: &mut R, options: &ParseOptions) -> Result<Self, Error>
     where
         R: FitsRead<'a, Image> + Seek + 'a,
     {
         let mut num_bytes_read = 0;
 
-        let cards = consume_cards(reader, &mut num_bytes_read)?;
+        let cards = consume_cards(reader, &mut num_bytes_read, options, true)?;
 
         // Check for SIMPLE keyword
         if let Card::Value {
@@ -168,10 +266,11 @@ impl HDU {
         } = &cards[0]
         {
             if name == "SIMPLE" {
-                Ok(HDU::Primary(fits::HDU::<Image>::new(
+                Ok(HDU::Primary(fits::HDU::<Image>::new_with_limits(
                     reader,
                     &mut num_bytes_read,
                     cards,
+                    &options.limits,
                 )?))
             } else {
                 // TODO log the card to stderr
@@ -185,6 +284,13 @@ impl HDU {
         }
     }
 
+    pub fn get_header_byte_offset(&self) -> u64 {
+        match self {
+            HDU::Primary(hdu) | HDU::XImage(hdu) => hdu.get_header_byte_offset(),
+            HDU::XBinaryTable(hdu) => hdu.get_header_byte_offset(),
+            HDU::XASCIITable(hdu) => hdu.get_header_byte_offset(),
+        }
+    }
     pub fn get_data_unit_byte_offset(&self) -> u64 {
         match self {
             HDU::Primary(hdu) | HDU::XImage(hdu) => hdu.get_data_unit_byte_offset(),
@@ -210,7 +316,7 @@ pub enum AsyncHDU {
 }
 
 impl AsyncHDU {
-    pub(crate) async fn new_xtension<'a, R>(reader: &mut R) -> Result<Self, Error>
+    pub(crate) async fn new_xtension<'a, R>(reader: &mut R, limits: Limits) -> Result<Self, Error>
     where
         R: AsyncDataBufRead<'a, Image>
             + AsyncDataBufRead<'a, BinTable>
@@ -219,7 +325,7 @@ impl AsyncHDU {
     {
         let mut num_bytes_read = 0;
 
-        let cards = consume_cards_async(reader, &mut num_bytes_read).await?;
+        let cards = consume_cards_async(reader, &mut num_bytes_read, &limits).await?;
         // Check only the if the first card. Even if not FITS valid we could accept
         // it if its xtension card is down in the header.
         let hdu = match &cards[0] {
@@ -227,19 +333,37 @@ impl AsyncHDU {
                 x: XtensionType::Image,
                 ..
             } => AsyncHDU::XImage(
-                async_fits::AsyncHDU::<Image>::new(reader, &mut num_bytes_read, cards).await?,
+                async_fits::AsyncHDU::<Image>::new_with_limits(
+                    reader,
+                    &mut num_bytes_read,
+                    cards,
+                    &limits,
+                )
+                .await?,
             ),
             Card::Xtension {
                 x: XtensionType::BinTable,
                 ..
             } => AsyncHDU::XBinaryTable(
-                async_fits::AsyncHDU::<BinTable>::new(reader, &mut num_bytes_read, cards).await?,
+                async_fits::AsyncHDU::<BinTable>::new_with_limits(
+                    reader,
+                    &mut num_bytes_read,
+                    cards,
+                    &limits,
+                )
+                .await?,
             ),
             Card::Xtension {
                 x: XtensionType::AsciiTable,
                 ..
             } => AsyncHDU::XASCIITable(
-                async_fits::AsyncHDU::<AsciiTable>::new(reader, &mut num_bytes_read, cards).await?,
+                async_fits::AsyncHDU::<AsciiTable>::new_with_limits(
+                    reader,
+                    &mut num_bytes_read,
+                    cards,
+                    &limits,
+                )
+                .await?,
             ),
             _ => {
                 return Err(Error::StaticError(
@@ -251,13 +375,13 @@ impl AsyncHDU {
         Ok(hdu)
     }
 
-    pub(crate) async fn new_primary<'a, R>(reader: &mut R) -> Result<Self, Error>
+    pub(crate) async fn new_primary<'a, R>(reader: &mut R, limits: Limits) -> Result<Self, Error>
     where
         R: AsyncDataBufRead<'a, Image> + 'a,
     {
         let mut num_bytes_read = 0;
 
-        let cards = consume_cards_async(reader, &mut num_bytes_read).await?;
+        let cards = consume_cards_async(reader, &mut num_bytes_read, &limits).await?;
 
         // Check for SIMPLE keyword
         let _name: String = "SIMPLE".to_owned();
@@ -268,7 +392,13 @@ impl AsyncHDU {
         } = &cards[0]
         {
             Ok(AsyncHDU::Primary(
-                async_fits::AsyncHDU::<Image>::new(reader, &mut num_bytes_read, cards).await?,
+                async_fits::AsyncHDU::<Image>::new_with_limits(
+                    reader,
+                    &mut num_bytes_read,
+                    cards,
+                    &limits,
+                )
+                .await?,
             ))
         } else {
             Err(Error::StaticError("not a FITSv4 file"))
@@ -14,11 +14,13 @@ use serde::Deserialize;
 
 use crate::fits::HDU as FitsHDU;
 use crate::hdu::data::bintable::data::BinaryTableData;
-use crate::hdu::data::bintable::tile_compressed::pixels::Pixels as TcPixels;
+use crate::hdu::data::bintable::tile_compressed::pixels::{It as TcIt, Pixels as TcPixels};
+use crate::hdu::data::bintable::tile_compressed::Keywords;
 use crate::hdu::data::image::Pixels;
+use crate::hdu::data::iter::TryNext;
 use crate::hdu::header::extension::bintable::BinTable;
 use crate::hdu::header::extension::image::Image as FitsImage;
-use crate::hdu::header::Bitpix;
+use crate::hdu::header::{Bitpix, Header};
 use crate::{Fits, HDU};
 
 /// Newtype around `GenericReader<'a>` that adds a trivial `Debug` impl,
@@ -76,24 +78,19 @@ pub fn register_fits_decoding_hook() {
     });
 }
 
+// The binary table header is bigger but only one HDU is kept by the decoder
+#[allow(clippy::large_enum_variant)]
 enum HduImageKind {
     Image(FitsHDU<FitsImage>),
-    TileCompressed(FitsHDU<BinTable>),
+    /// A tile compressed image along with its synthesized image header
+    TileCompressed(FitsHDU<BinTable>, Header<FitsImage>),
 }
 
 impl HduImageKind {
-    fn axes_and_bitpix(&self) -> ImageResult<(&[u64], Bitpix)> {
+    fn image_header(&self) -> &Header<FitsImage> {
         match self {
-            Self::Image(hdu) => {
-                let image = hdu.get_header().get_xtension();
-                Ok((image.get_naxis(), image.get_bitpix()))
-            }
-            Self::TileCompressed(hdu) => {
-                let Some(image) = hdu.get_header().get_xtension().get_z_image() else {
-                    return Err(to_image_error("tile-compressed HDU has no z_image"));
-                };
-                Ok((&image.z_naxisn, image.z_bitpix))
-            }
+            Self::Image(hdu) => hdu.get_header(),
+            Self::TileCompressed(_, header) => header,
         }
     }
 }
@@ -121,14 +118,16 @@ impl<'a> FitsDecoder<'a> {
                 Some(HDU::XBinaryTable(hdu))
                     if hdu.get_header().get_xtension().get_z_image().is_some() =>
                 {
-                    break HduImageKind::TileCompressed(hdu);
+                    let header = hdu.get_header().image_header().map_err(to_image_error)?;
+                    break HduImageKind::TileCompressed(hdu, header);
                 }
                 Some(_) => {}
                 None => return Err(to_image_error("no 2D image HDU found in FITS file")),
             }
         };
 
-        let (axes, bitpix) = found.axes_and_bitpix()?;
+        let image = found.image_header().get_xtension();
+        let (axes, bitpix) = (image.get_naxis(), image.get_bitpix());
         let &[width, height, ref extra_axes @ ..] = axes else {
             return Err(to_image_error("image HDU has fewer than 2 axes"));
         };
@@ -178,6 +177,20 @@ impl Scale {
 
 const RGB_CHANNELS: usize = 3;
 
+/// Iterate over the pixels of a tile compressed image in raster order
+fn raster<R, K>(it: TcIt<R, K>) -> ImageResult<impl Iterator<Item = K::T>>
+where
+    K: Keywords,
+    K::T: Copy + Default,
+    TcIt<R, K>: TryNext<Item = K::T>,
+{
+    Ok(it
+        .try_raster()
+        .map_err(to_image_error)?
+        .into_vec()
+        .into_iter())
+}
+
 /// Write `iter` of `P`-byte values into `buf` (`P*3` bytes per pixel), de-planing from
 /// FITS sequential-plane order into interleaved RGB order.
 fn write_rgb<const P: usize>(buf: &mut [u8], mut iter: impl Iterator<Item = [u8; P]>) {
@@ -315,38 +328,33 @@ impl<'a> ImageDecoder for FitsDecoder<'a> {
 
     fn read_image(mut self, buf: &mut [u8]) -> ImageResult<()> {
         let is_rgb = self.is_rgb;
+        let scale = Scale::deserialize(self.hdu.image_header().into_deserializer())
+            .map_err(to_image_error)?;
         match self.hdu {
-            HduImageKind::Image(hdu) => {
-                let scale = Scale::deserialize(hdu.get_header().into_deserializer())
-                    .map_err(to_image_error)?;
-                match self.fits.get_data(&hdu).pixels() {
-                    Pixels::U8(it) => write_u8(buf, it, scale, is_rgb),
-                    Pixels::I16(it) => write_i16(buf, it, scale, is_rgb),
-                    Pixels::I32(it) => write_i32(buf, it, scale, is_rgb),
-                    Pixels::I64(it) => write_i64(buf, it, scale, is_rgb),
-                    Pixels::F32(it) => write_f32(buf, it, is_rgb),
-                    Pixels::F64(it) => write_f64(buf, it, is_rgb),
-                }
-            }
-            HduImageKind::TileCompressed(hdu) => {
-                let scale = Scale::deserialize(hdu.get_header().into_deserializer())
-                    .map_err(to_image_error)?;
-                match self.fits.get_data(&hdu) {
-                    BinaryTableData::TileCompressed(tc) => match tc {
-                        TcPixels::U8(it) => write_u8(buf, it, scale, is_rgb),
-                        TcPixels::I16(it) => write_i16(buf, it, scale, is_rgb),
-                        TcPixels::I32(it) => write_i32(buf, it, scale, is_rgb),
-                        // Tile-compressed F32 and F64 iterators both yield f32
-                        TcPixels::F32(it) => write_f32(buf, it, is_rgb),
-                        TcPixels::F64(it) => write_f32(buf, it, is_rgb),
-                    },
-                    BinaryTableData::Table(_) => {
-                        return Err(to_image_error(
-                            "expected tile-compressed data but found plain binary table",
-                        ));
-                    }
+            HduImageKind::Image(hdu) => match self.fits.get_data(&hdu).pixels() {
+                Pixels::U8(it) => write_u8(buf, it, scale, is_rgb),
+                Pixels::I16(it) => write_i16(buf, it, scale, is_rgb),
+                Pixels::I32(it) => write_i32(buf, it, scale, is_rgb),
+                Pixels::I64(it) => write_i64(buf, it, scale, is_rgb),
+                Pixels::F32(it) => write_f32(buf, it, is_rgb),
+                Pixels::F64(it) => write_f64(buf, it, is_rgb),
+            },
+            HduImageKind::TileCompressed(hdu, _) => match self.fits.get_data(&hdu) {
+                // The tiles can span several rows, they are assembled in raster order first
+                BinaryTableData::TileCompressed(tc) => match tc {
+                    TcPixels::U8(it) => write_u8(buf, raster(it)?, scale, is_rgb),
+                    TcPixels::I16(it) => write_i16(buf, raster(it)?, scale, is_rgb),
+                    TcPixels::I32(it) => write_i32(buf, raster(it)?, scale, is_rgb),
+                    TcPixels::I64(it) => write_i64(buf, raster(it)?, scale, is_rgb),
+                    TcPixels::F32(it) => write_f32(buf, raster(it)?, is_rgb),
+                    TcPixels::F64(it) => write_f64(buf, raster(it)?, is_rgb),
+                },
+                BinaryTableData::Table(_) | BinaryTableData::TileCompressedTable(_) => {
+                    return Err(to_image_error(
+                        "expected tile-compressed data but found plain binary table",
+                    ));
                 }
-            }
+            },
         }
         Ok(())
     }
@@ -364,6 +372,32 @@ mod tests {
     use test_case::test_case;
     use ColorType::{Rgb32F, L16};
 
+    #[test]
+    fn decode_tile_compressed_image_in_raster_order() {
+        use crate::hdu::data::bintable::tile_compressed::tests::mock_gzip_tiles;
+        use image::ImageReader;
+        use std::io::Cursor;
+
+        register_fits_decoding_hook();
+
+        // A 3x3 image compressed in tiles of 2x2 pixels
+        let buf = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4], &[2, 5], &[6, 7], &[8]]);
+        let img = ImageReader::new(Cursor::new(buf))
+            .with_guessed_format()
+            .unwrap()
+            .decode()
+            .expect("decoding failed");
+
+        assert_eq!(img.dimensions(), (3, 3), "dimensions mismatch");
+        assert_eq!(img.color(), Rgb32F, "color type mismatch");
+        let pixels = img
+            .into_rgb32f()
+            .pixels()
+            .map(|p| (f64::from(p[0]) * f64::from(i32::MAX)).round() as i32)
+            .collect::<Vec<_>>();
+        assert_eq!(pixels, [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
     #[test_case("fits.gsfc.nasa.gov/Astro_UIT", 512, 512, L16)]
     #[test_case("fits.gsfc.nasa.gov/EUVE", 512, 512, L16)]
     #[test_case("fits.gsfc.nasa.gov/HST_FGS", 89688, 7, Rgb32F)]
@@ -44,23 +44,34 @@ extern crate quick_error;
 
 pub mod async_fits;
 pub mod card;
+pub mod checksum;
 pub mod error;
 pub mod file;
 pub mod fits;
+pub mod validate;
 pub mod wcs;
 
 #[cfg(any(test, feature = "image"))]
 pub mod image_integration;
 
+#[cfg(any(test, feature = "ndarray"))]
+pub mod ndarray_integration;
+
+#[cfg(any(test, feature = "arrow"))]
+pub mod arrow_integration;
+
 pub mod gz;
 pub mod hdu;
 
+#[cfg(test)]
+pub(crate) mod mock;
+
 pub use async_fits::AsyncFits;
 pub use file::FITSFile;
-pub use fits::Fits;
+pub use fits::{Fits, Limits, ParseMode, ParseOptions};
 pub use hdu::data::bintable::{DataValue, TableData, TableRowData};
 pub use hdu::data::image::{ImageData, Pixels};
-pub use hdu::data::iter::It;
+pub use hdu::data::iter::{It, TryNext};
 pub use hdu::{AsyncHDU, HDU};
 pub use wcs::{ImgXY, LonLat, WCSParams, WCS};
 
@@ -71,6 +82,7 @@ mod tests {
     use crate::hdu::data::image::Pixels;
     use crate::hdu::data::DataStream;
     use crate::hdu::AsyncHDU;
+    use crate::mock::MockFits;
     use crate::wcs::ImgXY;
     use crate::FITSFile;
 
@@ -283,6 +295,551 @@ mod tests {
         }
     }
 
+    /// Build an in-memory FITS file made of one primary image HDU
+    fn mock_image_fits(bitpix: i8, naxis: &[u64], data: &[u8]) -> Vec<u8> {
+        let mut cards = vec![
+            format!("SIMPLE  = {:>20}", "T"),
+            format!("BITPIX  = {bitpix:>20}"),
+            format!("NAXIS   = {:>20}", naxis.len()),
+        ];
+        for (i, naxisi) in naxis.iter().enumerate() {
+            cards.push(format!("NAXIS{:<3}= {naxisi:>20}", i + 1));
+        }
+        cards.push("END".to_owned());
+
+        // The data unit is not padded, so that it can be truncated
+        MockFits::default()
+            .header(&cards)
+            .data(data)
+            .build_unpadded()
+    }
+
+    #[test]
+    fn test_fits_image_read_into() {
+        let values: [i16; 6] = [0, 1, -1, 256, i16::MIN, i16::MAX];
+        let data = values
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .collect::<Vec<_>>();
+        let buf = mock_image_fits(16, &[3, 2], &data);
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+
+        let mut pixels = hdu_list.get_data(&hdu).pixels();
+        // BITPIX = 16 pixels cannot be read as f32
+        assert!(pixels.read_to_vec::<f32>().is_err());
+
+        let mut first = [0_i16; 4];
+        assert_eq!(pixels.read_into(&mut first), Ok(4));
+        assert_eq!(first, values[..4]);
+
+        // Only 2 pixels remain to be read
+        let mut last = [0_i16; 4];
+        assert_eq!(pixels.read_into(&mut last), Ok(2));
+        assert_eq!(last[..2], values[4..]);
+        assert_eq!(pixels.read_to_vec::<i16>(), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_fits_image_read_into_short_read() {
+        // 2 f32 pixels and a half instead of 4
+        let data = [1.5_f32, -2.0]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .chain([0x42, 0x28])
+            .collect::<Vec<_>>();
+        let buf = mock_image_fits(-32, &[4], &data);
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+
+        let Pixels::F32(mut it) = hdu_list.get_data(&hdu).pixels() else {
+            panic!("expected f32 pixels");
+        };
+
+        let mut values = [0.0_f32; 4];
+        assert_eq!(
+            it.read_into(&mut values),
+            Err(crate::error::Error::Truncated(6))
+        );
+        // the entirely read pixels are decoded
+        assert_eq!(values[..2], [1.5, -2.0]);
+        assert_eq!(it.num_remaining_items(), 2);
+
+        // the bytes of the half pixel are kept, the iterator stays aligned on the pixels
+        assert_eq!(
+            it.read_into(&mut values),
+            Err(crate::error::Error::Truncated(6))
+        );
+        assert_eq!(it.read_value(1), Ok(-2.0));
+        assert_eq!(it.read_value(0), Ok(1.5));
+    }
+
+    #[test]
+    fn test_fits_truncated_image() {
+        let data = [1_i16, 2, 3]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .chain([0x01])
+            .collect::<Vec<_>>();
+        let buf = mock_image_fits(16, &[3, 2], &data);
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        // 12 bytes are expected, only 7 are there
+        assert_eq!(
+            hdu_list.check_truncation(&hdu),
+            Err(crate::error::Error::Truncated(5))
+        );
+
+        let mut pixels = hdu_list.get_data(&hdu).pixels();
+        assert_eq!(pixels.read_available::<i16>(), Ok((vec![1, 2, 3], 3)));
+
+        // A complete data unit
+        let buf = mock_image_fits(16, &[3, 1], &data[..6]);
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        assert_eq!(hdu_list.check_truncation(&hdu), Ok(()));
+        let mut pixels = hdu_list.get_data(&hdu).pixels();
+        assert_eq!(pixels.read_available::<i16>(), Ok((vec![1, 2, 3], 0)));
+
+        // The header claims 1TB of pixels, only the ones in the stream are allocated
+        let buf = mock_image_fits(16, &[1_000_000_000_000], &data);
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        let mut pixels = hdu_list.get_data(&hdu).pixels();
+        assert_eq!(
+            pixels.read_available::<i16>(),
+            Ok((vec![1, 2, 3], 1_000_000_000_000 - 3))
+        );
+    }
+
+    #[test]
+    fn test_fits_try_next() {
+        use crate::error::Error;
+        use crate::hdu::data::bintable::DataValue;
+        use crate::TryNext;
+
+        let data = [1_i16, 2, 3]
+            .iter()
+            .flat_map(|v| v.to_be_bytes())
+            .chain([0x01])
+            .collect::<Vec<_>>();
+        let buf = mock_image_fits(16, &[3, 2], &data);
+
+        // The iterator stops at the end of the stream as if the data unit was complete
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        let Pixels::I16(it) = hdu_list.get_data(&hdu).pixels() else {
+            panic!("expected i16 pixels");
+        };
+        assert_eq!(it.collect::<Vec<_>>(), [1, 2, 3]);
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        let Pixels::I16(mut it) = hdu_list.get_data(&hdu).pixels() else {
+            panic!("expected i16 pixels");
+        };
+        for v in [1, 2, 3] {
+            assert_eq!(it.try_next(), Ok(Some(v)));
+        }
+        assert_eq!(it.try_next(), Err(Error::Truncated(5)));
+
+        // A binary table whose second row is truncated
+        let buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                    4",
+                "NAXIS2  =                    2",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    1",
+                "TFORM1  = '1J      '",
+                "END",
+            ])
+            .data(&[0, 0, 0, 7, 0, 0])
+            .build_unpadded();
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        hdu_list.next();
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("expected a binary table HDU");
+        };
+        let mut rows = hdu_list.get_data(&hdu).table_data().row_iter();
+        let row = rows.try_next().unwrap().unwrap();
+        assert!(matches!(*row, [DataValue::Integer { value: 7, .. }]));
+        assert!(matches!(rows.try_next(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn test_fits_bintable_bit_field() {
+        use crate::hdu::data::bintable::DataValue;
+
+        // 16 bits take exactly 2 bytes
+        let buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                    6",
+                "NAXIS2  =                    1",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    2",
+                "TFORM1  = '16X     '",
+                "TFORM2  = '1J      '",
+                "END",
+            ])
+            .data(&[0xaa, 0x55, 0, 0, 0, 9])
+            .build();
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        hdu_list.next();
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("expected a binary table HDU");
+        };
+        let rows = hdu_list
+            .get_data(&hdu)
+            .table_data()
+            .row_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(
+            *rows[0],
+            [
+                DataValue::Bit { byte: 0xaa, .. },
+                DataValue::Bit { byte: 0x55, .. },
+                DataValue::Integer { value: 9, .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_fits_bintable_empty_array() {
+        use crate::hdu::data::bintable::DataValue;
+
+        // The first row holds an empty array, the second one an array of one value
+        let mut data = vec![];
+        for (num_elems, value) in [(0_u32, 7_i32), (1, 8)] {
+            data.extend(num_elems.to_be_bytes());
+            data.extend(0_u32.to_be_bytes());
+            data.extend(value.to_be_bytes());
+        }
+        data.extend(5_i16.to_be_bytes());
+        let buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                   12",
+                "NAXIS2  =                    2",
+                "PCOUNT  =                    2",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    2",
+                "TFORM1  = '1PI     '",
+                "TFORM2  = '1J      '",
+                "END",
+            ])
+            .data(&data)
+            .build();
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(&buf[..]));
+        hdu_list.next();
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("expected a binary table HDU");
+        };
+        let rows = hdu_list
+            .get_data(&hdu)
+            .table_data()
+            .row_iter()
+            .collect::<Vec<_>>();
+        assert_eq!(rows.len(), 2);
+        // The descriptor of the empty array is given instead of its values
+        assert!(matches!(
+            *rows[0],
+            [
+                DataValue::VariableLengthArray32 { num_elems: 0, .. },
+                DataValue::Integer { value: 7, .. }
+            ]
+        ));
+        assert!(matches!(
+            *rows[1],
+            [
+                DataValue::Short { value: 5, .. },
+                DataValue::Integer { value: 8, .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn test_fits_strict_mode_fails_on_violation() {
+        use crate::error::Error;
+        use crate::fits::ParseOptions;
+
+        let mut buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "NAXIS   =                    0",
+                "BITPIX  =                    8",
+                "END",
+            ])
+            .build();
+
+        // The misplaced BITPIX is tolerated by default
+        let mut fits = Fits::from_reader(Cursor::new(&buf[..]));
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+
+        let mut fits = Fits::from_reader_with(Cursor::new(&buf[..]), ParseOptions::strict());
+        match fits.next() {
+            Some(Err(e)) => {
+                assert!(
+                    matches!(e.inner(), Error::DynamicError(e) if e.starts_with("Strict parsing"))
+                );
+                assert!(e.location().and_then(|l| l.card_idx).is_some());
+            }
+            _ => panic!("parsing should fail in strict mode"),
+        }
+        assert!(fits.next().is_none());
+
+        // A header padding filled with zeros instead of spaces
+        buf.copy_within(160..240, 80);
+        buf[160..240]
+            .copy_from_slice(format!("{:<80}", "NAXIS   =                    0").as_bytes());
+        buf[320..].fill(0);
+        let mut fits = Fits::from_reader(Cursor::new(&buf[..]));
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+        let mut fits = Fits::from_reader_with(Cursor::new(&buf[..]), ParseOptions::strict());
+        let err = fits.next().unwrap().expect_err("The padding is not valid");
+        assert!(matches!(err.inner(), Error::StaticError(_)));
+        assert_eq!(err.location().and_then(|l| l.byte_offset), Some(320));
+
+        buf[320..].fill(b' ');
+        let mut fits = Fits::from_reader_with(Cursor::new(&buf[..]), ParseOptions::strict());
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+        assert!(fits.next().is_none());
+    }
+
+    #[test]
+    fn test_fits_lenient_mode_repairs_quirks() {
+        use crate::card::{Card, Value};
+        use crate::fits::ParseOptions;
+
+        let mut buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .header(&[
+                "BITPIX  =                    8",
+                "XTENSION= 'IMAGE   '",
+                "naxis   =                    1",
+                "NAXIS1  =2",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "EXTNAME = 'SCI'",
+                "COMMENT =foo",
+                "HISTORY =x",
+                "END",
+            ])
+            .data(&[0, 0])
+            .build();
+        // A tab before the comment of EXTNAME
+        buf[2880 + 6 * 80 + 16] = b'\t';
+
+        // By default the XTENSION card must be the first one
+        let mut fits = Fits::from_reader(Cursor::new(&buf[..]));
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+        assert!(matches!(fits.next(), Some(Err(_))));
+
+        let mut fits = Fits::from_reader_with(Cursor::new(&buf[..]), ParseOptions::lenient());
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+        let Some(Ok(HDU::XImage(hdu))) = fits.next() else {
+            panic!("Should contain an image extension");
+        };
+        let header = hdu.get_header();
+        assert_eq!(header.get_xtension().get_naxis(), &[2]);
+        assert!(matches!(header.cards().next(), Some(Card::Xtension { .. })));
+        assert!(
+            matches!(header.get("EXTNAME"), Some(Value::String { value, .. }) if value == "SCI")
+        );
+        // The text of the commentary cards is not repaired
+        assert!(header
+            .cards()
+            .any(|card| card == &Card::Comment("=foo".to_owned())));
+        assert!(header
+            .cards()
+            .any(|card| card == &Card::History("=x".to_owned())));
+        assert_eq!(hdu.get_data_unit_byte_size(), 2);
+        assert!(fits.next().is_none());
+    }
+
+    #[test]
+    fn test_fits_error_location() {
+        use crate::error::{Error, Location};
+
+        let buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    3",
+                "NAXIS1  =                    4",
+                "NAXIS2  =                    1",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    1",
+                "TFORM1  = 'J       '",
+                "END",
+            ])
+            .build();
+
+        let mut fits = Fits::from_reader(Cursor::new(&buf[..]));
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+        let err = fits.next().unwrap().expect_err("NAXIS is not valid");
+        assert_eq!(
+            err.location(),
+            Some(&Location {
+                hdu_idx: Some(1),
+                card_idx: Some(2),
+                keyword: Some("NAXIS".to_owned()),
+                byte_offset: Some(2880 + 2 * 80),
+            })
+        );
+        assert!(matches!(err.inner(), Error::StaticError(_)));
+        assert_eq!(
+            err.to_string(),
+            "HDU 1, card 2 (NAXIS) at byte 3040: Binary Table HDU must have NAXIS = 2"
+        );
+    }
+
+    #[test]
+    fn test_fits_recover_after_corrupt_hdu() {
+        use crate::error::Error;
+        use crate::fits::ParseOptions;
+
+        // The size of the data unit of the binary table cannot be known, two blocks follow
+        let buf = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "EXTEND  =                    T",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                    4",
+                "NAXIS2  =                 1440",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    1",
+                "TFORM1  = 'K       '",
+                "END",
+            ])
+            .data(&[1_u8; 2 * 2880])
+            .header(&[
+                "XTENSION= 'IMAGE   '",
+                "BITPIX  =                    8",
+                "NAXIS   =                    1",
+                "NAXIS1  =                    3",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "END",
+            ])
+            .data(&[1, 2, 3])
+            .build();
+
+        // By default, the iteration ends at the corrupt HDU
+        let mut fits = Fits::from_reader(Cursor::new(&buf[..]));
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+        assert!(matches!(fits.next(), Some(Err(_))));
+        assert!(fits.next().is_none());
+
+        let options = ParseOptions {
+            recover: true,
+            ..Default::default()
+        };
+        let mut fits = Fits::from_reader_with(Cursor::new(&buf[..]), options);
+        assert!(matches!(fits.next(), Some(Ok(HDU::Primary(_)))));
+        let err = fits
+            .next()
+            .unwrap()
+            .expect_err("NAXIS1 does not match TFORM1");
+        assert_eq!(err.location().and_then(|l| l.hdu_idx), Some(1));
+        let Some(Ok(HDU::XImage(hdu))) = fits.next() else {
+            panic!("Should contain an image extension");
+        };
+        assert_eq!(hdu.get_header_byte_offset(), 4 * 2880);
+        let Pixels::U8(it) = fits.get_data(&hdu).pixels() else {
+            panic!("expected u8 pixels");
+        };
+        assert_eq!(it.collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(fits.next().is_none());
+
+        // A reader failing for ever ends the iteration instead of being resynchronized
+        #[derive(Debug)]
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("failing reader"))
+            }
+        }
+        impl std::io::Seek for FailingReader {
+            fn seek(&mut self, _pos: std::io::SeekFrom) -> std::io::Result<u64> {
+                Ok(0)
+            }
+        }
+
+        let mut fits = Fits::from_reader_with(FailingReader, options);
+        let err = fits.next().unwrap().expect_err("The reader fails");
+        assert!(matches!(err.inner(), Error::Io(_)));
+        assert!(fits.next().is_none());
+    }
+
     #[test]
     fn test_fits_image_borrowed() {
         use std::fs::File;
@@ -546,6 +1103,49 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_fits_limits_async() {
+        use crate::error::Error;
+        use crate::fits::Limits;
+
+        let buf = mock_image_fits(8, &[4, 2], &[0; 8]);
+
+        let limits = Limits {
+            max_num_cards: 3,
+            ..Default::default()
+        };
+        let mut hdu_list =
+            AsyncFits::from_reader_with_limits(futures::io::BufReader::new(&buf[..]), limits);
+        let err = hdu_list
+            .next()
+            .await
+            .unwrap()
+            .expect_err("The header has too many cards");
+        assert_eq!(
+            err.inner(),
+            &Error::LimitExceeded("Number of header cards", 4, 3)
+        );
+
+        let limits = Limits {
+            max_data_unit_byte_size: 7,
+            ..Default::default()
+        };
+        let mut hdu_list =
+            AsyncFits::from_reader_with_limits(futures::io::BufReader::new(&buf[..]), limits);
+        let err = hdu_list
+            .next()
+            .await
+            .unwrap()
+            .expect_err("The image is too large");
+        assert!(matches!(err.inner(), Error::LimitExceeded(_, 8, 7)));
+
+        let mut hdu_list = AsyncFits::from_reader(futures::io::BufReader::new(&buf[..]));
+        assert!(matches!(
+            hdu_list.next().await,
+            Some(Ok(AsyncHDU::Primary(_)))
+        ));
+    }
+
     #[test]
     fn test_fits_euve() {
         use std::fs::File;
@@ -0,0 +1,51 @@
+//! In-memory FITS files for the tests
+
+/// Size in bytes of a FITS block
+const BLOCK_NUM_BYTES: usize = 2880;
+
+/// Builds a FITS file in memory, one HDU after the other
+#[derive(Debug, Default)]
+pub(crate) struct MockFits {
+    bytes: Vec<u8>,
+}
+
+impl MockFits {
+    /// Append a header, each card being padded to 80 characters and the header to a
+    /// multiple of 2880 bytes with spaces
+    ///
+    /// The data unit of the previous HDU is padded with zeros first
+    pub(crate) fn header<S: AsRef<str>>(mut self, cards: &[S]) -> Self {
+        self.pad(0);
+        for card in cards {
+            self.bytes
+                .extend_from_slice(format!("{:<80}", card.as_ref()).as_bytes());
+        }
+        self.pad(b' ');
+
+        self
+    }
+
+    /// Append bytes to the data unit of the last HDU
+    pub(crate) fn data(mut self, data: &[u8]) -> Self {
+        self.bytes.extend_from_slice(data);
+
+        self
+    }
+
+    /// Get the file, the last data unit being padded with zeros
+    pub(crate) fn build(mut self) -> Vec<u8> {
+        self.pad(0);
+
+        self.bytes
+    }
+
+    /// Get the file without padding the last data unit, e.g. to mock a truncated file
+    pub(crate) fn build_unpadded(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    fn pad(&mut self, byte: u8) {
+        self.bytes
+            .resize(self.bytes.len().next_multiple_of(BLOCK_NUM_BYTES), byte);
+    }
+}
@@ -0,0 +1,411 @@
+//! Conversions between FITS data units and [ndarray] arrays
+//!
+//! FITS stores its arrays with the first axis (NAXIS1) varying the most rapidly, i.e. in
+//! column-major order. The arrays returned here are in the standard row-major layout of
+//! ndarray so their axes are given in the reverse order of the NAXISn cards: a FITS image
+//! with NAXIS1 = 300 and NAXIS2 = 200 gives an array of shape `[200, 300]` that can be
+//! indexed with `array[[y, x]]`.
+
+use std::convert::TryInto;
+use std::fmt::Debug;
+use std::io::{Read, Seek, Write};
+
+use ndarray::{ArrayD, ArrayView, Dimension, IxDyn};
+
+use crate::error::Error;
+use crate::hdu::data::bintable::tile_compressed::pixels::Pixels as TcPixels;
+use crate::hdu::data::bintable::DataValue;
+use crate::hdu::data::image::{ImageData, PixelType, Pixels};
+use crate::hdu::header::extension::bintable::BinTable;
+
+/// Size of a FITS block in bytes. Header and data units are padded to a multiple of it
+const FITS_BLOCK_NUM_BYTES: usize = 2880;
+/// Size of a header card in bytes
+const CARD_NUM_BYTES: usize = 80;
+/// Number of pixels converted to big-endian at once when writing an image
+const WRITE_BLOCK_NUM_ITEMS: usize = 1 << 16;
+
+/// The ndarray shape of FITS axes given in the NAXISn order
+fn shape_from_naxis<N: Copy + TryInto<usize>>(naxis: &[N]) -> Result<Vec<usize>, Error> {
+    naxis
+        .iter()
+        .rev()
+        .map(|&n| {
+            n.try_into()
+                .map_err(|_| Error::StaticError("Axis size does not fit into memory"))
+        })
+        .collect()
+}
+
+fn to_array<T>(shape: Vec<usize>, values: Vec<T>) -> Result<ArrayD<T>, Error> {
+    ArrayD::from_shape_vec(IxDyn(&shape), values).map_err(|e| Error::DynamicError(e.to_string()))
+}
+
+impl<R> ImageData<R>
+where
+    R: Read,
+{
+    /// Read the whole image into an array
+    ///
+    /// `T` must match the BITPIX of the image, e.g. `f32` for BITPIX = -32.
+    /// The axes of the array are in the reverse order of the NAXISn cards.
+    pub fn into_ndarray<T: PixelType>(self) -> Result<ArrayD<T>, Error> {
+        let naxis = self.naxis.clone();
+        self.pixels().into_ndarray(&naxis)
+    }
+}
+
+impl<R> Pixels<R>
+where
+    R: Read,
+{
+    /// Read all the remaining pixels into an array whose FITS axes sizes are `naxis`
+    ///
+    /// `T` must match the BITPIX of the image, e.g. `f32` for BITPIX = -32.
+    /// The axes of the array are in the reverse order of `naxis`.
+    pub fn into_ndarray<T: PixelType>(mut self, naxis: &[u64]) -> Result<ArrayD<T>, Error> {
+        let values = self.read_to_vec::<T>()?;
+        // An image with NAXIS = 0 has no data
+        let shape = if naxis.is_empty() {
+            vec![0]
+        } else {
+            shape_from_naxis(naxis)?
+        };
+
+        to_array(shape, values)
+    }
+}
+
+/// Pixel types a tile compressed image can be decompressed into
+pub trait TileCompressedPixelType: Default + Clone {
+    /// Decompress all the tiles of the image into an array
+    fn into_ndarray<R>(pixels: TcPixels<R>) -> Result<ArrayD<Self>, Error>
+    where
+        R: Read + Seek + Debug;
+}
+
+macro_rules! tile_compressed_pixel_type {
+    ( $t:ty, $( $variant:ident )|+ ) => {
+        impl TileCompressedPixelType for $t {
+            fn into_ndarray<R>(pixels: TcPixels<R>) -> Result<ArrayD<Self>, Error>
+            where
+                R: Read + Seek + Debug,
+            {
+                match pixels {
+                    $( TcPixels::$variant(it) => {
+                        let ctx = it.row_it.get_ctx();
+                        let z_image = ctx.z_image.as_ref().ok_or(Error::StaticError(
+                            "The binary table does not contain a tile compressed image",
+                        ))?;
+                        let shape = shape_from_naxis(&z_image.z_naxisn)?;
+
+                        to_array(shape, it.try_raster()?.into_vec())
+                    } )+
+                    _ => Err(Error::StaticError(
+                        "The pixel type asked does not match the ZBITPIX of the image",
+                    )),
+                }
+            }
+        }
+    };
+}
+
+tile_compressed_pixel_type!(u8, U8);
+tile_compressed_pixel_type!(i16, I16);
+tile_compressed_pixel_type!(i32, I32);
+tile_compressed_pixel_type!(i64, I64);
+tile_compressed_pixel_type!(f32, F32);
+tile_compressed_pixel_type!(f64, F64);
+
+impl<R> TcPixels<R>
+where
+    R: Read + Seek + Debug,
+{
+    /// Decompress the whole tile compressed image into an array
+    ///
+    /// `T` must match the ZBITPIX of the image, e.g. `i16` for ZBITPIX = 16.
+    /// The axes of the array are in the reverse order of the ZNAXISn cards.
+    pub fn into_ndarray<T: TileCompressedPixelType>(self) -> Result<ArrayD<T>, Error> {
+        T::into_ndarray(self)
+    }
+}
+
+/// Types the values of a binary table cell can be converted into
+pub trait CellType: Sized {
+    /// Get the value if its type matches `Self`
+    fn from_data_value(value: &DataValue) -> Option<Self>;
+}
+
+macro_rules! cell_type {
+    ( $t:ty, $variant:ident ) => {
+        impl CellType for $t {
+            fn from_data_value(value: &DataValue) -> Option<Self> {
+                if let DataValue::$variant { value, .. } = value {
+                    Some(*value)
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
+cell_type!(bool, Logical);
+cell_type!(u8, UnsignedByte);
+cell_type!(i16, Short);
+cell_type!(i32, Integer);
+cell_type!(i64, Long);
+cell_type!(char, Character);
+cell_type!(f32, Float);
+cell_type!(f64, Double);
+
+/// Convert the cell of a binary table row into an array
+///
+/// The shape of the array is given by the TDIMn card of the column, its axes being in
+/// the reverse order of the TDIMn dimensions. Without a TDIMn card, a one dimensional
+/// array containing all the values of the cell is returned.
+///
+/// # Params
+///
+/// * `ctx` - The binary table header extension
+/// * `row` - A row given by a [crate::TableRowData] iterator
+/// * `col_idx` - The index of the column of the cell
+pub fn cell_to_ndarray<T: CellType>(
+    ctx: &BinTable,
+    row: &[DataValue],
+    col_idx: usize,
+) -> Result<ArrayD<T>, Error> {
+    let mut values = row
+        .iter()
+        .filter(|value| value.column_idx() == Some(col_idx))
+        .map(|value| {
+            T::from_data_value(value).ok_or(Error::StaticError(
+                "The cell type asked does not match the TFORM of the column",
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let shape = if let Some(tdim) = ctx.get_tdim(col_idx) {
+        // The TDIMn array may not fill the whole repeat count of the field
+        let num_values = tdim.iter().product::<usize>();
+        if num_values > values.len() {
+            return Err(Error::StaticError(
+                "TDIMn describes more values than the cell contains",
+            ));
+        }
+        values.truncate(num_values);
+
+        shape_from_naxis(tdim)?
+    } else {
+        vec![values.len()]
+    };
+
+    to_array(shape, values)
+}
+
+/// Format a header card padded to 80 bytes
+fn write_card(header: &mut Vec<u8>, keyword: &str, value: &str) {
+    let card = format!("{keyword:<8}= {value:>20}");
+    header.extend_from_slice(card.as_bytes());
+    header.resize(header.len() + CARD_NUM_BYTES - card.len(), b' ');
+}
+
+/// Write an array as an image HDU
+///
+/// The NAXISn cards are given in the reverse order of the array axes so that reading
+/// the HDU back with [ImageData::into_ndarray] gives the same array.
+///
+/// # Params
+///
+/// * `writer` - The writer where the HDU is written
+/// * `array` - The pixels of the image. Its element type gives the BITPIX of the HDU
+/// * `primary` - Write a primary HDU if `true`, an IMAGE extension otherwise
+pub fn write_image_hdu<W, T, D>(
+    writer: &mut W,
+    array: ArrayView<'_, T, D>,
+    primary: bool,
+) -> Result<(), Error>
+where
+    W: Write,
+    T: PixelType,
+    D: Dimension,
+{
+    let mut header = Vec::with_capacity(FITS_BLOCK_NUM_BYTES);
+    if primary {
+        write_card(&mut header, "SIMPLE", "T");
+    } else {
+        // Character string values begin right after the value indicator
+        write_card(&mut header, "XTENSION", "'IMAGE   '          ");
+    }
+    write_card(&mut header, "BITPIX", &(T::BITPIX as i8).to_string());
+    write_card(&mut header, "NAXIS", &array.ndim().to_string());
+    for (i, naxisi) in array.shape().iter().rev().enumerate() {
+        write_card(&mut header, &format!("NAXIS{}", i + 1), &naxisi.to_string());
+    }
+    if !primary {
+        write_card(&mut header, "PCOUNT", "0");
+        write_card(&mut header, "GCOUNT", "1");
+    }
+    header.extend_from_slice(format!("{:<80}", "END").as_bytes());
+    header.resize(header.len().next_multiple_of(FITS_BLOCK_NUM_BYTES), b' ');
+    writer.write_all(&header)?;
+
+    // The pixels are converted to big-endian by blocks, whatever the memory layout of the array
+    let mut num_bytes = 0;
+    let mut block = Vec::with_capacity(WRITE_BLOCK_NUM_ITEMS.min(array.len()));
+    let mut write_block = |block: &mut Vec<T>| -> Result<(), Error> {
+        // Swapping bytes is its own inverse, converting from big-endian also converts to it
+        T::from_be_slice(block);
+        let bytes = T::as_bytes_mut(block);
+        writer.write_all(bytes)?;
+        num_bytes += bytes.len();
+        block.clear();
+
+        Ok(())
+    };
+    for &pixel in array.iter() {
+        block.push(pixel);
+        if block.len() == WRITE_BLOCK_NUM_ITEMS {
+            write_block(&mut block)?;
+        }
+    }
+    write_block(&mut block)?;
+
+    // The data unit is padded with zeros
+    let num_padding_bytes = num_bytes.next_multiple_of(FITS_BLOCK_NUM_BYTES) - num_bytes;
+    writer.write_all(&vec![0_u8; num_padding_bytes])?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cell_to_ndarray, write_image_hdu};
+    use crate::hdu::data::bintable::data::BinaryTableData;
+    use crate::mock::MockFits;
+    use crate::{Fits, HDU};
+
+    use ndarray::{array, ArrayD};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_ndarray_image_roundtrip() {
+        let array = array![[1.5_f32, -2.0, 3.25], [4.0, 5.5, -6.75]];
+
+        let mut bytes = Vec::new();
+        write_image_hdu(&mut bytes, array.view(), true).unwrap();
+        write_image_hdu(&mut bytes, array.t(), false).unwrap();
+        assert_eq!(bytes.len(), 4 * 2880);
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(bytes));
+
+        let Some(Ok(HDU::Primary(hdu))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        assert_eq!(hdu.get_header().get_xtension().get_naxis(), &[3, 2]);
+        let image = hdu_list.get_data(&hdu).into_ndarray::<f32>().unwrap();
+        assert_eq!(image, array.clone().into_dyn());
+
+        let Some(Ok(HDU::XImage(hdu))) = hdu_list.next() else {
+            panic!("expected an image extension HDU");
+        };
+        assert_eq!(hdu.get_header().get_xtension().get_naxis(), &[2, 3]);
+        let image = hdu_list.get_data(&hdu);
+        assert!(image.into_ndarray::<i16>().is_err());
+
+        let image = hdu_list.get_data(&hdu).into_ndarray::<f32>().unwrap();
+        assert_eq!(image, array.t().to_owned().into_dyn());
+    }
+
+    #[test]
+    fn test_ndarray_tile_compressed_image() {
+        use crate::error::Error;
+        use crate::hdu::data::bintable::tile_compressed::tests::mock_gzip_tiles;
+
+        // A 3x3 image compressed in tiles of 2x2 pixels. The tiles on the borders are smaller
+        let bytes = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4], &[2, 5], &[6, 7], &[8]]);
+        let tile_compressed_image = |bytes: &[u8]| {
+            let mut hdu_list = Fits::from_reader(Cursor::new(bytes.to_vec()));
+            let Some(Ok(HDU::Primary(_))) = hdu_list.next() else {
+                panic!("expected a primary HDU");
+            };
+            let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+                panic!("expected a binary table HDU");
+            };
+            let BinaryTableData::TileCompressed(pixels) = hdu_list.get_data(&hdu) else {
+                panic!("expected a tile compressed image");
+            };
+            pixels.into_ndarray::<i32>()
+        };
+
+        let image = tile_compressed_image(&bytes).unwrap();
+        assert_eq!(image, array![[0, 1, 2], [3, 4, 5], [6, 7, 8]].into_dyn());
+
+        // A corrupt tile gives the error of its decompression
+        let mut corrupt = bytes.clone();
+        let gzip_magic = corrupt.windows(2).position(|b| b == [0x1f, 0x8b]).unwrap();
+        corrupt[gzip_magic] = 0;
+        let err = tile_compressed_image(&corrupt).unwrap_err();
+        assert!(matches!(err.inner(), Error::Io(_)));
+
+        // The image misses its last tile
+        let bytes = mock_gzip_tiles([3, 3], [2, 2], &[&[0, 1, 3, 4], &[2, 5], &[6, 7]]);
+        assert!(tile_compressed_image(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_ndarray_tdim_cell() {
+        let mut data = 42_i16.to_be_bytes().to_vec();
+        data.extend((1..=6_i16).flat_map(|v| v.to_be_bytes()));
+        let bytes = MockFits::default()
+            .header(&[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+                "EXTEND  =                    T",
+                "END",
+            ])
+            .header(&[
+                "XTENSION= 'BINTABLE'",
+                "BITPIX  =                    8",
+                "NAXIS   =                    2",
+                "NAXIS1  =                   14",
+                "NAXIS2  =                    1",
+                "PCOUNT  =                    0",
+                "GCOUNT  =                    1",
+                "TFIELDS =                    2",
+                "TTYPE1  = 'ID      '",
+                "TFORM1  = '1I      '",
+                "TTYPE2  = 'ARRAY   '",
+                "TFORM2  = '6I      '",
+                "TDIM2   = '(3,2)   '",
+                "END",
+            ])
+            .data(&data)
+            .build();
+
+        let mut hdu_list = Fits::from_reader(Cursor::new(bytes));
+        let Some(Ok(HDU::Primary(_))) = hdu_list.next() else {
+            panic!("expected a primary HDU");
+        };
+        let Some(Ok(HDU::XBinaryTable(hdu))) = hdu_list.next() else {
+            panic!("expected a binary table HDU");
+        };
+        let ctx = hdu.get_header().get_xtension();
+        assert_eq!(ctx.get_tdim(0), None);
+        assert_eq!(ctx.get_tdim(1), Some(&[3, 2][..]));
+
+        let BinaryTableData::Table(table) = hdu_list.get_data(&hdu) else {
+            panic!("expected a binary table");
+        };
+        let row = table.row_iter().next().unwrap();
+
+        let id = cell_to_ndarray::<i16>(ctx, &row, 0).unwrap();
+        assert_eq!(id, ArrayD::from_shape_vec(vec![1], vec![42]).unwrap());
+
+        let cell = cell_to_ndarray::<i16>(ctx, &row, 1).unwrap();
+        assert_eq!(cell, array![[1, 2, 3], [4, 5, 6]].into_dyn());
+
+        assert!(cell_to_ndarray::<f32>(ctx, &row, 1).is_err());
+    }
+}
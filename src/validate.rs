@@ -0,0 +1,949 @@
+//! Conformance checks of a FITS file against the FITS 4.0 standard
+//!
+//! Contrary to the [Fits](crate::Fits) parser that tolerates many deviations from the
+//! standard, [validate] reads the raw bytes of the file and reports every deviation it
+//! finds, in the spirit of the `fitsverify` tool.
+//!
+//! # Example
+//!
+//! ```
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! use fitsrs::validate::{validate, Severity};
+//!
+//! let f = File::open("samples/fits.gsfc.nasa.gov/HST_FOC.fits").unwrap();
+//! let findings = validate(BufReader::new(f)).unwrap();
+//! for finding in findings.iter().filter(|f| f.severity == Severity::Error) {
+//!     println!("{finding}");
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt;
+use std::io::Read;
+
+use crate::card::{Card, CardBuf, Value};
+use crate::error::Error;
+use crate::hdu::header::extension::asciitable::TFormAsciiTable;
+use crate::hdu::header::extension::bintable::parse_tform;
+use crate::hdu::header::extension::XtensionType;
+use serde::Deserialize;
+
+const BLOCK_NUM_BYTES: usize = 2880;
+const CARD_NUM_BYTES: usize = 80;
+
+/// Severity of a finding
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum Severity {
+    /// The file is readable but does not follow a recommendation of the standard
+    Warning,
+    /// The file violates the standard
+    Error,
+}
+
+/// A deviation from the FITS standard
+#[derive(Debug, PartialEq, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    /// Index of the HDU, the primary HDU being 0
+    pub hdu_idx: usize,
+    /// Index of the card in the header of the HDU, `None` if the finding is not related to a card
+    pub card_idx: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        };
+        write!(f, "{severity}: HDU {}", self.hdu_idx)?;
+        if let Some(card_idx) = self.card_idx {
+            write!(f, ", card {card_idx}")?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Check a FITS file against the FITS 4.0 standard
+///
+/// The whole file is read. The checks of a HDU cover:
+/// * the characters of the cards, the keyword names and the duplicated keywords
+/// * the order and the values of the mandatory keywords
+/// * the BLANK keyword of floating point images
+/// * the consistency of the TFORMn and TBCOLn keywords with the row size of the tables
+/// * the END card, the padding of the header and the data unit
+/// * the EXTEND keyword of the primary header when extensions follow
+///
+/// The checks stop at the first HDU whose size cannot be determined.
+///
+/// # Params
+/// * `reader` - a reader created i.e. from the opening of a file
+pub fn validate<R: Read>(mut reader: R) -> Result<Vec<Finding>, Error> {
+    let mut validator = Validator {
+        findings: vec![],
+        hdu_idx: 0,
+    };
+    // Value of EXTEND in the primary header, and its card index
+    let mut extend = None;
+    // Number of headers read, the data unit of the last one may be truncated
+    let mut num_headers = 0;
+
+    while let Some(cards) = validator.read_header(&mut reader)? {
+        num_headers += 1;
+        let header = validator.check_header(&cards, validator.hdu_idx == 0);
+        if validator.hdu_idx == 0 {
+            extend = header
+                .values
+                .get("EXTEND")
+                .and_then(|(idx, value)| match value {
+                    Value::Logical { value, .. } => Some((*value, *idx)),
+                    _ => None,
+                });
+        }
+
+        let num_bytes = match header.data_unit_byte_size() {
+            Ok(num_bytes) => num_bytes,
+            Err(reason) => {
+                validator.push(
+                    Severity::Error,
+                    None,
+                    format!("{reason}, the checks stop here"),
+                );
+                break;
+            }
+        };
+
+        let padding = if header.xtension == Some(XtensionType::AsciiTable) {
+            b' '
+        } else {
+            0
+        };
+        if !validator.check_data_unit(&mut reader, num_bytes, padding)? {
+            break;
+        }
+
+        validator.hdu_idx += 1;
+    }
+
+    if let Some((false, card_idx)) = extend {
+        if num_headers > 1 {
+            validator.findings.push(Finding {
+                severity: Severity::Warning,
+                hdu_idx: 0,
+                card_idx: Some(card_idx),
+                message: "EXTEND = F but the file contains extensions".to_owned(),
+            });
+        }
+    }
+
+    Ok(validator.findings)
+}
+
+/// Get the first violation of the standard found in a header, used by the strict parsing
+///
+/// # Params
+/// * `cards` - The cards of the header, END included
+/// * `is_primary` - Whether the header is the primary one
+pub(crate) fn first_header_violation(cards: &[CardBuf], is_primary: bool) -> Option<Finding> {
+    let mut validator = Validator {
+        findings: vec![],
+        hdu_idx: 0,
+    };
+    validator.check_header(cards, is_primary);
+
+    validator
+        .findings
+        .into_iter()
+        .find(|finding| finding.severity == Severity::Error)
+}
+
+struct Validator {
+    findings: Vec<Finding>,
+    /// Index of the HDU currently checked
+    hdu_idx: usize,
+}
+
+/// The parsed header of a HDU
+struct ParsedHeader {
+    /// Extension type, `None` for the primary HDU or an unknown extension
+    xtension: Option<XtensionType>,
+    is_primary: bool,
+    /// The value cards by keyword, with the index of their first occurrence
+    values: HashMap<String, (usize, Value)>,
+}
+
+impl ParsedHeader {
+    fn integer(&self, keyword: &str) -> Option<i64> {
+        match self.values.get(keyword) {
+            Some((_, Value::Integer { value, .. })) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn string(&self, keyword: &str) -> Option<&str> {
+        match self.values.get(keyword) {
+            Some((_, Value::String { value, .. })) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Size in bytes of the data unit, without its padding
+    ///
+    /// Returns the reason why it cannot be computed otherwise
+    fn data_unit_byte_size(&self) -> Result<u64, &'static str> {
+        const MISSING: &str = "The size of the data unit cannot be computed";
+        const OVERFLOW: &str = "The data unit size overflows";
+
+        let bitpix = self.integer("BITPIX").ok_or(MISSING)?;
+        let naxis = self.integer("NAXIS").ok_or(MISSING)?;
+        if naxis == 0 {
+            return Ok(0);
+        }
+
+        let naxisn = (1..=naxis.clamp(0, 999))
+            .map(|i| self.integer(&format!("NAXIS{i}")).map(|n| n.max(0) as u64))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(MISSING)?;
+        // Random groups have NAXIS1 = 0, this axis is not taken into account
+        let is_random_groups = self.is_primary && naxisn.first() == Some(&0);
+        let axes = if is_random_groups {
+            &naxisn[1..]
+        } else {
+            &naxisn[..]
+        };
+        let num_pixels = axes
+            .iter()
+            .try_fold(1_u64, |n, &naxisi| n.checked_mul(naxisi))
+            .ok_or(OVERFLOW)?;
+
+        let (pcount, gcount) = if self.is_primary && !is_random_groups {
+            (0, 1)
+        } else {
+            (
+                self.integer("PCOUNT").unwrap_or(0).max(0) as u64,
+                self.integer("GCOUNT").unwrap_or(1).max(0) as u64,
+            )
+        };
+
+        pcount
+            .checked_add(num_pixels)
+            .and_then(|n| n.checked_mul(gcount))
+            .and_then(|n| n.checked_mul(bitpix.unsigned_abs() / 8))
+            .ok_or(OVERFLOW)
+    }
+}
+
+impl Validator {
+    fn push(&mut self, severity: Severity, card_idx: Option<usize>, message: String) {
+        self.findings.push(Finding {
+            severity,
+            hdu_idx: self.hdu_idx,
+            card_idx,
+            message,
+        });
+    }
+
+    /// Read the cards of a header until the END card
+    ///
+    /// Returns `None` if the file ends or if the header cannot be read
+    fn read_header<R: Read>(&mut self, reader: &mut R) -> Result<Option<Vec<CardBuf>>, Error> {
+        let mut cards = vec![];
+        let mut block = [0_u8; BLOCK_NUM_BYTES];
+
+        loop {
+            let num_bytes = read_block(reader, &mut block)?;
+            if num_bytes == 0 && cards.is_empty() {
+                if self.hdu_idx == 0 {
+                    self.push(Severity::Error, None, "The file is empty".to_owned());
+                }
+                return Ok(None);
+            } else if num_bytes < BLOCK_NUM_BYTES {
+                let message = if num_bytes == 0 {
+                    "The END card is missing"
+                } else {
+                    "The header is truncated, the file size is not a multiple of 2880 bytes"
+                };
+                self.push(Severity::Error, None, message.to_owned());
+                return Ok(None);
+            }
+
+            if cards.is_empty() && self.hdu_idx > 0 && !block.starts_with(b"XTENSION") {
+                self.push(
+                    Severity::Error,
+                    None,
+                    "Unexpected bytes after the last HDU, an extension must start with XTENSION"
+                        .to_owned(),
+                );
+                return Ok(None);
+            }
+
+            for (idx, card) in block.chunks_exact(CARD_NUM_BYTES).enumerate() {
+                let card: CardBuf = card.try_into().expect("cards are 80 bytes long");
+                if &card[..8] == b"END     " {
+                    cards.push(card);
+
+                    // The rest of the block must be filled with spaces
+                    let padding = &block[(idx + 1) * CARD_NUM_BYTES..];
+                    if padding.iter().any(|&b| b != b' ') {
+                        self.push(
+                            Severity::Error,
+                            Some(cards.len() - 1),
+                            "The header is not padded with spaces after the END card".to_owned(),
+                        );
+                    }
+                    return Ok(Some(cards));
+                }
+                cards.push(card);
+            }
+        }
+    }
+
+    /// Check the cards of a header and parse its values
+    fn check_header(&mut self, cards: &[CardBuf], is_primary: bool) -> ParsedHeader {
+        let mut values = HashMap::new();
+        let mut xtension = None;
+        // Whether the previous value is a string continued with a CONTINUE card
+        let mut continued = false;
+
+        for (idx, card) in cards.iter().enumerate() {
+            self.check_characters(idx, card);
+
+            let parsed = match Card::try_from(card) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    self.push(
+                        Severity::Error,
+                        Some(idx),
+                        format!("The card cannot be parsed: {err}"),
+                    );
+                    continue;
+                }
+            };
+
+            match parsed {
+                Card::Value { name, value } | Card::Hierarch { name, value } => {
+                    continued = value.continued();
+                    if let Some((first_idx, _)) = values.get(&name) {
+                        self.push(
+                            Severity::Warning,
+                            Some(idx),
+                            format!("Duplicated keyword {name}, first found at card {first_idx}"),
+                        );
+                    } else {
+                        values.insert(name, (idx, value));
+                    }
+                }
+                Card::Xtension { x, .. } => {
+                    continued = false;
+                    if idx == 0 {
+                        xtension = Some(x);
+                    }
+                }
+                Card::Continuation { .. } => {
+                    if !continued {
+                        self.push(
+                            Severity::Warning,
+                            Some(idx),
+                            "CONTINUE card not preceded by a string ending with '&'".to_owned(),
+                        );
+                    }
+                }
+                Card::End => {
+                    if card[8..].iter().any(|&b| b != b' ') {
+                        self.push(
+                            Severity::Error,
+                            Some(idx),
+                            "The END card must be filled with spaces".to_owned(),
+                        );
+                    }
+                }
+                Card::Undefined(_) => {
+                    continued = false;
+                    self.push(
+                        Severity::Warning,
+                        Some(idx),
+                        "The card has a keyword but no value indicator".to_owned(),
+                    );
+                }
+                Card::Comment(_) | Card::History(_) | Card::Space => continued = false,
+            }
+        }
+
+        let header = ParsedHeader {
+            xtension,
+            is_primary,
+            values,
+        };
+
+        let names = cards
+            .iter()
+            .map(|card| String::from_utf8_lossy(&card[..8]).trim_end().to_owned())
+            .collect::<Vec<_>>();
+        self.check_mandatory_keywords(&names, &header);
+        self.check_values(&header);
+        if header.xtension == Some(XtensionType::BinTable) {
+            self.check_bintable_fields(&header);
+        } else if header.xtension == Some(XtensionType::AsciiTable) {
+            self.check_asciitable_fields(&header);
+        }
+
+        header
+    }
+
+    /// Check that a card only has printable ASCII characters and a valid keyword name
+    fn check_characters(&mut self, idx: usize, card: &CardBuf) {
+        if let Some(col) = card.iter().position(|b| !(0x20..=0x7e).contains(b)) {
+            self.push(
+                Severity::Error,
+                Some(idx),
+                format!(
+                    "Illegal character 0x{:02x} at column {}",
+                    card[col],
+                    col + 1
+                ),
+            );
+        }
+
+        let keyword = &card[..8];
+        let len = keyword.iter().position(|&b| b == b' ').unwrap_or(8);
+        let is_valid = keyword[..len]
+            .iter()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || *b == b'-' || *b == b'_')
+            && keyword[len..].iter().all(|&b| b == b' ');
+        if !is_valid {
+            self.push(
+                Severity::Error,
+                Some(idx),
+                format!(
+                    "Illegal keyword name '{}', only uppercase letters, digits, '-' and '_' are allowed",
+                    String::from_utf8_lossy(keyword).trim_end()
+                ),
+            );
+        }
+    }
+
+    /// Check the order of the mandatory keywords, and that they are not found elsewhere
+    fn check_mandatory_keywords(&mut self, names: &[String], header: &ParsedHeader) {
+        let mut expected = vec![if header.is_primary {
+            "SIMPLE".to_owned()
+        } else {
+            "XTENSION".to_owned()
+        }];
+        expected.push("BITPIX".to_owned());
+        expected.push("NAXIS".to_owned());
+        let naxis = header.integer("NAXIS").unwrap_or(0).clamp(0, 999);
+        expected.extend((1..=naxis).map(|i| format!("NAXIS{i}")));
+        if !header.is_primary {
+            expected.push("PCOUNT".to_owned());
+            expected.push("GCOUNT".to_owned());
+        }
+        if matches!(
+            header.xtension,
+            Some(XtensionType::BinTable | XtensionType::AsciiTable)
+        ) {
+            expected.push("TFIELDS".to_owned());
+        }
+
+        for (idx, expected) in expected.iter().enumerate() {
+            match names.get(idx) {
+                Some(name) if name == expected => (),
+                Some(name) => self.push(
+                    Severity::Error,
+                    Some(idx),
+                    format!("Expected the mandatory keyword {expected}, found '{name}'"),
+                ),
+                None => self.push(
+                    Severity::Error,
+                    None,
+                    format!("The mandatory keyword {expected} is missing"),
+                ),
+            }
+        }
+
+        // The reserved keywords must not appear out of their place
+        let is_reserved = |name: &str| {
+            matches!(
+                name,
+                "SIMPLE" | "XTENSION" | "BITPIX" | "NAXIS" | "PCOUNT" | "GCOUNT" | "TFIELDS"
+            ) || name
+                .strip_prefix("NAXIS")
+                .is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit()))
+        };
+        for (idx, name) in names.iter().enumerate().skip(expected.len()) {
+            if is_reserved(name) && name != "END" {
+                self.push(
+                    Severity::Error,
+                    Some(idx),
+                    format!("The keyword {name} is not allowed at this place of the header"),
+                );
+            }
+        }
+    }
+
+    /// Check the values of the mandatory keywords and of BLANK
+    fn check_values(&mut self, header: &ParsedHeader) {
+        let card_idx = |keyword: &str| header.values.get(keyword).map(|(idx, _)| *idx);
+
+        if header.is_primary {
+            if let Some((idx, Value::Logical { value: false, .. })) = header.values.get("SIMPLE") {
+                self.push(
+                    Severity::Warning,
+                    Some(*idx),
+                    "SIMPLE = F, the file does not conform to the standard".to_owned(),
+                );
+            }
+        }
+
+        let bitpix = header.integer("BITPIX");
+        if let Some(bitpix) = bitpix {
+            if ![8, 16, 32, 64, -32, -64].contains(&bitpix) {
+                self.push(
+                    Severity::Error,
+                    card_idx("BITPIX"),
+                    format!("Illegal BITPIX value {bitpix}"),
+                );
+            }
+        }
+
+        if let Some(naxis) = header.integer("NAXIS") {
+            if !(0..=999).contains(&naxis) {
+                self.push(
+                    Severity::Error,
+                    card_idx("NAXIS"),
+                    format!("Illegal NAXIS value {naxis}, it must be between 0 and 999"),
+                );
+            }
+            for i in 1..=naxis.clamp(0, 999) {
+                let keyword = format!("NAXIS{i}");
+                if matches!(header.integer(&keyword), Some(n) if n < 0) {
+                    self.push(
+                        Severity::Error,
+                        card_idx(&keyword),
+                        format!("{keyword} must be a non negative integer"),
+                    );
+                }
+            }
+        }
+
+        // The constraints of the extensions
+        let (bitpix_8, naxis_2, pcount_0) = match header.xtension {
+            Some(XtensionType::Image) => (false, false, true),
+            Some(XtensionType::AsciiTable) => (true, true, true),
+            Some(XtensionType::BinTable) => (true, true, false),
+            None => (false, false, false),
+        };
+        let mut check = |keyword: &str, expected: i64| {
+            if let Some(value) = header.integer(keyword) {
+                if value != expected {
+                    self.push(
+                        Severity::Error,
+                        card_idx(keyword),
+                        format!("{keyword} must be {expected} in this extension, found {value}"),
+                    );
+                }
+            }
+        };
+        if bitpix_8 {
+            check("BITPIX", 8);
+        }
+        if naxis_2 {
+            check("NAXIS", 2);
+        }
+        if pcount_0 {
+            check("PCOUNT", 0);
+        }
+        if header.xtension.is_some() {
+            check("GCOUNT", 1);
+        }
+
+        if let (Some(bitpix), Some(idx)) = (bitpix, card_idx("BLANK")) {
+            if bitpix < 0 {
+                self.push(
+                    Severity::Error,
+                    Some(idx),
+                    "BLANK is not allowed in floating point images".to_owned(),
+                );
+            }
+        }
+    }
+
+    /// Check that TFIELDS is between 0 and 999 and return the number of fields to check
+    fn check_tfields(&mut self, header: &ParsedHeader) -> i64 {
+        let tfields = header.integer("TFIELDS").unwrap_or(0);
+        if !(0..=999).contains(&tfields) {
+            self.push(
+                Severity::Error,
+                header.values.get("TFIELDS").map(|(idx, _)| *idx),
+                format!("Illegal TFIELDS value {tfields}, it must be between 0 and 999"),
+            );
+        }
+        tfields.clamp(0, 999)
+    }
+
+    /// Check that the TFORMn keywords are valid and that they sum up to NAXIS1
+    fn check_bintable_fields(&mut self, header: &ParsedHeader) {
+        let tfields = self.check_tfields(header);
+
+        let mut row_byte_size = 0;
+        for i in 1..=tfields {
+            let keyword = format!("TFORM{i}");
+            match header.string(&keyword) {
+                // The illegal TFORMn are reported below
+                Some(tform) => match parse_tform(tform.trim(), &keyword, &mut vec![]) {
+                    Some(tform) => row_byte_size += tform.num_bytes_field() as i64,
+                    None => self.push(
+                        Severity::Error,
+                        header.values.get(&keyword).map(|(idx, _)| *idx),
+                        format!("Illegal {keyword} value '{tform}'"),
+                    ),
+                },
+                None => self.push(
+                    Severity::Error,
+                    None,
+                    format!("The keyword {keyword} is missing"),
+                ),
+            }
+        }
+
+        if let Some(naxis1) = header.integer("NAXIS1") {
+            if row_byte_size != naxis1 {
+                self.push(
+                    Severity::Error,
+                    header.values.get("NAXIS1").map(|(idx, _)| *idx),
+                    format!("The fields take {row_byte_size} bytes per row but NAXIS1 = {naxis1}"),
+                );
+            }
+        }
+    }
+
+    /// Check that the TFORMn keywords are valid and that the fields given by TBCOLn fit in a row
+    fn check_asciitable_fields(&mut self, header: &ParsedHeader) {
+        let tfields = self.check_tfields(header);
+        let naxis1 = header.integer("NAXIS1").unwrap_or(0);
+
+        for i in 1..=tfields {
+            let tform_kw = format!("TFORM{i}");
+            let tbcol_kw = format!("TBCOL{i}");
+
+            let width = match header.values.get(&tform_kw) {
+                Some((idx, value)) => match TFormAsciiTable::deserialize(value) {
+                    Ok(tform) => Some(tform.width()),
+                    Err(_) => {
+                        self.push(
+                            Severity::Error,
+                            Some(*idx),
+                            format!("Illegal {tform_kw} value"),
+                        );
+                        None
+                    }
+                },
+                None => {
+                    self.push(
+                        Severity::Error,
+                        None,
+                        format!("The keyword {tform_kw} is missing"),
+                    );
+                    None
+                }
+            };
+
+            let Some(tbcol) = header.integer(&tbcol_kw) else {
+                self.push(
+                    Severity::Error,
+                    header.values.get(&tbcol_kw).map(|(idx, _)| *idx),
+                    format!("The keyword {tbcol_kw} is missing or is not an integer"),
+                );
+                continue;
+            };
+
+            let last_col = tbcol + width.unwrap_or(1) as i64 - 1;
+            if tbcol < 1 || last_col > naxis1 {
+                self.push(
+                    Severity::Error,
+                    header.values.get(&tbcol_kw).map(|(idx, _)| *idx),
+                    format!(
+                        "The field {i} spans the columns {tbcol} to {last_col}, outside of the row of NAXIS1 = {naxis1} characters"
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Skip the data unit and check its padding
+    ///
+    /// Returns `false` if the file ends before the end of the data unit
+    fn check_data_unit<R: Read>(
+        &mut self,
+        reader: &mut R,
+        num_bytes: u64,
+        padding: u8,
+    ) -> Result<bool, Error> {
+        let num_skipped = std::io::copy(&mut reader.take(num_bytes), &mut std::io::sink())?;
+        if num_skipped < num_bytes {
+            self.push(
+                Severity::Error,
+                None,
+                format!("The data unit is truncated, {num_skipped} bytes out of {num_bytes}"),
+            );
+            return Ok(false);
+        }
+
+        let num_padding_bytes =
+            (num_bytes.next_multiple_of(BLOCK_NUM_BYTES as u64) - num_bytes) as usize;
+        let mut block = [0_u8; BLOCK_NUM_BYTES];
+        let num_read = read_block(reader, &mut block[..num_padding_bytes])?;
+        if num_read < num_padding_bytes {
+            self.push(
+                Severity::Warning,
+                None,
+                "The data unit is not padded to a multiple of 2880 bytes".to_owned(),
+            );
+        }
+        if block[..num_read].iter().any(|&b| b != padding) {
+            let message = if padding == 0 {
+                "The padding of the data unit contains non zero bytes"
+            } else {
+                "The padding of the data unit contains non space bytes"
+            };
+            self.push(Severity::Error, None, message.to_owned());
+        }
+
+        Ok(num_read == num_padding_bytes)
+    }
+}
+
+/// Fill the buffer, returning fewer bytes than its size only when the end of the file is reached
+fn read_block<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut num_read = 0;
+    while num_read < buf.len() {
+        match reader.read(&mut buf[num_read..]) {
+            Ok(0) => break,
+            Ok(n) => num_read += n,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(err) => return Err(err.into()),
+        }
+    }
+
+    Ok(num_read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{validate, Severity};
+    use crate::mock::MockFits;
+    use std::io::Cursor;
+
+    fn mock_fits(headers: &[(&[&str], &[u8])]) -> Vec<u8> {
+        headers
+            .iter()
+            .fold(MockFits::default(), |fits, (cards, data)| {
+                fits.header(cards).data(data)
+            })
+            .build()
+    }
+
+    #[test]
+    fn test_valid_file() {
+        let bytes = mock_fits(&[
+            (
+                &[
+                    "SIMPLE  =                    T",
+                    "BITPIX  =                   16",
+                    "NAXIS   =                    2",
+                    "NAXIS1  =                    2",
+                    "NAXIS2  =                    2",
+                    "EXTEND  =                    T",
+                    "END",
+                ],
+                &[0, 1, 0, 2, 0, 3, 0, 4],
+            ),
+            (
+                &[
+                    "XTENSION= 'BINTABLE'",
+                    "BITPIX  =                    8",
+                    "NAXIS   =                    2",
+                    "NAXIS1  =                    6",
+                    "NAXIS2  =                    1",
+                    "PCOUNT  =                    0",
+                    "GCOUNT  =                    1",
+                    "TFIELDS =                    2",
+                    "TFORM1  = '1J      '",
+                    "TFORM2  = '2A      '",
+                    "END",
+                ],
+                &[0, 0, 0, 1, b'a', b'b'],
+            ),
+        ]);
+
+        assert_eq!(validate(Cursor::new(bytes)).unwrap(), []);
+    }
+
+    #[test]
+    fn test_findings() {
+        let mut bytes = mock_fits(&[
+            (
+                &[
+                    "SIMPLE  =                    T",
+                    "NAXIS   =                    2",
+                    "BITPIX  =                  -32",
+                    "NAXIS1  =                    1",
+                    "NAXIS2  =                    1",
+                    "EXTEND  =                    F",
+                    "BLANK   =                   -1",
+                    "OBJECT  = 'M31     '",
+                    "OBJECT  = 'M32     '",
+                    "lower   =                    1",
+                    "END",
+                ],
+                &[0, 0, 0, 0],
+            ),
+            (
+                &[
+                    "XTENSION= 'BINTABLE'",
+                    "BITPIX  =                    8",
+                    "NAXIS   =                    2",
+                    "NAXIS1  =                    8",
+                    "NAXIS2  =                    1",
+                    "PCOUNT  =                    0",
+                    "GCOUNT  =                    1",
+                    "TFIELDS =                    1",
+                    "TFORM1  = '1J      '",
+                    "END",
+                ],
+                &[0; 8],
+            ),
+        ]);
+        // An illegal character in a comment, and non zero padding bytes
+        bytes[7 * 80 + 40] = 0x09;
+        bytes[2880 + 4] = 1;
+
+        let findings = validate(Cursor::new(bytes))
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.severity, f.hdu_idx, f.card_idx))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            findings,
+            [
+                // Illegal character
+                (Severity::Error, 0, Some(7)),
+                // Duplicated OBJECT
+                (Severity::Warning, 0, Some(8)),
+                // Illegal keyword name
+                (Severity::Error, 0, Some(9)),
+                // BITPIX and NAXIS swapped
+                (Severity::Error, 0, Some(1)),
+                (Severity::Error, 0, Some(2)),
+                // BLANK in a floating point image
+                (Severity::Error, 0, Some(6)),
+                // Padding of the data unit
+                (Severity::Error, 0, None),
+                // TFORM1 does not fill NAXIS1
+                (Severity::Error, 1, Some(3)),
+                // EXTEND = F
+                (Severity::Warning, 0, Some(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data_unit_size_overflow() {
+        let bytes = mock_fits(&[(
+            &[
+                "SIMPLE  =                    T",
+                "BITPIX  =                  -64",
+                "NAXIS   =                    3",
+                "NAXIS1  =           4294967296",
+                "NAXIS2  =           4294967296",
+                "NAXIS3  =                    2",
+                "END",
+            ],
+            &[],
+        )]);
+
+        let findings = validate(Cursor::new(bytes)).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "The data unit size overflows, the checks stop here"
+        );
+
+        // The NAXISn keywords expected are bounded by the largest legal NAXIS
+        let bytes = mock_fits(&[(
+            &[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =        1000000000000",
+                "END",
+            ],
+            &[],
+        )]);
+
+        let findings = validate(Cursor::new(bytes)).unwrap();
+        assert!(findings
+            .iter()
+            .any(|f| f.message.starts_with("Illegal NAXIS value")));
+        assert!(findings
+            .iter()
+            .any(|f| f.message == "The mandatory keyword NAXIS999 is missing"));
+        assert!(!findings.iter().any(|f| f.message.contains("NAXIS1000")));
+
+        // Likewise for the TFORMn keywords, bounded by the largest legal TFIELDS
+        let bytes = mock_fits(&[
+            (
+                &[
+                    "SIMPLE  =                    T",
+                    "BITPIX  =                    8",
+                    "NAXIS   =                    0",
+                    "END",
+                ],
+                &[],
+            ),
+            (
+                &[
+                    "XTENSION= 'BINTABLE'",
+                    "BITPIX  =                    8",
+                    "NAXIS   =                    2",
+                    "NAXIS1  =                    0",
+                    "NAXIS2  =                    0",
+                    "PCOUNT  =                    0",
+                    "GCOUNT  =                    1",
+                    "TFIELDS =  9223372036854775807",
+                    "END",
+                ],
+                &[],
+            ),
+        ]);
+
+        let findings = validate(Cursor::new(bytes)).unwrap();
+        assert!(findings
+            .iter()
+            .any(|f| f.message.starts_with("Illegal TFIELDS value")));
+        assert!(findings
+            .iter()
+            .any(|f| f.message == "The keyword TFORM999 is missing"));
+        assert!(!findings.iter().any(|f| f.message.contains("TFORM1000")));
+    }
+
+    #[test]
+    fn test_missing_end() {
+        let bytes = mock_fits(&[(
+            &[
+                "SIMPLE  =                    T",
+                "BITPIX  =                    8",
+                "NAXIS   =                    0",
+            ],
+            &[],
+        )]);
+
+        let findings = validate(Cursor::new(bytes)).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert_eq!(findings[0].message, "The END card is missing");
+    }
+}
@@ -39,6 +39,11 @@ impl<'a> TryFrom<&'a Header<BinTable>> for WCS {
     type Error = Error;
 
     fn try_from(h: &'a Header<BinTable>) -> Result<Self, Self::Error> {
+        // The NAXISn keywords of the table must not be confused with the image ones
+        if h.get_xtension().get_z_image().is_some() {
+            return (&h.image_header()?).try_into();
+        }
+
         let params = WCSParams::deserialize(h.into_deserializer())?;
         WCS::new(&params).map_err(|e| e.into())
     }